@@ -0,0 +1,151 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use clap::Parser;
+use jeep::{
+    analysis::{bits::Candidate, BitTracker},
+    candump,
+    listener::Listener,
+};
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Find the bits that toggle when you do something",
+    long_about = "Find the bits that toggle when you do something. Live, \
+    press Enter as you do the action (eg. press the hazard button) and the \
+    bits that toggled around every mark so far are printed after --window. \
+    With a `candump -L` file, pass the times of the actions with --mark."
+)]
+struct Args {
+    /// Candump file to replay.
+    #[arg(short, long, conflicts_with = "device", requires = "mark")]
+    in_file: Option<String>,
+
+    /// Time(s) of the action in the --in-file, in seconds (as in the log).
+    #[arg(short, long)]
+    mark: Vec<f64>,
+
+    /// CAN interface to open (eg. "can0").
+    #[arg(short, long, required_unless_present = "in_file")]
+    device: Option<String>,
+
+    /// Seconds before or after a mark a bit can toggle and still count.
+    #[arg(short, long, default_value_t = 1.0)]
+    window: f64,
+
+    /// Number of candidates to print.
+    #[arg(short, long, default_value_t = 20)]
+    top: usize,
+
+    /// Print candidates as json lines instead of text.
+    #[arg(long)]
+    json: bool,
+}
+
+fn print(
+    candidates: &[Candidate],
+    args: &Args,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for candidate in candidates.iter().take(args.top) {
+        if args.json {
+            println!("{}", serde_json::to_string(candidate)?);
+        } else {
+            println!("{candidate}");
+        }
+    }
+
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let window = Duration::from_secs_f64(args.window);
+    let mut tracker = BitTracker::default();
+
+    if let Some(in_file) = &args.in_file {
+        let reader = BufReader::new(File::open(in_file)?);
+        for record in candump::records(reader) {
+            let record = match record {
+                Ok(record) => record,
+                Err(candump::Error::Io(err)) => return Err(err.into()),
+                // Eg. remote or CAN FD frames.
+                Err(err) => {
+                    eprintln!("skipping: {err}");
+                    continue;
+                }
+            };
+            tracker.update(&record.frame, record.timestamp);
+        }
+        let marks: Vec<Duration> = args
+            .mark
+            .iter()
+            .map(|&m| Duration::from_secs_f64(m))
+            .collect();
+        return print(&tracker.candidates(&marks, window), &args);
+    }
+
+    // Every line on stdin is a mark.
+    let (sender, marks_rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for _ in std::io::stdin().lock().lines() {
+            if sender.send(Instant::now()).is_err() {
+                return;
+            }
+        }
+    });
+
+    // `device` is required without `in_file`.
+    let listener = Listener::connect(args.device.as_ref().unwrap(), true)?;
+    let start = Instant::now();
+    let mut marks = Vec::new();
+    let mut pending: Option<Duration> = None;
+    println!("press Enter as you do the action");
+
+    for frame in listener.frames() {
+        let now = start.elapsed();
+        tracker.update(&frame?, now);
+
+        for mark in marks_rx.try_iter() {
+            let mark = mark.duration_since(start);
+            marks.push(mark);
+            pending = Some(mark);
+            println!("mark {} at {:.3}s", marks.len(), mark.as_secs_f64());
+        }
+        // Report once the window after the latest mark has passed.
+        if pending.is_some_and(|mark| now > mark + window) {
+            pending = None;
+            print(&tracker.candidates(&marks, window), &args)?;
+        }
+    }
+
+    // We should never actually reach here when the Listener is blocking.
+    panic!("`Frames` iterator broken -- yielded no frames")
+}
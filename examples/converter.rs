@@ -20,14 +20,55 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use jeep::Event;
+use jeep::{
+    candump,
+    frame::SerdeRepr,
+    stream::{self, Record},
+    table::{Column, Rows, Table, TableConfig},
+    Event, Frame,
+};
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use serde::{Deserialize, Serialize};
 use socketcan::CANFrame;
 
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::time::Duration;
+
+/// How frames (eg. in errors) are written.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Frames {
+    /// `{"can_id":1,"can_dlc":8,"data":[2,3,4,5,6,7,8,9]}`
+    Struct,
+    /// `{"id":"2FA","data":"0100000000000000"}` (json only)
+    Hex,
+    /// `"2FA#0100000000000000"`
+    Candump,
+}
+
+/// Output file format.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Format {
+    /// Json lines.
+    Json,
+    /// Length-delimited CBOR records (see `jeep::stream`).
+    Cbor,
+    /// Length-delimited MessagePack records (see `jeep::stream`).
+    Msgpack,
+    /// A CSV table with one column per decoded signal (see `jeep::table`).
+    Csv,
+}
+
+impl From<Frames> for SerdeRepr {
+    fn from(frames: Frames) -> Self {
+        match frames {
+            Frames::Struct => SerdeRepr::Struct,
+            Frames::Hex => SerdeRepr::Hex,
+            Frames::Candump => SerdeRepr::Candump,
+        }
+    }
+}
 
 #[derive(Parser, Debug)]
 #[command(
@@ -41,13 +82,42 @@ struct Args {
     #[arg(short, long)]
     in_file: String,
 
-    /// Json lines output file.
+    /// Output file (json lines unless `--format` says otherwise).
     #[arg(short, long)]
     out_file: String,
 
+    /// Output file format.
+    #[arg(long, value_enum, default_value_t = Format::Json)]
+    format: Format,
+
     /// IDs to filter by
     #[arg(short, long, value_parser=clap_num::maybe_hex::<u32>)]
     filters: Option<Vec<u32>>,
+
+    /// How frames are written.
+    #[arg(long, value_enum, default_value_t = Frames::Struct)]
+    frames: Frames,
+
+    /// With `--format csv`, the columns to write (default: all). One or
+    /// more of: rpm, approx_mph, mph, cabin_c, aux_volts, odometer_km, doors,
+    /// locks, ignition, parking_lights.
+    #[arg(long, value_delimiter = ',')]
+    columns: Vec<Column>,
+
+    /// With `--format csv`, write a row every this many seconds with the last
+    /// value of every column, instead of one row per event.
+    #[arg(long)]
+    resample: Option<f64>,
+
+    /// With `--format csv`, leave out rows before this time (seconds since
+    /// the Unix epoch, like candump timestamps).
+    #[arg(long)]
+    start: Option<f64>,
+
+    /// With `--format csv`, leave out rows after this time (seconds since
+    /// the Unix epoch, like candump timestamps).
+    #[arg(long)]
+    end: Option<f64>,
 }
 
 /// parse a candump (-L) line into (timestamp, interface, id, data)
@@ -113,23 +183,140 @@ where
     Ok(())
 }
 
+/// Write one [`Record`] per event, or one for the error.
+fn write_records<W>(
+    writer: &mut stream::Writer<W>,
+    frame: CANFrame,
+    timestamp: u128,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    W: std::io::Write,
+{
+    // candump timestamps are in microseconds.
+    let timestamp = Duration::from_micros(timestamp.try_into()?);
+
+    match Event::parse(frame) {
+        Ok(events) => {
+            for event in events {
+                writer.write(&Record::new(timestamp, event))?;
+            }
+        }
+        Err(jeep::events::CanFrameError::ParseError(err)) => {
+            writer.write(&Record::new(timestamp, err))?;
+        }
+        Err(err) => return Err(err.into()),
+    }
+
+    Ok(())
+}
+
+/// Write a CSV table of decoded signals from a candump file.
+fn write_csv<W>(
+    writer: &mut W,
+    in_file: File,
+    args: &Args,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    W: std::io::Write,
+{
+    let mut table = Table::new(TableConfig {
+        columns: if args.columns.is_empty() {
+            Column::ALL.to_vec()
+        } else {
+            args.columns.clone()
+        },
+        rows: match args.resample {
+            Some(secs) => Rows::Resample(Duration::try_from_secs_f64(secs)?),
+            None => Rows::Sparse,
+        },
+        start: args.start.map(Duration::try_from_secs_f64).transpose()?,
+        end: args.end.map(Duration::try_from_secs_f64).transpose()?,
+    });
+
+    writeln!(writer, "{}", table.header())?;
+    for record in candump::records(BufReader::new(in_file)) {
+        let record = match record {
+            Ok(record) => record,
+            Err(candump::Error::Io(err)) => return Err(err.into()),
+            // Eg. remote or CAN FD frames.
+            Err(err) => {
+                eprintln!("skipping: {err}");
+                continue;
+            }
+        };
+        if let Some(filters) = &args.filters {
+            if !filters.contains(&record.frame.id()) {
+                continue;
+            }
+        }
+        // Frames that aren't events have nothing to put in the table.
+        let Ok(events) = Event::parse(record.frame) else {
+            continue;
+        };
+        for event in events {
+            for row in table.update(&event, record.timestamp) {
+                writeln!(writer, "{row}")?;
+            }
+        }
+    }
+    if let Some(row) = table.finish() {
+        writeln!(writer, "{row}")?;
+    }
+
+    Ok(writer.flush()?)
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
-    let in_file = File::open(args.in_file)?;
-    let mut out_file = File::create(args.out_file)?;
+    if matches!(args.format, Format::Cbor | Format::Msgpack)
+        && matches!(args.frames, Frames::Hex)
+    {
+        // They could be written, but not read back.
+        return Err(
+            "`--frames hex` is only supported with `--format json`".into()
+        );
+    }
+
+    let in_file = File::open(&args.in_file)?;
+    let mut out_file = BufWriter::new(File::create(&args.out_file)?);
+
+    let format = match args.format {
+        Format::Json => None,
+        Format::Cbor => Some(stream::Format::Cbor),
+        Format::Msgpack => Some(stream::Format::MessagePack),
+        Format::Csv => return write_csv(&mut out_file, in_file, &args),
+    };
     let mut lines = BufReader::new(in_file).lines();
 
-    while let Some(Ok(line)) = lines.next() {
-        if let Some((timestamp, frame)) =
-            parse_candump_line(&line, &args.filters)
-        {
-            let result = Event::parse(frame);
-            write_json(&mut out_file, &result, timestamp)?;
+    Frame::with_serde_repr(args.frames.into(), || {
+        match format {
+            None => {
+                while let Some(Ok(line)) = lines.next() {
+                    if let Some((timestamp, frame)) =
+                        parse_candump_line(&line, &args.filters)
+                    {
+                        let result = Event::parse(frame);
+                        write_json(&mut out_file, &result, timestamp)?;
+                    }
+                }
+                out_file.flush()?;
+            }
+            Some(format) => {
+                let mut writer = stream::Writer::new(out_file, format);
+                while let Some(Ok(line)) = lines.next() {
+                    if let Some((timestamp, frame)) =
+                        parse_candump_line(&line, &args.filters)
+                    {
+                        write_records(&mut writer, frame, timestamp)?;
+                    }
+                }
+                writer.flush()?;
+            }
         }
-    }
 
-    Ok(())
+        Ok(())
+    })
 }
 
 #[cfg(test)]
@@ -0,0 +1,121 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use clap::{Parser, ValueEnum};
+use jeep::{
+    analysis::correlate::{signals, Correlator, CorrelatorConfig},
+    candump, Event,
+};
+
+use std::{fs::File, io::BufReader, time::Duration};
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Signal {
+    /// GPS corrected speed.
+    Mph,
+    /// Approximate speed.
+    ApproxMph,
+    /// Engine RPMs.
+    Rpms,
+    /// Cabin temperature (Celsius).
+    CabinTemperature,
+}
+
+impl Signal {
+    fn get(self) -> fn(&Event) -> Option<f64> {
+        match self {
+            Signal::Mph => signals::mph,
+            Signal::ApproxMph => signals::approx_mph,
+            Signal::Rpms => signals::rpms,
+            Signal::CabinTemperature => signals::cabin_celsius,
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Find bytes that correlate with a decoded signal",
+    long_about = "Find bytes and words (as u8, i8, and u16/i16 in either \
+    byte order) in a `candump -L` file that correlate with a decoded signal, \
+    with a proposed scaling for a new decoder."
+)]
+struct Args {
+    /// Candump file to read.
+    #[arg(short, long)]
+    in_file: String,
+
+    /// The decoded signal to correlate with.
+    #[arg(short, long, value_enum)]
+    signal: Signal,
+
+    /// Candidate IDs (default: all)
+    #[arg(long, value_parser=clap_num::maybe_hex::<u32>)]
+    ids: Vec<u32>,
+
+    /// Seconds a signal value stays valid for.
+    #[arg(long, default_value_t = 1.0)]
+    max_age: f64,
+
+    /// Number of results to print.
+    #[arg(short, long, default_value_t = 20)]
+    top: usize,
+
+    /// Print results as json lines instead of text.
+    #[arg(long)]
+    json: bool,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let config = CorrelatorConfig {
+        ids: (!args.ids.is_empty()).then(|| args.ids.clone()),
+        max_age: Duration::try_from_secs_f64(args.max_age)?,
+        ..Default::default()
+    };
+    let mut correlator = Correlator::new(config, args.signal.get());
+
+    let reader = BufReader::new(File::open(&args.in_file)?);
+    for record in candump::records(reader) {
+        let record = match record {
+            Ok(record) => record,
+            Err(candump::Error::Io(err)) => return Err(err.into()),
+            // Eg. remote or CAN FD frames.
+            Err(err) => {
+                eprintln!("skipping: {err}");
+                continue;
+            }
+        };
+        correlator.update(&record.frame, record.timestamp);
+    }
+
+    for result in correlator.results().iter().take(args.top) {
+        if args.json {
+            println!("{}", serde_json::to_string(result)?);
+        } else {
+            println!("{result}");
+        }
+    }
+
+    Ok(())
+}
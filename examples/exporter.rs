@@ -0,0 +1,634 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use clap::Parser;
+use jeep::{
+    candump,
+    export::{Influx, InfluxConfig, Metrics},
+    listener::Listener,
+    Frame, Timestamp,
+};
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{
+            sync_channel, Receiver, RecvTimeoutError, SyncSender, TrySendError,
+        },
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Pause between polls of the (non-blocking) listener.
+const PAUSE: Duration = Duration::from_micros(8333);
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Export Jeep events as InfluxDB line protocol and Prometheus metrics",
+    long_about = "Decode a CAN interface or `candump -L` file and write the \
+    events as InfluxDB line protocol (to stdout, a file or an HTTP write \
+    endpoint) and/or serve the latest values and frame counters on a \
+    Prometheus `/metrics` endpoint."
+)]
+struct Args {
+    /// Candump file to replay.
+    #[arg(short, long, conflicts_with = "device")]
+    in_file: Option<String>,
+
+    /// CAN interface to open (eg. "can0").
+    #[arg(short, long, required_unless_present = "in_file")]
+    device: Option<String>,
+
+    /// Replay speed (2 is twice as fast). 0 replays as fast as possible.
+    #[arg(short, long, default_value_t = 0.0)]
+    speed: f64,
+
+    /// Where to write line protocol: `-` for stdout, an `http://` write url
+    /// (eg. `http://localhost:8086/api/v2/write?org=o&bucket=b`) or a file.
+    #[arg(long)]
+    influx: Option<String>,
+
+    /// With an `http://` `--influx`, the token to authorize with.
+    #[arg(long)]
+    token: Option<String>,
+
+    /// With an `http://` `--influx`, lines per request.
+    #[arg(long, default_value_t = 500)]
+    batch: usize,
+
+    /// Measurement name.
+    #[arg(long, default_value = "jeep")]
+    measurement: String,
+
+    /// Extra tags for every line (eg. `vehicle=blue`).
+    #[arg(long, value_parser = parse_tag)]
+    tag: Vec<(String, String)>,
+
+    /// Serve Prometheus metrics on this address (eg. `0.0.0.0:9898`).
+    #[arg(long)]
+    metrics: Option<String>,
+}
+
+fn parse_tag(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => {
+            Ok((key.to_owned(), value.to_owned()))
+        }
+        _ => Err(format!("expected `key=value`, got `{s}`")),
+    }
+}
+
+/// An `http://host[:port]/path` url.
+#[derive(Debug, PartialEq)]
+struct HttpUrl {
+    /// `host:port`
+    addr: String,
+    /// `host[:port]` as given, for the `Host` header.
+    host: String,
+    /// Path and query.
+    path: String,
+}
+
+impl std::str::FromStr for HttpUrl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s
+            .strip_prefix("http://")
+            .ok_or_else(|| format!("only http:// urls are supported: `{s}`"))?;
+        let (host, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        if host.is_empty() {
+            return Err(format!("no host in `{s}`"));
+        }
+        let addr = if host.contains(':') {
+            host.to_owned()
+        } else {
+            format!("{host}:80")
+        };
+        Ok(Self {
+            addr,
+            host: host.to_owned(),
+            path: path.to_owned(),
+        })
+    }
+}
+
+/// How long to wait for the write endpoint to accept a connection or
+/// respond.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Why a POST failed.
+#[derive(Debug)]
+enum PostError {
+    /// The endpoint rejected the lines (a 4xx, eg. malformed lines), so
+    /// sending them again won't help.
+    Rejected(String),
+    /// Anything else (eg. the endpoint is down). Worth retrying.
+    Failed(io::Error),
+}
+
+impl std::fmt::Display for PostError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PostError::Rejected(status) => write!(f, "rejected: {status}"),
+            PostError::Failed(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl From<io::Error> for PostError {
+    fn from(err: io::Error) -> Self {
+        PostError::Failed(err)
+    }
+}
+
+/// Connect to `addr` (`host:port`), giving up on each address after
+/// [`TIMEOUT`].
+fn connect(addr: &str) -> io::Result<TcpStream> {
+    let mut last = None;
+    for addr in addr.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last = Some(err),
+        }
+    }
+    Err(last.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no address for {addr}"),
+        )
+    }))
+}
+
+/// POST `body` to `url`, failing unless the response is a 2xx.
+fn post(
+    url: &HttpUrl,
+    token: Option<&str>,
+    body: &str,
+) -> Result<(), PostError> {
+    let mut stream = connect(&url.addr)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let auth = match token {
+        Some(token) => format!("Authorization: Token {token}\r\n"),
+        None => String::new(),
+    };
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}\r\n{auth}\
+        Content-Type: text/plain; charset=utf-8\r\n\
+        Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        url.path,
+        url.host,
+        body.len(),
+    )?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let status = response.lines().next().unwrap_or_default();
+    match status.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(()),
+        Some(code) if code.starts_with('4') => {
+            Err(PostError::Rejected(status.to_owned()))
+        }
+        _ => Err(PostError::Failed(io::Error::other(format!(
+            "influx write failed: {status}"
+        )))),
+    }
+}
+
+/// Batches kept for retrying while the write endpoint is failing. Beyond
+/// that, the oldest batches are dropped.
+const MAX_BATCHES: usize = 10;
+
+/// How long to wait before retrying a failed batch, if no new batch comes
+/// along first.
+const RETRY: Duration = Duration::from_secs(5);
+
+/// Lines of line protocol to POST together.
+#[derive(Debug, Default)]
+struct Batch {
+    body: String,
+    lines: u64,
+}
+
+/// POSTs [`Batch`]es from a worker thread, so a slow or unreachable write
+/// endpoint never holds up reading frames.
+struct Poster {
+    batch: u64,
+    current: Batch,
+    tx: SyncSender<Batch>,
+    worker: JoinHandle<()>,
+    /// Lines dropped because the endpoint rejected them or kept failing.
+    dropped: Arc<AtomicU64>,
+}
+
+impl Poster {
+    fn new(url: HttpUrl, token: Option<String>, batch: usize) -> Self {
+        let (tx, rx) = sync_channel(MAX_BATCHES);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let worker = {
+            let dropped = dropped.clone();
+            std::thread::spawn(move || {
+                Self::run(&url, token.as_deref(), &rx, &dropped)
+            })
+        };
+        Self {
+            batch: batch.max(1) as u64,
+            current: Batch::default(),
+            tx,
+            worker,
+            dropped,
+        }
+    }
+
+    /// The worker: POST batches in order, keeping up to [`MAX_BATCHES`] to
+    /// retry while the endpoint is failing.
+    fn run(
+        url: &HttpUrl,
+        token: Option<&str>,
+        rx: &Receiver<Batch>,
+        dropped: &AtomicU64,
+    ) {
+        let drop_batch = |batch: Batch| {
+            dropped.fetch_add(batch.lines, Ordering::Relaxed);
+        };
+        let mut pending = VecDeque::new();
+        let mut open = true;
+
+        while open || !pending.is_empty() {
+            let received = if pending.is_empty() {
+                rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
+            } else if open {
+                rx.recv_timeout(RETRY)
+            } else {
+                Err(RecvTimeoutError::Disconnected)
+            };
+            match received {
+                Ok(batch) => {
+                    pending.push_back(batch);
+                    if pending.len() > MAX_BATCHES {
+                        pending.pop_front().map(drop_batch);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => open = false,
+            }
+
+            while let Some(batch) = pending.front() {
+                match post(url, token, &batch.body) {
+                    Ok(()) => {
+                        pending.pop_front();
+                    }
+                    Err(err @ PostError::Rejected(_)) => {
+                        eprintln!("influx: {err} ({} lines)", batch.lines);
+                        pending.pop_front().map(drop_batch);
+                    }
+                    Err(err) => {
+                        eprintln!("influx: {err}");
+                        if !open {
+                            // Last try on exit.
+                            pending.drain(..).for_each(drop_batch);
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    fn write(&mut self, line: &str) {
+        self.current.body.push_str(line);
+        self.current.body.push('\n');
+        self.current.lines += 1;
+        if self.current.lines >= self.batch {
+            self.flush();
+        }
+    }
+
+    /// Hand the current batch to the worker, dropping it if the worker is
+    /// too far behind.
+    fn flush(&mut self) {
+        if self.current.lines == 0 {
+            return;
+        }
+        let batch = std::mem::take(&mut self.current);
+        if let Err(
+            TrySendError::Full(batch) | TrySendError::Disconnected(batch),
+        ) = self.tx.try_send(batch)
+        {
+            self.dropped.fetch_add(batch.lines, Ordering::Relaxed);
+        }
+    }
+
+    /// Send what's left and wait for the worker to finish, returning how
+    /// many lines were dropped.
+    fn finish(mut self) -> u64 {
+        self.flush();
+        drop(self.tx);
+        if self.worker.join().is_err() {
+            eprintln!("influx: worker panicked");
+        }
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// Where line protocol goes.
+enum Sink {
+    Writer(Box<dyn Write>),
+    Http(Poster),
+}
+
+impl Sink {
+    fn new(
+        target: &str,
+        args: &Args,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(if target == "-" {
+            Sink::Writer(Box::new(io::stdout().lock()))
+        } else if target.starts_with("http://") {
+            Sink::Http(Poster::new(
+                target.parse()?,
+                args.token.clone(),
+                args.batch,
+            ))
+        } else {
+            Sink::Writer(Box::new(BufWriter::new(File::create(target)?)))
+        })
+    }
+
+    /// Write a `line`. With an HTTP endpoint, lines are posted in batches
+    /// from a worker thread, so this never blocks on the network.
+    fn write(&mut self, line: &str) -> io::Result<()> {
+        match self {
+            Sink::Writer(writer) => writeln!(writer, "{line}"),
+            Sink::Http(poster) => {
+                poster.write(line);
+                Ok(())
+            }
+        }
+    }
+
+    /// Write everything pending, returning how many lines were dropped
+    /// because the HTTP endpoint rejected them or kept failing.
+    fn finish(self) -> io::Result<u64> {
+        match self {
+            Sink::Writer(mut writer) => writer.flush().map(|()| 0),
+            Sink::Http(poster) => Ok(poster.finish()),
+        }
+    }
+}
+
+/// Serve `metrics` on `addr` from a background thread.
+fn serve_metrics(
+    addr: &str,
+    metrics: Arc<Mutex<Metrics>>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let server = tiny_http::Server::http(addr)?;
+    let content_type = tiny_http::Header::from_bytes(
+        "Content-Type",
+        "text/plain; version=0.0.4; charset=utf-8",
+    )
+    .expect("valid header");
+    std::thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let response = if request.url() == "/metrics" {
+                let text = metrics.lock().expect("poisoned").to_string();
+                tiny_http::Response::from_string(text)
+                    .with_header(content_type.clone())
+            } else {
+                tiny_http::Response::from_string("not found\n")
+                    .with_status_code(404)
+            };
+            if let Err(err) = request.respond(response) {
+                eprintln!("metrics: {err}");
+            }
+        }
+    });
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let influx = Influx::new(InfluxConfig {
+        measurement: args.measurement.clone(),
+        tags: args.tag.iter().cloned().collect::<BTreeMap<_, _>>(),
+    });
+    let mut sink = match &args.influx {
+        Some(target) => Some(Sink::new(target, &args)?),
+        None => None,
+    };
+    let metrics = Arc::new(Mutex::new(Metrics::new()));
+    if let Some(addr) = &args.metrics {
+        serve_metrics(addr, metrics.clone()).map_err(|err| err.to_string())?;
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let handler_stop = stop.clone();
+    ctrlc::set_handler(move || handler_stop.store(true, Ordering::Relaxed))
+        .expect("Error setting Ctrl-C handler");
+
+    let mut handle = |frame: &Frame, at: Timestamp| -> io::Result<()> {
+        let events = metrics.lock().expect("poisoned").update(frame, at);
+        if let Some(sink) = &mut sink {
+            for event in events {
+                if let Some(line) = influx.line(&event, at) {
+                    sink.write(&line)?;
+                }
+            }
+        }
+        Ok(())
+    };
+
+    if let Some(path) = &args.in_file {
+        let start = Instant::now();
+        let mut first = None;
+        for record in candump::records(BufReader::new(File::open(path)?)) {
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            let record = match record {
+                Ok(record) => record,
+                Err(candump::Error::Io(err)) => return Err(err.into()),
+                // Eg. remote or CAN FD frames.
+                Err(err) => {
+                    eprintln!("skipping: {err}");
+                    continue;
+                }
+            };
+            // Keep the log's timestamps, so the data lines up with when it
+            // was recorded.
+            let first = *first.get_or_insert(record.timestamp);
+            if args.speed > 0.0 {
+                let due =
+                    record.timestamp.saturating_sub(first).div_f64(args.speed);
+                if let Some(wait) = due.checked_sub(start.elapsed()) {
+                    std::thread::sleep(wait);
+                }
+            }
+            handle(&record.frame, record.timestamp)?;
+        }
+    } else if let Some(device) = &args.device {
+        // Non-blocking and polled, so ctrl+c works on a quiet bus.
+        let listener = Listener::connect(device, false)?;
+        while !stop.load(Ordering::Relaxed) {
+            let loop_start = Instant::now();
+            for frame in listener.frames() {
+                match frame {
+                    Ok(frame) => handle(
+                        &frame,
+                        SystemTime::now().duration_since(UNIX_EPOCH)?,
+                    )?,
+                    Err(err) => eprintln!("{err}"),
+                }
+            }
+            let elapsed = loop_start.elapsed();
+            if elapsed < PAUSE {
+                std::thread::sleep(PAUSE - elapsed);
+            }
+        }
+    }
+    if let Some(sink) = sink {
+        let dropped = sink.finish()?;
+        if dropped > 0 {
+            eprintln!("influx: dropped {dropped} lines");
+        }
+    }
+
+    // Keep serving the final values until ctrl+c.
+    if args.metrics.is_some() && !stop.load(Ordering::Relaxed) {
+        eprintln!("End of input. Serving metrics until ctrl+c.");
+        while !stop.load(Ordering::Relaxed) {
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_url() {
+        let url: HttpUrl = "http://localhost:8086/api/v2/write?bucket=b"
+            .parse()
+            .unwrap();
+        assert_eq!(url.addr, "localhost:8086");
+        assert_eq!(url.path, "/api/v2/write?bucket=b");
+        let url: HttpUrl = "http://influx".parse().unwrap();
+        assert_eq!((url.addr.as_str(), url.path.as_str()), ("influx:80", "/"));
+        assert!("https://influx".parse::<HttpUrl>().is_err());
+    }
+
+    fn poster(addr: impl std::fmt::Display, batch: usize) -> Poster {
+        let url = format!("http://{addr}/api/v2/write?bucket=jeep")
+            .parse()
+            .unwrap();
+        Poster::new(url, Some("secret".to_owned()), batch)
+    }
+
+    #[test]
+    fn test_post_to_stand_in() {
+        // A stand-in for InfluxDB that accepts a write, fails the next, accepts
+        // the retry and then rejects malformed lines.
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr().to_ip().unwrap();
+        let stand_in = std::thread::spawn(move || {
+            let mut writes = Vec::new();
+            for status in [204, 503, 204, 400] {
+                let mut request = server.recv().unwrap();
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                let auth = request
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv("Authorization"))
+                    .map(|h| h.value.to_string());
+                writes.push((request.url().to_owned(), auth, body));
+                request.respond(tiny_http::Response::empty(status)).unwrap();
+            }
+            writes
+        });
+
+        let mut poster = poster(addr, 2);
+        poster.write("jeep,kind=doors doors=1i 1");
+        // The second line fills the batch and is posted.
+        poster.write("jeep,kind=doors doors=0i 2");
+        poster.write("jeep,kind=engine mph=30 3");
+        // Fails with a 503 and is retried with the next batch.
+        poster.flush();
+        poster.write("jeep,kind=engine mph=31 4");
+        // Rejected with a 400, so it's dropped rather than retried.
+        poster.write("jeep,kind=engine mph=32 5");
+        assert_eq!(poster.finish(), 2);
+
+        let writes = stand_in.join().unwrap();
+        assert_eq!(
+            writes[0],
+            (
+                "/api/v2/write?bucket=jeep".to_owned(),
+                Some("Token secret".to_owned()),
+                "jeep,kind=doors doors=1i 1\njeep,kind=doors doors=0i 2\n"
+                    .to_owned()
+            )
+        );
+        assert_eq!(writes[1].2, "jeep,kind=engine mph=30 3\n");
+        assert_eq!(writes[2].2, "jeep,kind=engine mph=30 3\n");
+        assert_eq!(
+            writes[3].2,
+            "jeep,kind=engine mph=31 4\njeep,kind=engine mph=32 5\n"
+        );
+    }
+
+    #[test]
+    fn test_endpoint_down() {
+        // Nothing is listening on this port once the listener is dropped.
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let mut poster = poster(addr, 1);
+        let start = Instant::now();
+        for i in 0..MAX_BATCHES * 3 {
+            poster.write(&format!("jeep,kind=engine mph={i} {i}"));
+        }
+        // Writing never waits on the network.
+        assert!(start.elapsed() < RETRY);
+        // Every line is either dropped along the way or on exit.
+        assert_eq!(poster.finish(), MAX_BATCHES as u64 * 3);
+    }
+}
@@ -21,7 +21,11 @@
 // SOFTWARE.
 
 use clap::Parser;
-use jeep::listener::{Error, Listener, Message};
+use jeep::{
+    listener::{Error, Listener, Message},
+    on_change::{ChangeFilter, OnChangeConfig},
+    stream::{self, Record},
+};
 use serde::{Deserialize, Serialize};
 
 use std::{
@@ -37,6 +41,35 @@ const PAUSE: std::time::Duration = std::time::Duration::from_nanos(8330000);
 enum DumpFormat {
     /// Json lines, including timestamp.
     Json,
+    /// Length-delimited CBOR records (see `jeep::stream`).
+    Cbor,
+    /// Length-delimited MessagePack records (see `jeep::stream`).
+    Msgpack,
+}
+
+/// An open dump file.
+enum Dump {
+    Json(File),
+    Stream(stream::Writer<File>),
+}
+
+impl Dump {
+    fn create(
+        filename: &str,
+        format: DumpFormat,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = File::create(filename)?;
+        Ok(match format {
+            DumpFormat::Json => Dump::Json(file),
+            DumpFormat::Cbor => {
+                Dump::Stream(stream::Writer::new(file, stream::Format::Cbor))
+            }
+            DumpFormat::Msgpack => Dump::Stream(stream::Writer::new(
+                file,
+                stream::Format::MessagePack,
+            )),
+        })
+    }
 }
 
 #[derive(Parser, Debug)]
@@ -53,9 +86,27 @@ struct Args {
     /// Print ERRORs as well as OK results.
     #[arg(short, long)]
     verbose: bool,
-    /// Dump frames to this file as json lines.
+    /// Dump frames to this file as json lines (or `--dump-format`).
     #[arg(long)]
     dump: Option<String>,
+    /// Format of the `--dump` file.
+    #[arg(long, value_enum, default_value_t = DumpFormat::Json)]
+    dump_format: DumpFormat,
+    /// Only output events that differ from the last one from the same source.
+    #[arg(long)]
+    on_change: bool,
+    /// With --on-change, output unchanged events again after this many
+    /// seconds.
+    #[arg(long)]
+    heartbeat: Option<u64>,
+    /// With --on-change, ignore cabin temperature changes of this many degrees
+    /// Celsius or less.
+    #[arg(long, default_value_t = 0.0)]
+    temperature_deadband: f32,
+    /// With --on-change, ignore aux battery voltage changes of this many volts
+    /// or less.
+    #[arg(long, default_value_t = 0.0)]
+    volts_deadband: f32,
 }
 
 fn ns_since_unix_epoch() -> Result<u128, Box<dyn std::error::Error>> {
@@ -109,6 +160,28 @@ where
     Ok(())
 }
 
+/// Write a [`Message`] as a [`Record`]. Errors other than [`Error::ParseError`]
+/// have no [`Record`] form and are skipped.
+fn write_record<W>(
+    writer: &mut stream::Writer<W>,
+    message: Message,
+    timestamp: u128,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    W: std::io::Write,
+{
+    let timestamp = std::time::Duration::from_nanos(timestamp.try_into()?);
+    match message {
+        Ok(event) => writer.write(&Record::new(timestamp, event))?,
+        Err(Error::ParseError(err)) => {
+            writer.write(&Record::new(timestamp, err))?
+        }
+        Err(err) => eprintln!("{timestamp:?}: not dumped: {err}"),
+    }
+
+    Ok(())
+}
+
 /// Print a [`Message`] (an [`jeep::Event`] or [`Error`])
 fn print_message(timestamp: u128, message: &Message, verbose: bool) {
     match message {
@@ -121,18 +194,18 @@ fn print_message(timestamp: u128, message: &Message, verbose: bool) {
     }
 }
 
-fn handle_message<W>(
-    writer: &mut W,
+fn handle_message(
+    dump: &mut Dump,
     message: Message,
     verbose: bool,
-) -> Result<(), Box<dyn std::error::Error>>
-where
-    W: std::io::Write,
-{
+) -> Result<(), Box<dyn std::error::Error>> {
     let timestamp = ns_since_unix_epoch()?;
 
     print_message(timestamp, &message, verbose);
-    write_json(writer, &message, timestamp)?;
+    match dump {
+        Dump::Json(file) => write_json(file, &message, timestamp)?,
+        Dump::Stream(writer) => write_record(writer, message, timestamp)?,
+    }
 
     Ok(())
 }
@@ -141,12 +214,20 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
     // open dump file
     let mut dump = match args.dump {
-        Some(filename) => Some(File::create(filename)?),
+        Some(filename) => Some(Dump::create(&filename, args.dump_format)?),
         None => None,
     };
     // listener in non-blocking mode should be polled peridically for pending
     // listener.messages().
-    let listener = Listener::connect(&args.device, false)?;
+    let mut listener = Listener::connect(&args.device, false)?;
+    if args.on_change {
+        listener =
+            listener.with_change_filter(ChangeFilter::new(OnChangeConfig {
+                heartbeat: args.heartbeat.map(std::time::Duration::from_secs),
+                temperature_deadband: args.temperature_deadband,
+                volts_deadband: args.volts_deadband,
+            }));
+    }
     // A channel to connect the ctrl+c signal handler thread to the main loop.
     let (tx, rx) = sync_channel(0);
     ctrlc::set_handler(move || tx.send(()).expect("rx disconnected somehow."))
@@ -0,0 +1,163 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use clap::Parser;
+use jeep::{
+    candump,
+    listener::Listener,
+    mqtt::{Bridge, MqttConfig},
+    Event, Frame, Timestamp,
+};
+
+use std::{
+    fs::File,
+    io::BufReader,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Pause between polls of the (non-blocking) listener.
+const PAUSE: Duration = Duration::from_micros(8333);
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Publish Jeep events and state to an MQTT broker",
+    long_about = "Decode a CAN interface or `candump -L` file and publish \
+    every event as json to `<prefix>/<name>/<type>`, the latest state to \
+    retained `<prefix>/<name>/state/<field>` topics and the bridge's status \
+    to `<prefix>/<name>/status`. See `jeep::mqtt` for the config file."
+)]
+struct Args {
+    /// TOML config file (see `jeep::mqtt::MqttConfig`). Defaults are used
+    /// without one.
+    #[arg(short, long)]
+    config: Option<String>,
+
+    /// The vehicle's VIN or name, used in topics (overrides the config).
+    #[arg(short, long)]
+    name: Option<String>,
+
+    /// Candump file to replay.
+    #[arg(short, long, conflicts_with = "device")]
+    in_file: Option<String>,
+
+    /// CAN interface to open (eg. "can0").
+    #[arg(short, long, required_unless_present = "in_file")]
+    device: Option<String>,
+
+    /// Replay speed (2 is twice as fast). 0 replays as fast as possible.
+    #[arg(short, long, default_value_t = 1.0)]
+    speed: f64,
+
+    /// Seconds to wait for buffered messages on exit.
+    #[arg(long, default_value_t = 5.0)]
+    linger: f64,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let linger = Duration::try_from_secs_f64(args.linger)?;
+
+    let mut config = match &args.config {
+        Some(path) => MqttConfig::load(path)?,
+        None => MqttConfig::default(),
+    };
+    if let Some(name) = &args.name {
+        config.name = name.clone();
+    }
+    let mut bridge = Bridge::connect(config)?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let handler_stop = stop.clone();
+    ctrlc::set_handler(move || handler_stop.store(true, Ordering::Relaxed))
+        .expect("Error setting Ctrl-C handler");
+
+    let mut publish = |frame: Frame, at: Timestamp| {
+        // Frames that aren't events have nothing to publish.
+        if let Ok(events) = Event::parse(frame) {
+            for event in events {
+                bridge.publish(&event, at);
+            }
+        }
+    };
+
+    if let Some(path) = &args.in_file {
+        let start = Instant::now();
+        let mut first = None;
+        for record in candump::records(BufReader::new(File::open(path)?)) {
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            let record = match record {
+                Ok(record) => record,
+                Err(candump::Error::Io(err)) => return Err(err.into()),
+                // Eg. remote or CAN FD frames.
+                Err(err) => {
+                    eprintln!("skipping: {err}");
+                    continue;
+                }
+            };
+            let first = *first.get_or_insert(record.timestamp);
+            if args.speed > 0.0 {
+                let due =
+                    record.timestamp.saturating_sub(first).div_f64(args.speed);
+                if let Some(wait) = due.checked_sub(start.elapsed()) {
+                    std::thread::sleep(wait);
+                }
+            }
+            publish(record.frame, record.timestamp);
+        }
+    } else if let Some(device) = &args.device {
+        // Non-blocking and polled, so ctrl+c works on a quiet bus.
+        let listener = Listener::connect(device, false)?;
+        while !stop.load(Ordering::Relaxed) {
+            let loop_start = Instant::now();
+            for frame in listener.frames() {
+                match frame {
+                    Ok(frame) => publish(
+                        frame,
+                        SystemTime::now().duration_since(UNIX_EPOCH)?,
+                    ),
+                    Err(err) => eprintln!("{err}"),
+                }
+            }
+            let elapsed = loop_start.elapsed();
+            if elapsed < PAUSE {
+                std::thread::sleep(PAUSE - elapsed);
+            }
+        }
+    }
+
+    if bridge.dropped() > 0 {
+        eprintln!("{} messages were dropped (buffer full)", bridge.dropped());
+    }
+    if !bridge.disconnect(linger) {
+        eprintln!("Timed out sending buffered messages.");
+    }
+
+    Ok(())
+}
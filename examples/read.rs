@@ -0,0 +1,105 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use jeep::{
+    frame::SerdeRepr,
+    stream::{self, Reader, Record},
+    Frame,
+};
+
+use clap::{Parser, ValueEnum};
+
+use std::fs::File;
+use std::io::BufReader;
+
+/// Input file format.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Format {
+    /// Length-delimited CBOR records.
+    Cbor,
+    /// Length-delimited MessagePack records.
+    Msgpack,
+}
+
+/// How frames were written (must match the writer). Hex frames aren't
+/// supported in binary streams.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Frames {
+    Struct,
+    Candump,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Print a CBOR or MessagePack record stream as json lines",
+    long_about = None
+)]
+struct Args {
+    /// Record stream written by `jeep-converter` or `jeep-listen`.
+    #[arg(short, long)]
+    in_file: String,
+
+    /// Input file format.
+    #[arg(long, value_enum)]
+    format: Format,
+
+    /// How frames were written.
+    #[arg(long, value_enum, default_value_t = Frames::Struct)]
+    frames: Frames,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let format = match args.format {
+        Format::Cbor => stream::Format::Cbor,
+        Format::Msgpack => stream::Format::MessagePack,
+    };
+    let repr = match args.frames {
+        Frames::Struct => SerdeRepr::Struct,
+        Frames::Candump => SerdeRepr::Candump,
+    };
+
+    let file = BufReader::new(File::open(args.in_file)?);
+    let mut reader: Reader<_, Record> = Reader::new(file, format);
+
+    Frame::with_serde_repr(repr, || {
+        for record in reader.by_ref() {
+            match record {
+                Ok(record) => println!("{}", serde_json::to_string(&record)?),
+                // The next record may still be fine.
+                Err(err @ stream::Error::Decode(_)) => eprintln!("{err}"),
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok::<_, Box<dyn std::error::Error>>(())
+    })?;
+
+    if reader.is_truncated() {
+        eprintln!("warning: the last record was cut off");
+    }
+
+    Ok(())
+}
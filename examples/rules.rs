@@ -0,0 +1,137 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use clap::Parser;
+use jeep::{
+    listener::{Error, Listener},
+    rules::Rules,
+};
+
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
+};
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Run commands or write logs based on rules from a TOML file",
+    long_about = "Run commands or write logs based on rules from a TOML \
+    file. The rules are validated on startup and reloaded on SIGHUP. If the \
+    reloaded rules are invalid, the old rules are kept."
+)]
+struct Args {
+    /// CAN interface to open (eg. "can0").
+    #[arg(short, long, required_unless_present = "check")]
+    device: Option<String>,
+
+    /// Path to the rules file.
+    #[arg(short, long)]
+    rules: PathBuf,
+
+    /// Validate the rules file and exit.
+    #[arg(long)]
+    check: bool,
+}
+
+/// Set by the SIGHUP handler.
+static RELOAD: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_sighup(_: libc::c_int) {
+    RELOAD.store(true, Ordering::Relaxed);
+}
+
+/// Install the SIGHUP handler. `SA_RESTART` is deliberately *not* set so a
+/// blocking read is interrupted and the rules are reloaded right away, rather
+/// than on the next frame.
+fn install_sighup_handler() -> std::io::Result<()> {
+    // SAFETY: the handler only stores to an atomic, which is signal safe, and
+    // `action` is fully initialized before use.
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction =
+            on_sighup as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        libc::sigemptyset(&mut action.sa_mask);
+        if libc::sigaction(libc::SIGHUP, &action, std::ptr::null_mut()) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    // Validate up front, so a bad rules file fails fast.
+    let mut rules = Rules::load(&args.rules)?;
+    println!("loaded {} rules from {:?}", rules.rules().len(), args.rules);
+    if args.check {
+        return Ok(());
+    }
+
+    install_sighup_handler()?;
+    // `device` is required without `--check`.
+    let listener = Listener::connect(&args.device.unwrap(), true)?;
+    let epoch = Instant::now();
+
+    for message in listener.messages() {
+        if RELOAD.swap(false, Ordering::Relaxed) {
+            match Rules::load(&args.rules) {
+                Ok(new) => {
+                    println!(
+                        "reloaded {} rules from {:?}",
+                        new.rules().len(),
+                        args.rules
+                    );
+                    rules.reload(new);
+                }
+                Err(e) => eprintln!("keeping old rules: {e}"),
+            }
+        }
+
+        match message {
+            Ok(event) => {
+                for rule in rules.process(&event, epoch.elapsed()) {
+                    if let Err(e) = rule.action.execute(&rule.name, &event) {
+                        eprintln!("rule `{}` failed: {e}", rule.name);
+                    }
+                }
+            }
+            // Interrupted by a signal (probably SIGHUP), so carry on.
+            Err(Error::IoError(e))
+                if e.kind() == std::io::ErrorKind::Interrupted =>
+            {
+                continue
+            }
+            // Any other IO Error, so we probably want to exit.
+            Err(Error::IoError(e)) => return Err(Box::new(e)),
+            // ignore anything else (eg. ParseError)
+            _ => continue,
+        }
+    }
+
+    // We should never actually reach here when the Listener is blocking.
+    panic!("`Messages` iterator broken -- yielded no messages")
+}
@@ -0,0 +1,375 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use clap::Parser;
+use jeep::{
+    analysis::{sniffer::SnifferConfig, Sniffer},
+    candump,
+    events::{EventKind, EventKinds},
+    listener::Listener,
+    Frame, Timestamp,
+};
+use ratatui::{
+    crossterm::event::{
+        self, Event as TermEvent, KeyCode, KeyEventKind, KeyModifiers,
+    },
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Cell, Row as TableRow, Table, TableState},
+    DefaultTerminal,
+};
+
+use std::{
+    fs::File,
+    io::BufReader,
+    sync::mpsc::{sync_channel, Receiver, SyncSender, TryRecvError},
+    time::{Duration, Instant},
+};
+
+/// How often the screen is redrawn (and keys are polled).
+const REDRAW: Duration = Duration::from_millis(50);
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "A cansniffer-like view of a CAN interface or `candump -L` file",
+    long_about = "Show one row per ID with the latest data, the decoded \
+    events and the frame rate. Bytes that recently changed are highlighted. \
+    Keys: q quit, space pause, up/down select, m mark, f freeze, c clear."
+)]
+struct Args {
+    /// Candump file to replay.
+    #[arg(short, long, conflicts_with = "device")]
+    in_file: Option<String>,
+
+    /// CAN interface to open (eg. "can0").
+    #[arg(short, long, required_unless_present = "in_file")]
+    device: Option<String>,
+
+    /// Replay speed (2 is twice as fast). 0 replays as fast as possible.
+    #[arg(short, long, default_value_t = 1.0)]
+    speed: f64,
+
+    /// Only show these IDs.
+    #[arg(long, value_parser=clap_num::maybe_hex::<u32>)]
+    ids: Vec<u32>,
+
+    /// Only show IDs that decode to these kinds (eg. "Doors").
+    #[arg(long)]
+    kinds: Vec<EventKind>,
+
+    /// Milliseconds a changed byte stays highlighted.
+    #[arg(long, default_value_t = 1000)]
+    highlight: u64,
+}
+
+/// What the reader thread sends to the ui.
+enum Message {
+    Frame(Frame, Timestamp),
+    Error(String),
+    Done,
+}
+
+/// Replay a candump file at `speed`, sending frames to `tx`.
+fn replay(path: String, speed: f64, tx: SyncSender<Message>) {
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(err) => {
+            let _ = tx.send(Message::Error(format!("{path}: {err}")));
+            return;
+        }
+    };
+    let start = Instant::now();
+    let mut first = None;
+    for record in candump::records(BufReader::new(file)) {
+        let record = match record {
+            Ok(record) => record,
+            Err(candump::Error::Io(err)) => {
+                let _ = tx.send(Message::Error(err.to_string()));
+                return;
+            }
+            // Eg. remote or CAN FD frames.
+            Err(_) => continue,
+        };
+        let first = *first.get_or_insert(record.timestamp);
+        let at = record.timestamp.saturating_sub(first);
+        if speed > 0.0 {
+            let due = at.div_f64(speed);
+            if let Some(wait) = due.checked_sub(start.elapsed()) {
+                std::thread::sleep(wait);
+            }
+        }
+        if tx.send(Message::Frame(record.frame, at)).is_err() {
+            return;
+        }
+    }
+    let _ = tx.send(Message::Done);
+}
+
+/// Listen on `device`, sending frames to `tx`.
+fn listen(device: String, tx: SyncSender<Message>) {
+    let listener = match Listener::connect(&device, true) {
+        Ok(listener) => listener,
+        Err(err) => {
+            let _ = tx.send(Message::Error(format!("{device}: {err}")));
+            return;
+        }
+    };
+    let start = Instant::now();
+    for frame in listener.frames() {
+        let message = match frame {
+            Ok(frame) => Message::Frame(frame, start.elapsed()),
+            Err(err) => Message::Error(err.to_string()),
+        };
+        if tx.send(message).is_err() {
+            return;
+        }
+    }
+}
+
+/// The state of the ui.
+struct App {
+    sniffer: Sniffer,
+    table: TableState,
+    /// Timestamp of the latest frame.
+    now: Timestamp,
+    paused: bool,
+    status: Option<String>,
+}
+
+impl App {
+    /// The id of the selected row, if any.
+    fn selected(&self) -> Option<u32> {
+        let index = self.table.selected()?;
+        self.sniffer.rows().nth(index).map(|row| row.id)
+    }
+
+    /// Drain frames from `rx`. Returns false once the reader is gone.
+    fn receive(&mut self, rx: &Receiver<Message>) -> bool {
+        loop {
+            match rx.try_recv() {
+                Ok(Message::Frame(frame, at)) => {
+                    self.now = self.now.max(at);
+                    self.sniffer.update(&frame, at);
+                }
+                Ok(Message::Error(err)) => self.status = Some(err),
+                Ok(Message::Done) => {
+                    self.status = Some("end of file".to_owned())
+                }
+                Err(TryRecvError::Empty) => return true,
+                Err(TryRecvError::Disconnected) => return false,
+            }
+        }
+    }
+
+    /// Handle a key. Returns false to quit.
+    fn key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+                return false
+            }
+            KeyCode::Char(' ') => self.paused = !self.paused,
+            KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+            KeyCode::Char('m') => {
+                if let Some(id) = self.selected() {
+                    self.sniffer.toggle_mark(id);
+                }
+            }
+            KeyCode::Char('f') => {
+                if let Some(id) = self.selected() {
+                    self.sniffer.toggle_freeze(id);
+                }
+            }
+            KeyCode::Char('c') => self.sniffer.clear(),
+            _ => {}
+        }
+        true
+    }
+
+    fn draw(&mut self, frame: &mut ratatui::Frame) {
+        let [main, footer] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)])
+                .areas(frame.area());
+
+        let changed = Style::default().fg(Color::Black).bg(Color::Yellow);
+        let rows: Vec<TableRow> = self
+            .sniffer
+            .rows()
+            .map(|row| {
+                let data: Vec<Span> = row
+                    .data
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, byte)| {
+                        let text = format!("{byte:02X}");
+                        let span =
+                            match self.sniffer.is_highlighted(row, i, self.now)
+                            {
+                                true => Span::styled(text, changed),
+                                false => Span::raw(text),
+                            };
+                        [span, Span::raw(" ")]
+                    })
+                    .collect();
+                let decoded = match &row.error {
+                    Some(err) => Span::styled(
+                        err.clone(),
+                        Style::default().fg(Color::Red),
+                    ),
+                    None => Span::raw(
+                        row.events
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                    ),
+                };
+                let rate = match row.rate_hz() {
+                    Some(hz) => format!("{hz:.1}"),
+                    None => "-".to_owned(),
+                };
+                let flags = match (row.marked, row.frozen) {
+                    (true, true) => "MF",
+                    (true, false) => "M",
+                    (false, true) => "F",
+                    (false, false) => "",
+                };
+                let mut style = Style::default();
+                if row.marked {
+                    style = style.fg(Color::Green).add_modifier(Modifier::BOLD);
+                }
+                if row.frozen {
+                    style = style.fg(Color::Cyan);
+                }
+                TableRow::new([
+                    Cell::from(format!("{:03X}", row.id)),
+                    Cell::from(flags),
+                    Cell::from(rate),
+                    Cell::from(row.count.to_string()),
+                    Cell::from(Line::from(data)),
+                    Cell::from(decoded),
+                ])
+                .style(style)
+            })
+            .collect();
+
+        let header =
+            TableRow::new(["ID", "", "Hz", "Count", "Data", "Decoded"])
+                .style(Style::default().add_modifier(Modifier::BOLD));
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(8),
+                Constraint::Length(2),
+                Constraint::Length(7),
+                Constraint::Length(8),
+                Constraint::Length(24),
+                Constraint::Min(20),
+            ],
+        )
+        .header(header)
+        .block(Block::bordered().title(" jeep-sniff "))
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, main, &mut self.table);
+
+        let mut help = format!(
+            "{:.1}s  q quit  space {}  m mark  f freeze  c clear",
+            self.now.as_secs_f64(),
+            if self.paused { "resume" } else { "pause" },
+        );
+        if let Some(status) = &self.status {
+            help.push_str("  | ");
+            help.push_str(status);
+        }
+        frame.render_widget(Line::from(help), footer);
+    }
+}
+
+fn run(
+    terminal: &mut DefaultTerminal,
+    mut app: App,
+    rx: Receiver<Message>,
+) -> std::io::Result<()> {
+    loop {
+        // While paused the reader blocks on the full channel, so a replay
+        // resumes where it left off.
+        if !app.paused && !app.receive(&rx) && app.status.is_none() {
+            app.status = Some("reader stopped".to_owned());
+        }
+        terminal.draw(|frame| app.draw(frame))?;
+
+        if event::poll(REDRAW)? {
+            if let TermEvent::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press
+                    && !app.key(key.code, key.modifiers)
+                {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let mut sniffer = Sniffer::new(SnifferConfig {
+        highlight: Duration::from_millis(args.highlight),
+    });
+    if !args.ids.is_empty() {
+        sniffer.filter_ids(Some(args.ids.iter().copied().collect()));
+    }
+    if !args.kinds.is_empty() {
+        let kinds =
+            args.kinds.iter().fold(EventKinds::empty(), |kinds, kind| {
+                kinds | EventKinds::from(*kind)
+            });
+        sniffer.filter_kinds(Some(kinds));
+    }
+
+    let (tx, rx) = sync_channel(1024);
+    if let Some(in_file) = args.in_file {
+        let speed = args.speed;
+        std::thread::spawn(move || replay(in_file, speed, tx));
+    } else {
+        // `device` is required without `in_file`.
+        let device = args.device.unwrap();
+        std::thread::spawn(move || listen(device, tx));
+    }
+
+    let app = App {
+        sniffer,
+        table: TableState::default(),
+        now: Timestamp::ZERO,
+        paused: false,
+        status: None,
+    };
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, app, rx);
+    ratatui::restore();
+
+    Ok(result?)
+}
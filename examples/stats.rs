@@ -0,0 +1,151 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use clap::Parser;
+use jeep::{
+    analysis::{BusStats, UnknownCatalog},
+    candump,
+    listener::Listener,
+    Event, Frame, Timestamp,
+};
+
+use std::{
+    fs::File,
+    io::BufReader,
+    sync::mpsc::sync_channel,
+    time::{Duration, Instant},
+};
+
+/// Pause between polls of the (non-blocking) listener.
+const PAUSE: Duration = Duration::from_micros(8333);
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Print per-id bus statistics from a `candump -L` file or live",
+    long_about = "Print per-id bus statistics (count, period, jitter, DLCs, \
+    byte ranges, and how many frames parsed) from a `candump -L` file, or \
+    live until Ctrl+C or --seconds have passed."
+)]
+struct Args {
+    /// Candump file to read.
+    #[arg(short, long, conflicts_with = "device")]
+    in_file: Option<String>,
+
+    /// CAN interface to open (eg. "can0").
+    #[arg(short, long, required_unless_present = "in_file")]
+    device: Option<String>,
+
+    /// Stop listening after this many seconds.
+    #[arg(short, long)]
+    seconds: Option<u64>,
+
+    /// Print json instead of a table.
+    #[arg(long)]
+    json: bool,
+
+    /// Also print a report of unknown ids and unrecognized values (as
+    /// Markdown, or json with --json) to attach to an issue.
+    #[arg(long)]
+    unknown: bool,
+}
+
+/// Update the stats, and the catalog if there is one, with a `frame`.
+fn update(
+    stats: &mut BusStats,
+    catalog: &mut Option<UnknownCatalog>,
+    frame: Frame,
+    at: Timestamp,
+) {
+    stats.update(&frame, at);
+    if let Some(catalog) = catalog {
+        if let Err(err) = Event::parse(frame) {
+            catalog.add(&err, at);
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let mut stats = BusStats::new();
+    let mut catalog = args.unknown.then(UnknownCatalog::default);
+
+    if let Some(in_file) = &args.in_file {
+        let reader = BufReader::new(File::open(in_file)?);
+        for record in candump::records(reader) {
+            let record = match record {
+                Ok(record) => record,
+                Err(candump::Error::Io(err)) => return Err(err.into()),
+                // Eg. remote or CAN FD frames.
+                Err(err) => {
+                    eprintln!("skipping: {err}");
+                    continue;
+                }
+            };
+            update(&mut stats, &mut catalog, record.frame, record.timestamp);
+        }
+    } else {
+        // `device` is required without `in_file`.
+        let listener = Listener::connect(args.device.as_ref().unwrap(), false)?;
+        let (tx, rx) = sync_channel(0);
+        ctrlc::set_handler(move || {
+            tx.send(()).expect("rx disconnected somehow.")
+        })
+        .expect("Error setting Ctrl-C handler");
+        let start = Instant::now();
+        let stop = args.seconds.map(Duration::from_secs);
+
+        loop {
+            let loop_start = Instant::now();
+            for frame in listener.frames() {
+                update(&mut stats, &mut catalog, frame?, start.elapsed());
+            }
+            if rx.try_recv().is_ok()
+                || stop.is_some_and(|stop| start.elapsed() >= stop)
+            {
+                break;
+            }
+            let elapsed = loop_start.elapsed();
+            if elapsed < PAUSE {
+                std::thread::sleep(PAUSE - elapsed);
+            }
+        }
+    }
+
+    let report = stats.report();
+    let unknown = catalog.map(|catalog| catalog.report());
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        if let Some(unknown) = unknown {
+            println!("{}", serde_json::to_string_pretty(&unknown)?);
+        }
+    } else {
+        println!("{report}");
+        if let Some(unknown) = unknown {
+            println!();
+            print!("{unknown}");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,82 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use clap::Parser;
+use jeep::{candump, listener::Listener, trip::TripComputer};
+
+use std::{
+    fs::File,
+    io::BufReader,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Print trips (as json lines) from a `candump -L` file or live",
+    long_about = None
+)]
+struct Args {
+    /// Candump file to replay.
+    #[arg(short, long, conflicts_with = "device")]
+    in_file: Option<String>,
+
+    /// CAN interface to open (eg. "can0").
+    #[arg(short, long, required_unless_present = "in_file")]
+    device: Option<String>,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let mut computer = TripComputer::new();
+
+    if let Some(in_file) = args.in_file {
+        let reader = BufReader::new(File::open(in_file)?);
+        // Frames that don't parse are expected (unknown ids), so skip them.
+        for (at, event) in candump::events(reader).filter_map(Result::ok) {
+            if let Some(trip) = computer.update(&event, at) {
+                println!("{}", serde_json::to_string(trip)?);
+            }
+        }
+        // A trip may still be in progress at the end of the log.
+        if let Some(trip) = computer.current() {
+            println!("{}", serde_json::to_string(trip)?);
+        }
+        return Ok(());
+    }
+
+    // `device` is required without `in_file`.
+    let listener = Listener::connect(&args.device.unwrap(), true)?;
+    // Timestamps since the Unix epoch, like `candump -L`.
+    let epoch = SystemTime::now().duration_since(UNIX_EPOCH)?;
+    let start = Instant::now();
+
+    for event in listener.messages().filter_map(Result::ok) {
+        if let Some(trip) = computer.update(&event, epoch + start.elapsed()) {
+            println!("{}", serde_json::to_string(trip)?);
+        }
+    }
+
+    // We should never actually reach here when the Listener is blocking.
+    panic!("`Messages` iterator broken -- yielded no messages")
+}
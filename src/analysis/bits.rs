@@ -0,0 +1,370 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! A [`BitTracker`] that records which bits of which frames changed and when,
+//! so the bits that toggle around a user action ("pressing the hazard button
+//! now") can be found.
+//!
+//! ```
+//! use std::time::Duration;
+//! use jeep::{analysis::BitTracker, Frame};
+//!
+//! let mut tracker = BitTracker::default();
+//! let t = Duration::from_millis;
+//! let frame = |data: &[u8]| Frame::from_id_slice(0x123, data).unwrap();
+//!
+//! // A counter toggles all the time, but the hazard bit only around the mark.
+//! for i in 0..100u8 {
+//!     let hazards = if (50..52).contains(&i) { 0b1000 } else { 0 };
+//!     tracker.update(&frame(&[i, hazards]), t(i as u64 * 100));
+//! }
+//!
+//! let best = &tracker.candidates(&[t(5000)], Duration::from_millis(500))[0];
+//! assert_eq!((best.id, best.byte, best.bit), (0x123, 1, 3));
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt,
+    ops::RangeBounds,
+    time::Duration,
+};
+
+use crate::{Frame, Timestamp};
+
+/// Configuration for a [`BitTracker`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitTrackerConfig {
+    /// How long to remember changes for, relative to the latest frame.
+    /// Longer gives better [`Candidate::specificity`] at the cost of memory.
+    pub history: Duration,
+}
+
+impl Default for BitTrackerConfig {
+    fn default() -> Self {
+        Self {
+            history: Duration::from_secs(300),
+        }
+    }
+}
+
+/// A single bit that changed.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Change {
+    /// When it changed.
+    pub at: Timestamp,
+    /// Frame id.
+    pub id: u32,
+    /// Byte index in the frame's data.
+    pub byte: u8,
+    /// Bit index in the byte (0 is the least significant).
+    pub bit: u8,
+    /// The new value of the bit.
+    pub value: bool,
+}
+
+/// A bit that toggled around one or more marks, as returned by
+/// [`BitTracker::candidates`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    /// Frame id.
+    pub id: u32,
+    /// Byte index in the frame's data.
+    pub byte: u8,
+    /// Bit index in the byte (0 is the least significant).
+    pub bit: u8,
+    /// How many of the marks this bit toggled around.
+    pub marks: usize,
+    /// How many times this bit toggled around any of the marks.
+    pub toggles_near: usize,
+    /// How many times this bit toggled in the whole history.
+    pub toggles_total: usize,
+    /// The fraction (0-1) of this bit's toggles that were near a mark. A
+    /// counter or other noisy bit scores low. The bit for the action scores
+    /// (close to) 1.
+    pub specificity: f32,
+}
+
+impl fmt::Display for Candidate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:#05X} byte {} bit {}: {} marks, {}/{} toggles ({:.0}%)",
+            self.id,
+            self.byte,
+            self.bit,
+            self.marks,
+            self.toggles_near,
+            self.toggles_total,
+            self.specificity * 100.0
+        )
+    }
+}
+
+/// Keeps the last data of every id and a history of every bit that changed.
+#[derive(Debug, Clone, Default)]
+pub struct BitTracker {
+    config: BitTrackerConfig,
+    /// Last data seen for each id.
+    last: HashMap<u32, Vec<u8>>,
+    /// Every change within the history, oldest first.
+    changes: VecDeque<Change>,
+}
+
+impl BitTracker {
+    /// Create a new [`BitTracker`] from a [`BitTrackerConfig`].
+    pub fn new(config: BitTrackerConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// The [`BitTrackerConfig`] in use.
+    pub fn config(&self) -> &BitTrackerConfig {
+        &self.config
+    }
+
+    /// Update with a `frame` received `at` some time. The first frame from
+    /// each id is the baseline, so it never produces changes. A change in
+    /// length counts every bit past the shorter length as changed.
+    pub fn update(&mut self, frame: &Frame, at: Timestamp) {
+        let data = frame.data();
+        match self.last.get_mut(&frame.id()) {
+            Some(last) => {
+                let len = last.len().max(data.len());
+                for byte in 0..len {
+                    let old = last.get(byte).copied().unwrap_or_default();
+                    let new = data.get(byte).copied().unwrap_or_default();
+                    let mut diff = old ^ new;
+                    while diff != 0 {
+                        let bit = diff.trailing_zeros() as u8;
+                        diff &= diff - 1;
+                        self.changes.push_back(Change {
+                            at,
+                            id: frame.id(),
+                            byte: byte as u8,
+                            bit,
+                            value: new & (1 << bit) != 0,
+                        });
+                    }
+                }
+                last.clear();
+                last.extend_from_slice(data);
+            }
+            None => {
+                self.last.insert(frame.id(), data.to_vec());
+            }
+        }
+
+        // Forget anything older than the history.
+        let oldest = at.saturating_sub(self.config.history);
+        while self
+            .changes
+            .front()
+            .is_some_and(|change| change.at < oldest)
+        {
+            self.changes.pop_front();
+        }
+    }
+
+    /// The last data seen for an `id`, if any.
+    pub fn last(&self, id: u32) -> Option<&[u8]> {
+        self.last.get(&id).map(Vec::as_slice)
+    }
+
+    /// Every [`Change`] within a time `range`, oldest first.
+    pub fn changes<R>(&self, range: R) -> impl Iterator<Item = &Change>
+    where
+        R: RangeBounds<Timestamp>,
+    {
+        self.changes
+            .iter()
+            .filter(move |change| range.contains(&change.at))
+    }
+
+    /// Bits that toggled within `window` (before or after) of any of the
+    /// `marks`, ranked best first: by how many marks they toggled around,
+    /// then by [`Candidate::specificity`].
+    ///
+    /// Marking the same action several times makes the ranking much better,
+    /// since unrelated bits are unlikely to toggle around every mark.
+    pub fn candidates(
+        &self,
+        marks: &[Timestamp],
+        window: Duration,
+    ) -> Vec<Candidate> {
+        #[derive(Default)]
+        struct Tally {
+            /// Which marks the bit toggled around.
+            hit: Vec<bool>,
+            near: usize,
+            total: usize,
+        }
+
+        let mut bits: BTreeMap<(u32, u8, u8), Tally> = BTreeMap::new();
+        for change in &self.changes {
+            let tally = bits
+                .entry((change.id, change.byte, change.bit))
+                .or_insert_with(|| Tally {
+                    hit: vec![false; marks.len()],
+                    ..Default::default()
+                });
+            tally.total += 1;
+
+            let mut is_near = false;
+            for (i, mark) in marks.iter().enumerate() {
+                if change.at.abs_diff(*mark) <= window {
+                    tally.hit[i] = true;
+                    is_near = true;
+                }
+            }
+            if is_near {
+                tally.near += 1;
+            }
+        }
+
+        let mut candidates: Vec<Candidate> = bits
+            .into_iter()
+            .filter(|(_, tally)| tally.near > 0)
+            .map(|((id, byte, bit), tally)| Candidate {
+                id,
+                byte,
+                bit,
+                marks: tally.hit.into_iter().filter(|hit| *hit).count(),
+                toggles_near: tally.near,
+                toggles_total: tally.total,
+                specificity: tally.near as f32 / tally.total as f32,
+            })
+            .collect();
+
+        candidates.sort_by(|a, b| {
+            b.marks
+                .cmp(&a.marks)
+                .then(b.specificity.total_cmp(&a.specificity))
+                .then(b.toggles_near.cmp(&a.toggles_near))
+        });
+
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(id: u32, data: &[u8]) -> Frame {
+        Frame::from_id_slice(id, data).unwrap()
+    }
+
+    #[test]
+    fn test_changes() {
+        let mut tracker = BitTracker::default();
+        let t = Duration::from_secs;
+
+        tracker.update(&frame(1, &[0b0000_0001]), t(0));
+        assert_eq!(tracker.changes(..).count(), 0);
+
+        tracker.update(&frame(1, &[0b1000_0000, 0b10]), t(1));
+        let changes: Vec<_> = tracker.changes(..).copied().collect();
+        assert_eq!(
+            changes,
+            [
+                Change {
+                    at: t(1),
+                    id: 1,
+                    byte: 0,
+                    bit: 0,
+                    value: false
+                },
+                Change {
+                    at: t(1),
+                    id: 1,
+                    byte: 0,
+                    bit: 7,
+                    value: true
+                },
+                Change {
+                    at: t(1),
+                    id: 1,
+                    byte: 1,
+                    bit: 1,
+                    value: true
+                },
+            ]
+        );
+        assert_eq!(tracker.last(1), Some(&[0b1000_0000, 0b10][..]));
+        assert_eq!(tracker.changes(t(2)..).count(), 0);
+    }
+
+    #[test]
+    fn test_history() {
+        let mut tracker = BitTracker::new(BitTrackerConfig {
+            history: Duration::from_secs(10),
+        });
+        let t = Duration::from_secs;
+
+        for i in 0..30 {
+            tracker.update(&frame(1, &[i as u8 & 1]), t(i));
+        }
+        // Only the last 10 seconds (inclusive) are kept.
+        assert_eq!(tracker.changes(..).count(), 11);
+    }
+
+    #[test]
+    fn test_candidates() {
+        let mut tracker = BitTracker::default();
+        let t = Duration::from_millis;
+
+        // id 1 byte 0 is a counter, id 2 bit 5 is pressed twice, and id 3
+        // bit 0 toggles once around the first press by coincidence.
+        for i in 0..200u64 {
+            tracker.update(&frame(1, &[i as u8]), t(i * 100));
+            let pressed = (50..55).contains(&i) || (150..155).contains(&i);
+            tracker.update(&frame(2, &[(pressed as u8) << 5]), t(i * 100));
+            tracker.update(&frame(3, &[(i > 51) as u8]), t(i * 100));
+        }
+
+        let marks = [t(5000), t(15000)];
+        let candidates = tracker.candidates(&marks, t(1000));
+        let best = &candidates[0];
+        assert_eq!((best.id, best.byte, best.bit), (2, 0, 5));
+        assert_eq!(best.marks, 2);
+        assert_eq!(best.toggles_near, 4);
+        assert_eq!(best.specificity, 1.0);
+
+        // The counter's low bit toggles around both marks, but rarely
+        // *only* around them.
+        let counter = candidates
+            .iter()
+            .find(|c| (c.id, c.byte, c.bit) == (1, 0, 0))
+            .unwrap();
+        assert_eq!(counter.marks, 2);
+        assert!(counter.specificity < 0.25);
+
+        let coincidence = candidates.iter().find(|c| c.id == 3).unwrap();
+        assert_eq!(coincidence.marks, 1);
+    }
+}
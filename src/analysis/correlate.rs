@@ -0,0 +1,411 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! A [`Correlator`] that tries every byte and word of candidate frames, under
+//! a few [`Encoding`]s, and ranks how well each correlates with a signal that
+//! is already decoded (eg. speed). The best candidates come with a linear fit
+//! to propose a scaling for a new decoder.
+//!
+//! The signal is sampled and held: each candidate frame is paired with the
+//! latest value of the signal, as long as it's not older than
+//! [`CorrelatorConfig::max_age`].
+
+use std::{collections::HashMap, fmt, time::Duration};
+
+use crate::{
+    events::{engine::Engine, hvac::HVAC, OneOrMany},
+    Event, Frame, Timestamp,
+};
+
+/// Ready made signals for [`Correlator::new`].
+pub mod signals {
+    use super::*;
+
+    /// GPS corrected speed ([`Engine::MPH`]).
+    pub fn mph(event: &Event) -> Option<f64> {
+        match event {
+            Event::Engine(Engine::MPH(mph)) => Some(mph.mph().to_f64()),
+            _ => None,
+        }
+    }
+
+    /// Approximate speed ([`Engine::ApproxMPH`]).
+    pub fn approx_mph(event: &Event) -> Option<f64> {
+        match event {
+            Event::Engine(Engine::ApproxMPH(mph)) => Some(mph.mph().to_f64()),
+            _ => None,
+        }
+    }
+
+    /// Engine RPMs ([`Engine::RPMs`]), while the engine is on.
+    pub fn rpms(event: &Event) -> Option<f64> {
+        match event {
+            Event::Engine(Engine::RPMs(rpms)) => rpms.get().map(f64::from),
+            _ => None,
+        }
+    }
+
+    /// Cabin temperature in Celsius ([`HVAC::Cabin`]).
+    pub fn cabin_celsius(event: &Event) -> Option<f64> {
+        match event {
+            Event::HVAC(HVAC::Cabin(temperature)) => {
+                Some(temperature.celsius().to_f64())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// How to interpret bytes as a number.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Encoding {
+    U8,
+    I8,
+    U16Be,
+    U16Le,
+    I16Be,
+    I16Le,
+}
+
+impl Encoding {
+    /// Every [`Encoding`].
+    pub const ALL: [Encoding; 6] = [
+        Encoding::U8,
+        Encoding::I8,
+        Encoding::U16Be,
+        Encoding::U16Le,
+        Encoding::I16Be,
+        Encoding::I16Le,
+    ];
+
+    /// Number of bytes.
+    pub const fn width(self) -> usize {
+        match self {
+            Encoding::U8 | Encoding::I8 => 1,
+            _ => 2,
+        }
+    }
+
+    /// Read a value from `data` at `offset`, if there are enough bytes.
+    pub fn read(self, data: &[u8], offset: usize) -> Option<f64> {
+        let bytes = data.get(offset..offset + self.width())?;
+        let word = || [bytes[0], bytes[1]];
+        Some(match self {
+            Encoding::U8 => f64::from(bytes[0]),
+            Encoding::I8 => f64::from(bytes[0] as i8),
+            Encoding::U16Be => f64::from(u16::from_be_bytes(word())),
+            Encoding::U16Le => f64::from(u16::from_le_bytes(word())),
+            Encoding::I16Be => f64::from(i16::from_be_bytes(word())),
+            Encoding::I16Le => f64::from(i16::from_le_bytes(word())),
+        })
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Encoding::U8 => "u8",
+            Encoding::I8 => "i8",
+            Encoding::U16Be => "u16be",
+            Encoding::U16Le => "u16le",
+            Encoding::I16Be => "i16be",
+            Encoding::I16Le => "i16le",
+        })
+    }
+}
+
+/// Configuration for a [`Correlator`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorrelatorConfig {
+    /// Candidate ids, or `None` for every id.
+    pub ids: Option<Vec<u32>>,
+    /// A signal value older than this isn't paired with frames.
+    pub max_age: Duration,
+    /// Candidates with fewer samples than this are not reported.
+    pub min_samples: u64,
+}
+
+impl Default for CorrelatorConfig {
+    fn default() -> Self {
+        Self {
+            ids: None,
+            max_age: Duration::from_secs(1),
+            min_samples: 10,
+        }
+    }
+}
+
+/// How well a candidate correlates with the signal, as returned by
+/// [`Correlator::results`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Correlation {
+    /// Frame id.
+    pub id: u32,
+    /// Offset of the first byte in the frame's data.
+    pub offset: usize,
+    /// How the bytes were read.
+    pub encoding: Encoding,
+    /// Number of (value, signal) pairs.
+    pub samples: u64,
+    /// Pearson correlation coefficient (-1 to 1).
+    pub r: f64,
+    /// Proposed scaling: `signal ≈ scale * value + bias`.
+    pub scale: f64,
+    /// Proposed bias: `signal ≈ scale * value + bias`.
+    pub bias: f64,
+}
+
+impl fmt::Display for Correlation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:#05X} [{}..{}] {}: r={:+.4} signal ≈ {:.6} * value {:+.4} (n={})",
+            self.id,
+            self.offset,
+            self.offset + self.encoding.width(),
+            self.encoding,
+            self.r,
+            self.scale,
+            self.bias,
+            self.samples
+        )
+    }
+}
+
+/// Running (co)variance of a candidate and the signal (Welford's method).
+#[derive(Debug, Clone, Default)]
+struct Accumulator {
+    n: u64,
+    mean_x: f64,
+    mean_y: f64,
+    m2_x: f64,
+    m2_y: f64,
+    c_xy: f64,
+}
+
+impl Accumulator {
+    fn add(&mut self, x: f64, y: f64) {
+        self.n += 1;
+        let n = self.n as f64;
+        let dx = x - self.mean_x;
+        let dy = y - self.mean_y;
+        self.mean_x += dx / n;
+        self.mean_y += dy / n;
+        self.m2_x += dx * (x - self.mean_x);
+        self.m2_y += dy * (y - self.mean_y);
+        self.c_xy += dx * (y - self.mean_y);
+    }
+
+    /// `(r, scale, bias)`, or `None` if either side never changed.
+    fn fit(&self) -> Option<(f64, f64, f64)> {
+        if self.m2_x <= 0.0 || self.m2_y <= 0.0 {
+            return None;
+        }
+        let r = self.c_xy / (self.m2_x * self.m2_y).sqrt();
+        let scale = self.c_xy / self.m2_x;
+        let bias = self.mean_y - scale * self.mean_x;
+
+        Some((r, scale, bias))
+    }
+}
+
+/// Finds bytes and words that correlate with a decoded signal.
+pub struct Correlator<F> {
+    config: CorrelatorConfig,
+    signal: F,
+    /// The latest signal value and when.
+    latest: Option<(f64, Timestamp)>,
+    candidates: HashMap<(u32, usize, Encoding), Accumulator>,
+}
+
+impl<F> Correlator<F>
+where
+    F: FnMut(&Event) -> Option<f64>,
+{
+    /// Create a new [`Correlator`] for a `signal` (see [`signals`]).
+    pub fn new(config: CorrelatorConfig, signal: F) -> Self {
+        Self {
+            config,
+            signal,
+            latest: None,
+            candidates: HashMap::new(),
+        }
+    }
+
+    /// The [`CorrelatorConfig`] in use.
+    pub fn config(&self) -> &CorrelatorConfig {
+        &self.config
+    }
+
+    /// Update with a `frame` received `at` some time. The frame is decoded to
+    /// update the signal, and if it's a candidate, paired with the signal.
+    pub fn update(&mut self, frame: &Frame, at: Timestamp) {
+        match Event::parse(frame.clone()) {
+            Ok(OneOrMany::One(event)) => self.sample(&event, at),
+            Ok(OneOrMany::Many(events)) => {
+                for event in &events {
+                    self.sample(event, at);
+                }
+            }
+            Err(_) => {}
+        }
+
+        if let Some(ids) = &self.config.ids {
+            if !ids.contains(&frame.id()) {
+                return;
+            }
+        }
+        let y = match self.latest {
+            Some((y, when))
+                if at.saturating_sub(when) <= self.config.max_age =>
+            {
+                y
+            }
+            _ => return,
+        };
+
+        let data = frame.data();
+        for encoding in Encoding::ALL {
+            for offset in 0..data.len() {
+                if let Some(x) = encoding.read(data, offset) {
+                    self.candidates
+                        .entry((frame.id(), offset, encoding))
+                        .or_default()
+                        .add(x, y);
+                }
+            }
+        }
+    }
+
+    fn sample(&mut self, event: &Event, at: Timestamp) {
+        if let Some(value) = (self.signal)(event) {
+            self.latest = Some((value, at));
+        }
+    }
+
+    /// Every candidate with enough samples that changed along with the
+    /// signal, best (highest `|r|`) first.
+    pub fn results(&self) -> Vec<Correlation> {
+        let mut results: Vec<Correlation> = self
+            .candidates
+            .iter()
+            .filter(|(_, acc)| acc.n >= self.config.min_samples)
+            .filter_map(|(&(id, offset, encoding), acc)| {
+                let (r, scale, bias) = acc.fit()?;
+                Some(Correlation {
+                    id,
+                    offset,
+                    encoding,
+                    samples: acc.n,
+                    r,
+                    scale,
+                    bias,
+                })
+            })
+            .collect();
+
+        results.sort_by(|a, b| {
+            b.r.abs().total_cmp(&a.r.abs()).then(
+                (a.id, a.offset, a.encoding).cmp(&(b.id, b.offset, b.encoding)),
+            )
+        });
+
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(id: u32, data: &[u8]) -> Frame {
+        Frame::from_id_slice(id, data).unwrap()
+    }
+
+    #[test]
+    fn test_encoding() {
+        let data = [0xff, 0x01, 0x80];
+        assert_eq!(Encoding::U8.read(&data, 0), Some(255.0));
+        assert_eq!(Encoding::I8.read(&data, 0), Some(-1.0));
+        assert_eq!(Encoding::U16Be.read(&data, 1), Some(384.0));
+        assert_eq!(Encoding::U16Le.read(&data, 1), Some(32769.0));
+        assert_eq!(Encoding::I16Le.read(&data, 1), Some(-32767.0));
+        assert_eq!(Encoding::I16Be.read(&data, 2), None);
+    }
+
+    #[test]
+    fn test_correlate_speed() {
+        let mut correlator = Correlator::new(
+            CorrelatorConfig {
+                ids: Some(vec![0x7ff]),
+                ..Default::default()
+            },
+            signals::mph,
+        );
+        let t = Duration::from_millis;
+
+        for i in 0..100u64 {
+            let mph = (i % 50) as u8;
+            // Speed frame (0x340, byte 7 is mph).
+            correlator
+                .update(&frame(0x340, &[0, 0, 0, 0, 0, 0, 0, mph]), t(i * 100));
+            // Candidate: counter in byte 0, speed in kph * 4 as u16be in
+            // bytes 2..4, and a constant in byte 4.
+            let kph4 = (f64::from(mph) * 1.609344 * 4.0).round() as u16;
+            let [hi, lo] = kph4.to_be_bytes();
+            correlator.update(
+                &frame(0x7ff, &[i as u8, 0, hi, lo, 7]),
+                t(i * 100 + 10),
+            );
+            // Not a candidate.
+            correlator.update(&frame(0x7fe, &[mph]), t(i * 100 + 20));
+        }
+
+        let results = correlator.results();
+        let best = &results[0];
+        assert_eq!(
+            (best.id, best.offset, best.encoding),
+            (0x7ff, 2, Encoding::U16Be)
+        );
+        assert!(best.r > 0.999);
+        assert!((best.scale - 1.0 / (1.609344 * 4.0)).abs() < 0.001);
+        assert!(best.bias.abs() < 0.1);
+        // Constants are never reported and neither are other ids.
+        assert!(results.iter().all(|c| c.id == 0x7ff && c.offset != 4));
+    }
+
+    #[test]
+    fn test_stale_signal() {
+        let mut correlator =
+            Correlator::new(CorrelatorConfig::default(), signals::mph);
+        let t = Duration::from_secs;
+
+        correlator.update(&frame(0x340, &[0, 0, 0, 0, 0, 0, 0, 1]), t(0));
+        for i in 0..20 {
+            correlator.update(&frame(0x7ff, &[i]), t(2));
+        }
+        assert!(correlator.results().is_empty());
+    }
+}
@@ -0,0 +1,37 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Utilities to reverse engineer Jeep CAN frames. Everything here works on
+//! timestamped [`Frame`](crate::Frame)s, so it can be fed from a live
+//! [`Listener`](crate::Listener) (see `Listener::frames`) or from a recorded
+//! log (see [`crate::candump`]).
+
+pub mod bits;
+pub use bits::BitTracker;
+pub mod correlate;
+pub use correlate::Correlator;
+pub mod sniffer;
+pub use sniffer::Sniffer;
+pub mod stats;
+pub use stats::BusStats;
+pub mod unknown;
+pub use unknown::UnknownCatalog;
@@ -0,0 +1,370 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! A [`Sniffer`] that keeps one [`Row`] per id with the latest data, which
+//! bytes recently changed, the decoded [`Event`]s and the frame rate, like
+//! `cansniffer`. It's the model behind the `jeep-sniff` example, kept here so
+//! it can be tested (and reused) without a terminal.
+//!
+//! ```
+//! use std::time::Duration;
+//! use jeep::{analysis::Sniffer, Frame};
+//!
+//! let mut sniffer = Sniffer::default();
+//! let t = Duration::from_millis;
+//!
+//! sniffer.update(&Frame::from_id_slice(0x123, &[0, 1]).unwrap(), t(0));
+//! sniffer.update(&Frame::from_id_slice(0x123, &[0, 2]).unwrap(), t(100));
+//!
+//! let row = sniffer.rows().next().unwrap();
+//! assert_eq!(row.data, [0, 2]);
+//! assert!(!sniffer.is_highlighted(row, 0, t(200)));
+//! assert!(sniffer.is_highlighted(row, 1, t(200)));
+//! ```
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::Duration,
+};
+
+use crate::{
+    events::{registry, EventKinds, ParseError},
+    Event, Frame, Timestamp,
+};
+
+/// Weight of the newest period in a [`Row`]'s moving average.
+const RATE_ALPHA: f64 = 0.25;
+
+/// Configuration for a [`Sniffer`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnifferConfig {
+    /// How long a changed byte stays highlighted.
+    pub highlight: Duration,
+}
+
+impl Default for SnifferConfig {
+    fn default() -> Self {
+        Self {
+            highlight: Duration::from_secs(1),
+        }
+    }
+}
+
+/// The latest state of a single id.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    /// The frame id.
+    pub id: u32,
+    /// The latest data.
+    pub data: Vec<u8>,
+    /// When each byte of [`Row::data`] last changed, if it has since the
+    /// first frame.
+    pub changed: Vec<Option<Timestamp>>,
+    /// Number of frames (while not [`Row::frozen`]).
+    pub count: u64,
+    /// When the first frame was seen.
+    pub first_seen: Timestamp,
+    /// When the latest frame was seen.
+    pub last_seen: Timestamp,
+    /// Moving average of the time between frames, if there were at least two.
+    pub period: Option<Duration>,
+    /// [`Event`]s decoded from the latest data.
+    pub events: Vec<Event>,
+    /// Why the latest data didn't decode, if it didn't. Unknown ids are not
+    /// errors; they just have no [`Row::events`].
+    pub error: Option<String>,
+    /// Marked by the user (eg. "this is the one").
+    pub marked: bool,
+    /// Frozen by the user. A frozen row ignores new frames.
+    pub frozen: bool,
+}
+
+impl Row {
+    fn new(frame: &Frame, at: Timestamp) -> Self {
+        let mut row = Self {
+            id: frame.id(),
+            data: frame.data().to_vec(),
+            changed: vec![None; frame.data().len()],
+            count: 1,
+            first_seen: at,
+            last_seen: at,
+            period: None,
+            events: Vec::new(),
+            error: None,
+            marked: false,
+            frozen: false,
+        };
+        row.decode(frame);
+        row
+    }
+
+    fn update(&mut self, frame: &Frame, at: Timestamp) {
+        let period = at.saturating_sub(self.last_seen);
+        self.period = Some(match self.period {
+            Some(mean) => {
+                mean.mul_f64(1.0 - RATE_ALPHA) + period.mul_f64(RATE_ALPHA)
+            }
+            None => period,
+        });
+        self.count += 1;
+        self.last_seen = at;
+
+        let data = frame.data();
+        self.changed.resize(data.len(), Some(at));
+        for (i, (old, new)) in self.data.iter().zip(data).enumerate() {
+            if old != new {
+                self.changed[i] = Some(at);
+            }
+        }
+        if self.data != data {
+            self.data = data.to_vec();
+            self.decode(frame);
+        }
+    }
+
+    fn decode(&mut self, frame: &Frame) {
+        self.events.clear();
+        self.error = None;
+        match Event::parse(frame.clone()) {
+            Ok(events) => self.events.extend(events),
+            Err(ParseError::Id { .. }) => {}
+            Err(err) => self.error = Some(err.to_string()),
+        }
+    }
+
+    /// Frames per second, from [`Row::period`].
+    pub fn rate_hz(&self) -> Option<f64> {
+        self.period
+            .filter(|period| !period.is_zero())
+            .map(|period| 1.0 / period.as_secs_f64())
+    }
+}
+
+/// A `cansniffer`-like view of the bus. See the [module](self) docs.
+#[derive(Debug, Clone, Default)]
+pub struct Sniffer {
+    config: SnifferConfig,
+    rows: BTreeMap<u32, Row>,
+    ids: Option<BTreeSet<u32>>,
+    kinds: Option<EventKinds>,
+}
+
+impl Sniffer {
+    /// Create a new [`Sniffer`] with a [`SnifferConfig`].
+    pub fn new(config: SnifferConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// The [`SnifferConfig`] in use.
+    pub fn config(&self) -> &SnifferConfig {
+        &self.config
+    }
+
+    /// Update the [`Row`] for `frame`'s id, unless it's [`Row::frozen`].
+    pub fn update(&mut self, frame: &Frame, at: Timestamp) {
+        match self.rows.get_mut(&frame.id()) {
+            Some(row) if row.frozen => {}
+            Some(row) => row.update(frame, at),
+            None => {
+                self.rows.insert(frame.id(), Row::new(frame, at));
+            }
+        }
+    }
+
+    /// [`Row`]s passing the filters, sorted by id.
+    pub fn rows(&self) -> impl Iterator<Item = &Row> {
+        self.rows.values().filter(|row| self.passes(row.id))
+    }
+
+    /// The [`Row`] for `id`, if it has been seen (regardless of filters).
+    pub fn get(&self, id: u32) -> Option<&Row> {
+        self.rows.get(&id)
+    }
+
+    /// Whether `byte` of `row` changed within [`SnifferConfig::highlight`] of
+    /// `now`.
+    pub fn is_highlighted(
+        &self,
+        row: &Row,
+        byte: usize,
+        now: Timestamp,
+    ) -> bool {
+        row.changed
+            .get(byte)
+            .copied()
+            .flatten()
+            .is_some_and(|at| now.saturating_sub(at) < self.config.highlight)
+    }
+
+    /// Only show these ids (or all, with `None`).
+    pub fn filter_ids(&mut self, ids: Option<BTreeSet<u32>>) {
+        self.ids = ids;
+    }
+
+    /// Only show ids that decode to any of these kinds (or all, with `None`),
+    /// according to the [`registry`]. Unknown ids are hidden by a kind filter.
+    pub fn filter_kinds(&mut self, kinds: Option<EventKinds>) {
+        self.kinds = kinds;
+    }
+
+    /// Toggle [`Row::marked`] for `id`. Returns the new value, or `None` if
+    /// the id hasn't been seen.
+    pub fn toggle_mark(&mut self, id: u32) -> Option<bool> {
+        self.rows.get_mut(&id).map(|row| {
+            row.marked = !row.marked;
+            row.marked
+        })
+    }
+
+    /// Toggle [`Row::frozen`] for `id`. Returns the new value, or `None` if
+    /// the id hasn't been seen.
+    pub fn toggle_freeze(&mut self, id: u32) -> Option<bool> {
+        self.rows.get_mut(&id).map(|row| {
+            row.frozen = !row.frozen;
+            row.frozen
+        })
+    }
+
+    /// Forget every row that isn't [`Row::marked`] or [`Row::frozen`].
+    pub fn clear(&mut self) {
+        self.rows.retain(|_, row| row.marked || row.frozen);
+    }
+
+    fn passes(&self, id: u32) -> bool {
+        if self.ids.as_ref().is_some_and(|ids| !ids.contains(&id)) {
+            return false;
+        }
+        match self.kinds {
+            Some(kinds) => registry::lookup(id)
+                .is_some_and(|info| info.kind_set().intersects(kinds)),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventKind;
+
+    fn frame(id: u32, data: &[u8]) -> Frame {
+        Frame::from_id_slice(id, data).unwrap()
+    }
+
+    #[test]
+    fn test_rows() {
+        let mut sniffer = Sniffer::default();
+        let t = Duration::from_millis;
+
+        sniffer.update(&frame(0x7ff, &[1, 2, 3]), t(0));
+        sniffer.update(&frame(0x7ff, &[1, 5, 3]), t(100));
+        sniffer.update(&frame(0x7ff, &[1, 5, 3]), t(200));
+
+        let row = sniffer.get(0x7ff).unwrap();
+        assert_eq!(row.count, 3);
+        assert_eq!(row.data, [1, 5, 3]);
+        assert_eq!(row.changed, [None, Some(t(100)), None]);
+        assert_eq!(row.period, Some(t(100)));
+        assert!((row.rate_hz().unwrap() - 10.0).abs() < 1e-9);
+        // Unknown ids aren't errors.
+        assert!(row.events.is_empty());
+        assert!(row.error.is_none());
+
+        assert!(sniffer.is_highlighted(row, 1, t(1099)));
+        assert!(!sniffer.is_highlighted(row, 1, t(1100)));
+        assert!(!sniffer.is_highlighted(row, 0, t(200)));
+        assert!(!sniffer.is_highlighted(row, 8, t(200)));
+
+        // A longer frame marks the new bytes changed.
+        sniffer.update(&frame(0x7ff, &[1, 5, 3, 4]), t(300));
+        let row = sniffer.get(0x7ff).unwrap();
+        assert_eq!(row.changed[3], Some(t(300)));
+    }
+
+    #[test]
+    fn test_events() {
+        let mut sniffer = Sniffer::default();
+        let data = 0x07000000_00000001_u64.to_be_bytes();
+
+        sniffer.update(&frame(0x2d3, &data), Duration::ZERO);
+        let row = sniffer.get(0x2d3).unwrap();
+        assert!(!row.events.is_empty());
+        assert!(row.error.is_none());
+
+        // Wrong length for the id.
+        sniffer.update(&frame(0x2d3, &data[..2]), Duration::ZERO);
+        let row = sniffer.get(0x2d3).unwrap();
+        assert!(row.events.is_empty());
+        assert!(row.error.is_some());
+    }
+
+    #[test]
+    fn test_filters() {
+        let mut sniffer = Sniffer::default();
+        let data = 0x07000000_00000001_u64.to_be_bytes();
+        sniffer.update(&frame(0x2d3, &data), Duration::ZERO);
+        sniffer.update(&frame(0x7ff, &[0]), Duration::ZERO);
+        let ids = |sniffer: &Sniffer| {
+            sniffer.rows().map(|row| row.id).collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&sniffer), [0x2d3, 0x7ff]);
+
+        sniffer.filter_ids(Some([0x7ff].into()));
+        assert_eq!(ids(&sniffer), [0x7ff]);
+        sniffer.filter_ids(None);
+
+        sniffer.filter_kinds(Some(EventKind::ControlPanel.into()));
+        assert_eq!(ids(&sniffer), [0x2d3]);
+        sniffer.filter_kinds(Some(EventKind::Doors.into()));
+        assert!(ids(&sniffer).is_empty());
+    }
+
+    #[test]
+    fn test_mark_freeze_clear() {
+        let mut sniffer = Sniffer::default();
+        let t = Duration::from_millis;
+        sniffer.update(&frame(0x100, &[0]), t(0));
+        sniffer.update(&frame(0x200, &[0]), t(0));
+        sniffer.update(&frame(0x300, &[0]), t(0));
+
+        assert_eq!(sniffer.toggle_freeze(0x100), Some(true));
+        assert_eq!(sniffer.toggle_mark(0x200), Some(true));
+        assert_eq!(sniffer.toggle_mark(0x400), None);
+
+        // Frozen rows ignore frames.
+        sniffer.update(&frame(0x100, &[1]), t(100));
+        assert_eq!(sniffer.get(0x100).unwrap().data, [0]);
+        assert_eq!(sniffer.get(0x100).unwrap().count, 1);
+
+        sniffer.clear();
+        let ids: Vec<u32> = sniffer.rows().map(|row| row.id).collect();
+        assert_eq!(ids, [0x100, 0x200]);
+
+        assert_eq!(sniffer.toggle_freeze(0x100), Some(false));
+        sniffer.update(&frame(0x100, &[1]), t(100));
+        assert_eq!(sniffer.get(0x100).unwrap().data, [1]);
+    }
+}
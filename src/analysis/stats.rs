@@ -0,0 +1,398 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! [`BusStats`] collects per-id statistics over any stream of [`Frame`]s:
+//! message count, period and jitter, DLC distribution, per-byte ranges, and
+//! how many frames [`Event::parse`] could handle. Useful for spotting
+//! decoders that are wrong (eg. a value that never changes).
+
+use std::{collections::BTreeMap, fmt};
+
+use crate::{events::ParseError, Event, Frame, Timestamp};
+
+/// Statistics for a single byte position of an id.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ByteStats {
+    /// Smallest value seen.
+    pub min: u8,
+    /// Largest value seen.
+    pub max: u8,
+    /// Number of distinct values seen.
+    pub distinct: u16,
+}
+
+/// How the frames from an id (or the whole bus) were handled by
+/// [`Event::parse`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseCounts {
+    /// Parsed into one or more [`Event`]s.
+    pub parsed: u64,
+    /// [`ParseError::Id`] (no decoder for the id).
+    pub unknown_id: u64,
+    /// Any other [`ParseError`] (bad length or unrecognized data).
+    pub errors: u64,
+}
+
+impl ParseCounts {
+    /// Total frames counted.
+    pub const fn total(&self) -> u64 {
+        self.parsed + self.unknown_id + self.errors
+    }
+
+    /// Fraction (0-1) of frames that parsed, or `None` if there were none.
+    pub fn parsed_share(&self) -> Option<f64> {
+        match self.total() {
+            0 => None,
+            total => Some(self.parsed as f64 / total as f64),
+        }
+    }
+
+    fn add(&mut self, result: &Result<(), ParseError>) {
+        match result {
+            Ok(()) => self.parsed += 1,
+            Err(ParseError::Id { .. }) => self.unknown_id += 1,
+            Err(_) => self.errors += 1,
+        }
+    }
+}
+
+/// Statistics for a single id.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdStats {
+    /// The frame id.
+    pub id: u32,
+    /// Number of frames.
+    pub count: u64,
+    /// When the first frame was seen.
+    pub first_seen: Timestamp,
+    /// When the last frame was seen.
+    pub last_seen: Timestamp,
+    /// Mean time between frames in milliseconds, if there were at least two.
+    pub mean_period_ms: Option<f64>,
+    /// Standard deviation of the time between frames in milliseconds, if
+    /// there were at least two.
+    pub jitter_ms: Option<f64>,
+    /// Number of frames with each DLC (index 0-8).
+    pub dlc: [u64; 9],
+    /// Statistics for each byte position seen.
+    pub bytes: Vec<ByteStats>,
+    /// How the frames were handled by [`Event::parse`].
+    pub parse: ParseCounts,
+}
+
+/// The statistics for a whole bus, as returned by [`BusStats::report`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BusReport {
+    /// Statistics for each id, sorted by id.
+    pub ids: Vec<IdStats>,
+    /// How all frames were handled by [`Event::parse`].
+    pub parse: ParseCounts,
+}
+
+impl fmt::Display for BusReport {
+    /// Format the report as a table.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |value: Option<f64>| match value {
+            Some(value) => format!("{value:.1}"),
+            None => "-".to_owned(),
+        };
+
+        writeln!(
+            f,
+            "{:>5} {:>8} {:>10} {:>9} {:>7} {:>7}  bytes (min-max/distinct)",
+            "id", "count", "period ms", "jitter ms", "dlc", "parsed"
+        )?;
+        for stats in &self.ids {
+            let dlcs: Vec<String> = stats
+                .dlc
+                .iter()
+                .enumerate()
+                .filter(|(_, count)| **count > 0)
+                .map(|(dlc, _)| dlc.to_string())
+                .collect();
+            let bytes: Vec<String> = stats
+                .bytes
+                .iter()
+                .map(|b| format!("{:02X}-{:02X}/{}", b.min, b.max, b.distinct))
+                .collect();
+            let parsed = match stats.parse.parsed_share() {
+                Some(share) => format!("{:.0}%", share * 100.0),
+                None => "-".to_owned(),
+            };
+            writeln!(
+                f,
+                "{:>#5X} {:>8} {:>10} {:>9} {:>7} {:>7}  {}",
+                stats.id,
+                stats.count,
+                ms(stats.mean_period_ms),
+                ms(stats.jitter_ms),
+                dlcs.join(","),
+                parsed,
+                bytes.join(" ")
+            )?;
+        }
+        write!(
+            f,
+            "{} frames: {} parsed, {} unknown id, {} other errors",
+            self.parse.total(),
+            self.parse.parsed,
+            self.parse.unknown_id,
+            self.parse.errors
+        )
+    }
+}
+
+/// Running statistics for a single byte position.
+#[derive(Debug, Clone)]
+struct ByteAccumulator {
+    min: u8,
+    max: u8,
+    /// Bitset of every value seen.
+    seen: [u64; 4],
+}
+
+impl ByteAccumulator {
+    fn new(value: u8) -> Self {
+        let mut acc = Self {
+            min: value,
+            max: value,
+            seen: [0; 4],
+        };
+        acc.add(value);
+        acc
+    }
+
+    fn add(&mut self, value: u8) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.seen[usize::from(value / 64)] |= 1 << (value % 64);
+    }
+
+    fn stats(&self) -> ByteStats {
+        ByteStats {
+            min: self.min,
+            max: self.max,
+            distinct: self
+                .seen
+                .iter()
+                .map(|bits| bits.count_ones() as u16)
+                .sum(),
+        }
+    }
+}
+
+/// Running statistics for a single id.
+#[derive(Debug, Clone)]
+struct IdAccumulator {
+    count: u64,
+    first_seen: Timestamp,
+    last_seen: Timestamp,
+    /// Welford's running mean and sum of squares of periods, in seconds.
+    period_mean: f64,
+    period_m2: f64,
+    dlc: [u64; 9],
+    bytes: Vec<ByteAccumulator>,
+    parse: ParseCounts,
+}
+
+impl IdAccumulator {
+    fn new(at: Timestamp) -> Self {
+        Self {
+            count: 0,
+            first_seen: at,
+            last_seen: at,
+            period_mean: 0.0,
+            period_m2: 0.0,
+            dlc: [0; 9],
+            bytes: Vec::new(),
+            parse: ParseCounts::default(),
+        }
+    }
+
+    fn add(&mut self, frame: &Frame, at: Timestamp) {
+        if self.count > 0 {
+            let period = at.saturating_sub(self.last_seen).as_secs_f64();
+            // `count - 1` periods have been seen before this one.
+            let n = self.count as f64;
+            let delta = period - self.period_mean;
+            self.period_mean += delta / n;
+            self.period_m2 += delta * (period - self.period_mean);
+        }
+        self.count += 1;
+        self.last_seen = at;
+
+        let data = frame.data();
+        self.dlc[data.len()] += 1;
+        for (i, &value) in data.iter().enumerate() {
+            match self.bytes.get_mut(i) {
+                Some(byte) => byte.add(value),
+                None => self.bytes.push(ByteAccumulator::new(value)),
+            }
+        }
+    }
+
+    fn stats(&self, id: u32) -> IdStats {
+        let periods = self.count.saturating_sub(1);
+        let (mean_period_ms, jitter_ms) = if periods == 0 {
+            (None, None)
+        } else {
+            let variance = self.period_m2 / periods as f64;
+            (
+                Some(self.period_mean * 1000.0),
+                Some(variance.sqrt() * 1000.0),
+            )
+        };
+
+        IdStats {
+            id,
+            count: self.count,
+            first_seen: self.first_seen,
+            last_seen: self.last_seen,
+            mean_period_ms,
+            jitter_ms,
+            dlc: self.dlc,
+            bytes: self.bytes.iter().map(ByteAccumulator::stats).collect(),
+            parse: self.parse,
+        }
+    }
+}
+
+/// Collects statistics for every id on a bus.
+#[derive(Debug, Clone, Default)]
+pub struct BusStats {
+    ids: BTreeMap<u32, IdAccumulator>,
+    parse: ParseCounts,
+}
+
+impl BusStats {
+    /// Create a new, empty, [`BusStats`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Update with a `frame` received `at` some time.
+    pub fn update(&mut self, frame: &Frame, at: Timestamp) {
+        let parsed = Event::parse(frame.clone()).map(|_| ());
+        self.parse.add(&parsed);
+
+        let acc = self
+            .ids
+            .entry(frame.id())
+            .or_insert_with(|| IdAccumulator::new(at));
+        acc.parse.add(&parsed);
+        acc.add(frame, at);
+    }
+
+    /// Statistics for a single `id`, if it was seen.
+    pub fn get(&self, id: u32) -> Option<IdStats> {
+        self.ids.get(&id).map(|acc| acc.stats(id))
+    }
+
+    /// A [`BusReport`] of everything seen so far.
+    pub fn report(&self) -> BusReport {
+        BusReport {
+            ids: self.ids.iter().map(|(&id, acc)| acc.stats(id)).collect(),
+            parse: self.parse,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn frame(id: u32, data: &[u8]) -> Frame {
+        Frame::from_id_slice(id, data).unwrap()
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut stats = BusStats::new();
+        let t = Duration::from_millis;
+
+        // Periods of 100, 100, 200ms (mean 133.3, std dev 47.1)
+        stats.update(&frame(0x7ff, &[1, 0xff]), t(0));
+        stats.update(&frame(0x7ff, &[2, 0xff]), t(100));
+        stats.update(&frame(0x7ff, &[2, 0xff]), t(200));
+        stats.update(&frame(0x7ff, &[9]), t(400));
+
+        let id = stats.get(0x7ff).unwrap();
+        assert_eq!(id.count, 4);
+        assert_eq!(id.first_seen, t(0));
+        assert_eq!(id.last_seen, t(400));
+        assert!((id.mean_period_ms.unwrap() - 133.333).abs() < 0.01);
+        assert!((id.jitter_ms.unwrap() - 47.140).abs() < 0.01);
+        assert_eq!(id.dlc[1], 1);
+        assert_eq!(id.dlc[2], 3);
+        assert_eq!(
+            id.bytes,
+            [
+                ByteStats {
+                    min: 1,
+                    max: 9,
+                    distinct: 3
+                },
+                ByteStats {
+                    min: 0xff,
+                    max: 0xff,
+                    distinct: 1
+                }
+            ]
+        );
+        assert_eq!(id.parse.unknown_id, 4);
+        assert!(stats.get(0x123).is_none());
+    }
+
+    #[test]
+    fn test_parse_counts() {
+        let mut stats = BusStats::new();
+        let t = Duration::from_millis;
+
+        // Camera: parsed, bad length, unknown id.
+        stats.update(&frame(0x302, &[0, 7, 0, 0, 0, 0, 0, 0]), t(0));
+        stats.update(&frame(0x302, &[0]), t(1));
+        stats.update(&frame(0x7ff, &[0]), t(2));
+
+        let report = stats.report();
+        assert_eq!(
+            report.parse,
+            ParseCounts {
+                parsed: 1,
+                unknown_id: 1,
+                errors: 1
+            }
+        );
+        assert_eq!(report.ids.len(), 2);
+        assert_eq!(report.ids[0].parse.parsed_share(), Some(0.5));
+
+        let table = report.to_string();
+        assert!(table.contains("0x302"));
+        assert!(
+            table.ends_with("3 frames: 1 parsed, 1 unknown id, 1 other errors")
+        );
+    }
+}
@@ -0,0 +1,308 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! An [`UnknownCatalog`] that gathers every unknown id and unrecognized value
+//! that the decoders rejected, instead of printing and forgetting them. The
+//! [`UnknownReport`] can be attached to an issue (as Markdown or JSON) so the
+//! decoders can be extended.
+
+use std::{collections::BTreeMap, fmt, ops::Range};
+
+use crate::{events::ParseError, Frame, Timestamp};
+
+/// Which bytes hold the value a decoder matches on, for decoders that reject
+/// unrecognized values. For other ids, all of the data is used.
+const VALUE_BYTES: &[(u32, Range<usize>)] = &[
+    // `Ignition`: the whole u32
+    (0x122, 0..4),
+    // `Remote`: the first byte
+    (0x1c0, 0..1),
+    // `Camera`: the first byte
+    (0x302, 0..1),
+    // `Bus::Wake`: bytes 4 and 5
+    (0x401, 4..6),
+];
+
+/// The bytes of `frame` that a decoder matches on.
+fn value_bytes(frame: &Frame) -> Vec<u8> {
+    let data = frame.data();
+    VALUE_BYTES
+        .iter()
+        .find(|(id, _)| *id == frame.id())
+        .and_then(|(_, range)| data.get(range.clone()))
+        .unwrap_or(data)
+        .to_vec()
+}
+
+/// What was unrecognized.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Unknown {
+    /// An id with no decoder.
+    Id,
+    /// A length the decoder didn't expect.
+    Len {
+        /// The length seen.
+        len: usize,
+        /// The length expected.
+        expected: usize,
+    },
+    /// A value the decoder didn't recognize.
+    Value {
+        /// The bytes the decoder matches on (eg. the first byte for `Remote`).
+        bytes: Vec<u8>,
+    },
+}
+
+impl fmt::Display for Unknown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unknown::Id => write!(f, "unknown id"),
+            Unknown::Len { len, expected } => {
+                write!(f, "length {len} (expected {expected})")
+            }
+            Unknown::Value { bytes } => {
+                write!(f, "value ")?;
+                for byte in bytes {
+                    write!(f, "{byte:02X}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A single entry of an [`UnknownReport`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// The frame id.
+    pub id: u32,
+    /// What was unrecognized.
+    pub unknown: Unknown,
+    /// How many times it was seen.
+    pub count: u64,
+    /// When it was first seen.
+    pub first_seen: Timestamp,
+    /// When it was last seen.
+    pub last_seen: Timestamp,
+    /// The decoder's detail message (from the first occurrence), if any.
+    pub detail: Option<String>,
+    /// A few distinct example frames, as `ID#DATA` (like `candump`).
+    pub examples: Vec<String>,
+}
+
+/// Everything gathered by an [`UnknownCatalog`], sorted by id. Display
+/// formats it as Markdown, ready to paste into an issue.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownReport {
+    /// Version of this crate that produced the report.
+    pub version: String,
+    /// Every unknown id and value.
+    pub entries: Vec<Entry>,
+}
+
+impl fmt::Display for UnknownReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Unknown frames (jeep {})", self.version)?;
+        writeln!(f)?;
+        writeln!(f, "| id | unknown | count | detail | examples |")?;
+        writeln!(f, "|----|---------|-------|--------|----------|")?;
+        for entry in &self.entries {
+            writeln!(
+                f,
+                "| {:#05X} | {} | {} | {} | {} |",
+                entry.id,
+                entry.unknown,
+                entry.count,
+                entry.detail.as_deref().unwrap_or("").replace('|', "\\|"),
+                entry.examples.join("<br>")
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Gathers unique unknown ids and unrecognized values from [`ParseError`]s.
+#[derive(Debug, Clone)]
+pub struct UnknownCatalog {
+    /// How many distinct example frames to keep per entry.
+    max_examples: usize,
+    entries: BTreeMap<(u32, Unknown), Entry>,
+}
+
+impl Default for UnknownCatalog {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+impl UnknownCatalog {
+    /// Create a new, empty, catalog keeping up to `max_examples` distinct
+    /// example frames for each entry.
+    pub fn new(max_examples: usize) -> Self {
+        Self {
+            max_examples,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Add a [`ParseError`] that happened `at` some time.
+    pub fn add(&mut self, error: &ParseError, at: Timestamp) {
+        let (frame, unknown, detail) = match error {
+            ParseError::Id { frame } => (frame, Unknown::Id, None),
+            ParseError::Len { frame, expected } => (
+                frame,
+                Unknown::Len {
+                    len: frame.data().len(),
+                    expected: *expected,
+                },
+                None,
+            ),
+            ParseError::Data { frame, detail } => (
+                frame,
+                Unknown::Value {
+                    bytes: value_bytes(frame),
+                },
+                Some(detail),
+            ),
+        };
+
+        let entry = self
+            .entries
+            .entry((frame.id(), unknown.clone()))
+            .or_insert_with(|| Entry {
+                id: frame.id(),
+                unknown,
+                count: 0,
+                first_seen: at,
+                last_seen: at,
+                detail: detail.cloned(),
+                examples: Vec::new(),
+            });
+        entry.count += 1;
+        entry.last_seen = at;
+
+        if entry.examples.len() < self.max_examples {
+            let example = frame.to_candump();
+            if !entry.examples.contains(&example) {
+                entry.examples.push(example);
+            }
+        }
+    }
+
+    /// Number of distinct unknown ids and values.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if nothing unknown has been seen.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// An [`UnknownReport`] of everything gathered so far.
+    pub fn report(&self) -> UnknownReport {
+        UnknownReport {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            entries: self.entries.values().cloned().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Event;
+    use std::time::Duration;
+
+    fn add(catalog: &mut UnknownCatalog, id: u32, data: &[u8], secs: u64) {
+        let frame = Frame::from_id_slice(id, data).unwrap();
+        let error = Event::parse(frame).unwrap_err();
+        catalog.add(&error, Duration::from_secs(secs));
+    }
+
+    #[test]
+    fn test_catalog() {
+        let mut catalog = UnknownCatalog::new(2);
+
+        // Unknown id, with 3 distinct frames (only 2 examples kept).
+        add(&mut catalog, 0x7ff, &[1], 0);
+        add(&mut catalog, 0x7ff, &[2], 1);
+        add(&mut catalog, 0x7ff, &[3], 2);
+        add(&mut catalog, 0x7ff, &[1], 3);
+        // Unrecognized remote byte, with different trailing bytes.
+        add(&mut catalog, 0x1c0, &[0x99, 0, 0, 0, 0, 0], 4);
+        add(&mut catalog, 0x1c0, &[0x99, 1, 0, 0, 0, 0], 5);
+        // Another unrecognized remote byte.
+        add(&mut catalog, 0x1c0, &[0x98, 0, 0, 0, 0, 0], 6);
+        // Wrong length.
+        add(&mut catalog, 0x1c0, &[0x21], 7);
+
+        let report = catalog.report();
+        assert_eq!(catalog.len(), 4);
+        let entries = &report.entries;
+
+        assert_eq!(entries[0].id, 0x1c0);
+        assert_eq!(
+            entries[0].unknown,
+            Unknown::Len {
+                len: 1,
+                expected: 6
+            }
+        );
+        assert_eq!(entries[1].unknown, Unknown::Value { bytes: vec![0x98] });
+        assert_eq!(entries[2].unknown, Unknown::Value { bytes: vec![0x99] });
+        assert_eq!(entries[2].count, 2);
+        assert_eq!(entries[2].first_seen, Duration::from_secs(4));
+        assert_eq!(entries[2].last_seen, Duration::from_secs(5));
+        assert_eq!(
+            entries[2].examples,
+            ["1C0#990000000000", "1C0#990100000000"]
+        );
+        assert!(entries[2].detail.is_some());
+
+        assert_eq!(entries[3].unknown, Unknown::Id);
+        assert_eq!(entries[3].count, 4);
+        assert_eq!(entries[3].examples, ["7FF#01", "7FF#02"]);
+
+        let markdown = report.to_string();
+        assert!(markdown
+            .contains("| 0x7FF | unknown id | 4 |  | 7FF#01<br>7FF#02 |"));
+        assert!(markdown.contains("| 0x1C0 | value 99 | 2 |"));
+    }
+
+    #[test]
+    fn test_value_bytes() {
+        let frame = |id, data: &[u8]| Frame::from_id_slice(id, data).unwrap();
+
+        assert_eq!(
+            value_bytes(&frame(0x401, &[0, 0, 0, 0, 0x0c, 0x08, 0, 0])),
+            [0x0c, 0x08]
+        );
+        assert_eq!(value_bytes(&frame(0x122, &[1, 2, 3, 4])), [1, 2, 3, 4]);
+        // Too short for the range, so everything.
+        assert_eq!(value_bytes(&frame(0x401, &[1])), [1]);
+    }
+}
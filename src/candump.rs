@@ -0,0 +1,295 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Read and write `candump -L` style logs, eg:
+//!
+//! ```text
+//! (1436509052.249713) can0 2FA#2000000000000000
+//! ```
+//!
+//! so that anything that consumes [`Event`]s can be tested against (or
+//! replayed from) a recorded drive.
+
+use std::{fmt, io::BufRead, str::FromStr, time::Duration};
+
+use crate::{
+    events::{OneOrMany, ParseError},
+    frame::{BadLen, ParseFrameError},
+    Event, Frame, Timestamp,
+};
+
+/// An error reading a `candump -L` log.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading the log failed.
+    Io(std::io::Error),
+    /// A line is not in `candump -L` format.
+    Syntax {
+        /// The offending line.
+        line: String,
+    },
+    /// A line has more than 8 bytes of data.
+    BadLen(BadLen),
+    /// A [`Frame`] was read, but it's not a valid [`Event`].
+    ParseError(ParseError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "{err}"),
+            Error::Syntax { line } => write!(f, "not a candump line: {line}"),
+            Error::BadLen(err) => write!(f, "{err}"),
+            Error::ParseError(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::BadLen(err) => Some(err),
+            Error::ParseError(err) => Some(err),
+            Error::Syntax { .. } => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// A single line of a `candump -L` log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    /// When the frame was received (since the Unix epoch).
+    pub timestamp: Timestamp,
+    /// The interface the frame was received on (eg. `can0`).
+    pub interface: String,
+    /// The received frame.
+    pub frame: Frame,
+}
+
+impl FromStr for Record {
+    type Err = Error;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let syntax = || Error::Syntax {
+            line: line.to_owned(),
+        };
+
+        let mut parts = line.split_whitespace();
+        let (timestamp, interface, frame) =
+            match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                (Some(t), Some(i), Some(f), None) => (t, i, f),
+                _ => return Err(syntax()),
+            };
+
+        // (seconds.fraction)
+        let (secs, fraction) = timestamp
+            .strip_prefix('(')
+            .and_then(|t| t.strip_suffix(')'))
+            .and_then(|t| t.split_once('.'))
+            .ok_or_else(syntax)?;
+        if fraction.is_empty()
+            || fraction.len() > 9
+            || !fraction.bytes().all(|b| b.is_ascii_digit())
+        {
+            return Err(syntax());
+        }
+        let secs: u64 = secs.parse().map_err(|_| syntax())?;
+        let nanos = fraction.parse::<u32>().map_err(|_| syntax())?
+            * 10u32.pow(9 - fraction.len() as u32);
+        let timestamp = Duration::new(secs, nanos);
+
+        // ID#DATA
+        let frame = match frame.parse::<Frame>() {
+            Ok(frame) => frame,
+            Err(ParseFrameError::Syntax) => return Err(syntax()),
+            Err(ParseFrameError::BadLen(err)) => {
+                return Err(Error::BadLen(err))
+            }
+        };
+
+        Ok(Self {
+            timestamp,
+            interface: interface.to_owned(),
+            frame,
+        })
+    }
+}
+
+impl fmt::Display for Record {
+    /// Format the [`Record`] as a `candump -L` line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "({}.{:06}) {} {}",
+            self.timestamp.as_secs(),
+            self.timestamp.subsec_micros(),
+            self.interface,
+            self.frame.to_candump()
+        )
+    }
+}
+
+/// An [`Iterator`] through the [`Record`]s of a `candump -L` log. Blank lines
+/// are skipped. Remote and CAN FD frames aren't supported and are returned as
+/// [`Error::Syntax`], after which reading can continue.
+pub struct Records<R> {
+    lines: std::io::Lines<R>,
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = Result<Record, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err.into())),
+            };
+            if !line.trim().is_empty() {
+                return Some(line.parse());
+            }
+        }
+    }
+}
+
+/// Read the [`Record`]s from a `candump -L` log.
+pub fn records<R: BufRead>(reader: R) -> Records<R> {
+    Records {
+        lines: reader.lines(),
+    }
+}
+
+/// An [`Iterator`] through the timestamped [`Event`]s of a `candump -L` log.
+pub struct Events<R> {
+    records: Records<R>,
+    pending: Vec<(Timestamp, Event)>,
+}
+
+impl<R: BufRead> Iterator for Events<R> {
+    type Item = Result<(Timestamp, Event), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(pending) = self.pending.pop() {
+            return Some(Ok(pending));
+        }
+        let record = match self.records.next()? {
+            Ok(record) => record,
+            Err(err) => return Some(Err(err)),
+        };
+        match Event::parse(record.frame) {
+            Ok(OneOrMany::One(event)) => Some(Ok((record.timestamp, event))),
+            Ok(OneOrMany::Many(events)) => {
+                // Reversed so they're popped in order.
+                self.pending = events
+                    .into_iter()
+                    .rev()
+                    .map(|event| (record.timestamp, event))
+                    .collect();
+                self.next()
+            }
+            Err(err) => Some(Err(Error::ParseError(err))),
+        }
+    }
+}
+
+/// Read the timestamped [`Event`]s from a `candump -L` log.
+pub fn events<R: BufRead>(reader: R) -> Events<R> {
+    Events {
+        records: records(reader),
+        pending: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{doors::Doors, locks::Locks};
+
+    #[test]
+    fn test_parse_and_display() {
+        let line = "(1436509052.249713) vcan0 044#2A366C2BBA";
+        let record: Record = line.parse().unwrap();
+        assert_eq!(record.timestamp, Duration::new(1436509052, 249713000));
+        assert_eq!(record.interface, "vcan0");
+        assert_eq!(record.frame.id(), 0x44);
+        assert_eq!(record.frame.data(), &[0x2A, 0x36, 0x6C, 0x2B, 0xBA]);
+        assert_eq!(record.to_string(), line);
+
+        // Empty data is fine.
+        let record: Record = "(0.000001) can0 123#".parse().unwrap();
+        assert!(record.frame.data().is_empty());
+
+        for bad in [
+            "",
+            "(1.0) can0",
+            "1.0 can0 123#00",
+            "(1.0) can0 123#0",
+            "(1.0) can0 123#zz",
+            "(1.0) can0 123 00",
+            // Remote and CAN FD frames aren't supported.
+            "(1.0) can0 123#R",
+            "(1.0) can0 123##100",
+        ] {
+            assert!(
+                matches!(bad.parse::<Record>(), Err(Error::Syntax { .. })),
+                "{bad}"
+            );
+        }
+        assert!(matches!(
+            "(1.0) can0 123#000000000000000000".parse::<Record>(),
+            Err(Error::BadLen(_))
+        ));
+    }
+
+    #[test]
+    fn test_events() {
+        let log = "\
+            (1.000000) can0 2FA#2000000000000000\n\
+            \n\
+            (2.000000) can0 7FF#00\n";
+        let mut events = events(log.as_bytes());
+
+        assert!(matches!(
+            events.next(),
+            Some(Ok((_, Event::Doors(Doors::SWING_GATE))))
+        ));
+        // Lights are in the same frame.
+        assert!(matches!(events.next(), Some(Ok((_, Event::Lights(_))))));
+        assert!(matches!(events.next(), Some(Ok((_, Event::Lights(_))))));
+        match events.next() {
+            Some(Ok((t, Event::Locks(locks)))) => {
+                assert_eq!(t, Duration::from_secs(1));
+                assert_eq!(locks, Locks::empty());
+            }
+            other => panic!("expected locks, got {other:?}"),
+        }
+        assert!(matches!(events.next(), Some(Err(Error::ParseError(_)))));
+        assert!(events.next().is_none());
+    }
+}
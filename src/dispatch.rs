@@ -0,0 +1,381 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! A callback based [`Dispatcher`] where handlers register for the type of
+//! sub-event they care about, instead of every tool writing the same
+//! `for message in listener.messages() { match ... }` loop.
+//!
+//! ```
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//! use std::sync::Arc;
+//! use jeep::{
+//!     dispatch::Dispatcher,
+//!     events::{doors::Doors, remote::Remote},
+//!     Event,
+//! };
+//!
+//! let opened = Arc::new(AtomicUsize::new(0));
+//! let counter = opened.clone();
+//!
+//! let mut dispatcher = Dispatcher::new();
+//! dispatcher
+//!     .on::<Doors>(move |doors| {
+//!         if doors.any_open() {
+//!             counter.fetch_add(1, Ordering::Relaxed);
+//!         }
+//!     })
+//!     .on::<Remote>(|remote| println!("{remote}"))
+//!     .on_error(|err: &String| eprintln!("{err}"));
+//!
+//! dispatcher.run(vec![
+//!     Ok(Event::Doors(Doors::DRIVER)),
+//!     Err("something went wrong".to_owned()),
+//!     Ok(Event::Doors(Doors::empty())),
+//! ]);
+//! assert_eq!(opened.load(Ordering::Relaxed), 1);
+//! ```
+//!
+//! Handlers run on the calling thread with [`Dispatcher::dispatch`] and
+//! [`Dispatcher::run`], or on a [`WorkerPool`] with
+//! [`Dispatcher::into_pool`].
+//!
+//! # Panics
+//!
+//! Panics in handlers are not caught. Handle errors inside a handler (eg. by
+//! logging them) rather than panicking, since with `panic = 'abort'` (like
+//! this crate's release profile) a panic ends the whole process. When panics
+//! unwind, one from [`Dispatcher::dispatch`] or [`Dispatcher::run`] unwinds
+//! through the caller, and one on a [`WorkerPool`] ends that worker.
+
+use std::{
+    sync::{
+        mpsc::{sync_channel, SendError, SyncSender},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+};
+
+use crate::{
+    events::{
+        battery, bus, camera, control_panel, datetime, doors, engine, force,
+        hvac, ignition, lights, locks, odometer, remote, steering_wheel,
+        EventKind,
+    },
+    Event,
+};
+
+/// A type that can be extracted from an [`Event`], so a handler can be
+/// registered for it with [`Dispatcher::on`].
+pub trait SubEvent {
+    /// The [`EventKind`] containing this type, or `None` if it's every kind
+    /// (ie. [`Event`] itself).
+    const KIND: Option<EventKind>;
+
+    /// Get `Self` from an [`Event`], if it contains one.
+    fn from_event(event: &Event) -> Option<&Self>;
+}
+
+impl SubEvent for Event {
+    const KIND: Option<EventKind> = None;
+
+    #[inline(always)] // because trivial
+    fn from_event(event: &Event) -> Option<&Self> {
+        Some(event)
+    }
+}
+
+/// Implement [`SubEvent`] for the type inside an [`Event`] variant.
+macro_rules! impl_sub_event {
+    ($($ty:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl SubEvent for $ty {
+                const KIND: Option<EventKind> = Some(EventKind::$variant);
+
+                #[inline]
+                fn from_event(event: &Event) -> Option<&Self> {
+                    match event {
+                        Event::$variant(inner) => Some(inner),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_sub_event!(
+    battery::Battery => Battery,
+    remote::Remote => Remote,
+    ignition::Ignition => Ignition,
+    steering_wheel::Buttons => SteeringWheel,
+    control_panel::ControlPanel => ControlPanel,
+    lights::Lights => Lights,
+    doors::Doors => Doors,
+    locks::Locks => Locks,
+    force::Force => Force,
+    camera::Camera => Camera,
+    engine::Engine => Engine,
+    hvac::HVAC => HVAC,
+    datetime::DateTime => DateTime,
+    odometer::Odometer => Odometer,
+    bus::Bus => Bus,
+);
+
+type EventHandler = Box<dyn Fn(&Event) + Send + Sync>;
+type ErrorHandler<E> = Box<dyn Fn(&E) + Send + Sync>;
+
+/// Dispatches [`Event`]s to typed handlers and errors to an error handler.
+///
+/// Handlers are `Fn + Send + Sync` so they can also be run from a
+/// [`WorkerPool`]. Use atomics or a [`Mutex`] for any state they change.
+pub struct Dispatcher<E> {
+    handlers: Vec<(Option<EventKind>, EventHandler)>,
+    error_handler: Option<ErrorHandler<E>>,
+}
+
+impl<E> Default for Dispatcher<E> {
+    fn default() -> Self {
+        Self {
+            handlers: Vec::new(),
+            error_handler: None,
+        }
+    }
+}
+
+impl<E> Dispatcher<E> {
+    /// Create a new [`Dispatcher`] with no handlers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a `handler` for a [`SubEvent`] type, eg. `on::<Doors>(...)`.
+    /// Use `on::<Event>(...)` to handle every [`Event`]. Handlers are called in
+    /// the order they were registered.
+    pub fn on<T: SubEvent>(
+        &mut self,
+        handler: impl Fn(&T) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.handlers.push((
+            T::KIND,
+            Box::new(move |event| {
+                if let Some(sub_event) = T::from_event(event) {
+                    handler(sub_event)
+                }
+            }),
+        ));
+        self
+    }
+
+    /// Set the `handler` for errors (eg. [`ParseError`] or IO errors from a
+    /// [`Listener`]). Without one, errors are ignored.
+    ///
+    /// [`ParseError`]: crate::events::ParseError
+    /// [`Listener`]: crate::Listener
+    pub fn on_error<F>(&mut self, handler: F) -> &mut Self
+    where
+        F: Fn(&E) + Send + Sync + 'static,
+    {
+        self.error_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Dispatch a single message to the relevant handlers on this thread.
+    pub fn dispatch(&self, message: Result<Event, E>) {
+        match message {
+            Ok(event) => {
+                let kind = event.kind();
+                for (_, handler) in self
+                    .handlers
+                    .iter()
+                    .filter(|(k, _)| k.is_none() || *k == Some(kind))
+                {
+                    handler(&event);
+                }
+            }
+            Err(err) => {
+                if let Some(handler) = &self.error_handler {
+                    handler(&err);
+                }
+            }
+        }
+    }
+
+    /// Dispatch every message from `messages` on this thread. With a blocking
+    /// [`Listener`](crate::Listener) this never returns.
+    pub fn run<I>(&self, messages: I)
+    where
+        I: IntoIterator<Item = Result<Event, E>>,
+    {
+        for message in messages {
+            self.dispatch(message);
+        }
+    }
+}
+
+impl<E: Send + 'static> Dispatcher<E> {
+    /// Move the [`Dispatcher`] onto a [`WorkerPool`] of `workers` threads
+    /// (at least one). Messages sent to the pool are handled by whichever
+    /// worker is free, so **with more than one worker, messages may be handled
+    /// out of order**.
+    pub fn into_pool(self, workers: usize) -> WorkerPool<E> {
+        let (sender, receiver) = sync_channel::<Result<Event, E>>(1024);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let dispatcher = Arc::new(self);
+
+        let workers = (0..workers.max(1))
+            .map(|_| {
+                let receiver = receiver.clone();
+                let dispatcher = dispatcher.clone();
+                std::thread::spawn(move || loop {
+                    // Only hold the lock while receiving, not while handling.
+                    let message = match receiver.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        // Another worker panicked while holding the lock,
+                        // which can't happen since handlers run without it.
+                        Err(_) => return,
+                    };
+                    match message {
+                        Ok(message) => dispatcher.dispatch(message),
+                        // The pool was joined, so we're done.
+                        Err(_) => return,
+                    }
+                })
+            })
+            .collect();
+
+        WorkerPool {
+            sender: Some(sender),
+            workers,
+        }
+    }
+}
+
+/// Handles messages with a [`Dispatcher`] on background threads. Dropping the
+/// pool waits for every message already sent to be handled.
+pub struct WorkerPool<E> {
+    sender: Option<SyncSender<Result<Event, E>>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl<E> WorkerPool<E> {
+    /// Send a message to be handled by the pool. Blocks if the pool is too
+    /// far behind.
+    pub fn send(
+        &self,
+        message: Result<Event, E>,
+    ) -> Result<(), SendError<Result<Event, E>>> {
+        match &self.sender {
+            Some(sender) => sender.send(message),
+            None => Err(SendError(message)),
+        }
+    }
+
+    /// Wait for every message already sent to be handled and stop the
+    /// workers.
+    pub fn join(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        // Dropping the sender makes `recv` fail once the channel is empty.
+        self.sender.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl<E> Drop for WorkerPool<E> {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{
+        doors::Doors,
+        remote::{Remote, RemoteSource},
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+    #[test]
+    fn test_typed_handlers() {
+        let doors = Arc::new(AtomicUsize::new(0));
+        let remotes = Arc::new(AtomicUsize::new(0));
+        let events = Arc::new(AtomicUsize::new(0));
+        let errors = Arc::new(AtomicUsize::new(0));
+
+        let mut dispatcher = Dispatcher::new();
+        let (d, r, e, err) = (
+            doors.clone(),
+            remotes.clone(),
+            events.clone(),
+            errors.clone(),
+        );
+        dispatcher
+            .on::<Doors>(move |_| {
+                d.fetch_add(1, Relaxed);
+            })
+            .on::<Remote>(move |_| {
+                r.fetch_add(1, Relaxed);
+            })
+            .on::<Event>(move |_| {
+                e.fetch_add(1, Relaxed);
+            })
+            .on_error(move |_: &()| {
+                err.fetch_add(1, Relaxed);
+            });
+
+        dispatcher.run([
+            Ok(Event::Doors(Doors::empty())),
+            Ok(Event::Remote(Remote::LockFrom(RemoteSource::KeyFob))),
+            Ok(Event::Remote(Remote::Idle)),
+            Err(()),
+        ]);
+
+        assert_eq!(doors.load(Relaxed), 1);
+        assert_eq!(remotes.load(Relaxed), 2);
+        assert_eq!(events.load(Relaxed), 3);
+        assert_eq!(errors.load(Relaxed), 1);
+    }
+
+    #[test]
+    fn test_worker_pool() {
+        let handled = Arc::new(AtomicUsize::new(0));
+
+        let mut dispatcher = Dispatcher::<()>::new();
+        let h = handled.clone();
+        dispatcher.on::<Doors>(move |_| {
+            h.fetch_add(1, Relaxed);
+        });
+
+        let pool = dispatcher.into_pool(4);
+        for _ in 0..100 {
+            pool.send(Ok(Event::Doors(Doors::empty()))).unwrap();
+        }
+        pool.join();
+
+        assert_eq!(handled.load(Relaxed), 100);
+    }
+}
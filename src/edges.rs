@@ -0,0 +1,340 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Press and release [`Edge`] detection for button [`bitflags`].
+//!
+//! [`steering_wheel::Buttons`], [`control_panel::Buttons`] and
+//! [`control_panel::Warmers`] report the set of buttons *currently held* in
+//! every frame. An [`EdgeDetector`] diffs successive states from each source
+//! and yields when individual buttons are [`Edge::Pressed`] and
+//! [`Edge::Released`].
+//!
+//! ```
+//! use std::time::Duration;
+//! use jeep::{
+//!     edges::{ButtonEdge, Edge, EdgeDetector},
+//!     events::steering_wheel::Buttons,
+//!     Event,
+//! };
+//!
+//! let mut detector = EdgeDetector::default();
+//! let t0 = Duration::ZERO;
+//! let t1 = Duration::from_millis(100);
+//!
+//! // The first state seen from a source is a baseline, so no edges.
+//! assert!(detector.update(&Event::SteeringWheel(Buttons::empty()), t0).is_empty());
+//!
+//! let edges = detector.update(&Event::SteeringWheel(Buttons::DPAD_UP), t1);
+//! assert_eq!(
+//!     edges,
+//!     vec![ButtonEdge::SteeringWheel(Edge::Pressed(Buttons::DPAD_UP), t1)]
+//! );
+//! ```
+//!
+//! [`steering_wheel::Buttons`]: crate::events::steering_wheel::Buttons
+//! [`control_panel::Buttons`]: crate::events::control_panel::Buttons
+//! [`control_panel::Warmers`]: crate::events::control_panel::Warmers
+
+use crate::{
+    events::{
+        control_panel::{self, ControlPanel},
+        steering_wheel,
+    },
+    Event, Timestamp,
+};
+
+/// Button [`bitflags`] where each individual button can be tested.
+pub trait ButtonFlags: Copy + PartialEq + std::fmt::Debug + 'static {
+    /// Every individual button flag (no masks).
+    const BUTTONS: &'static [Self];
+
+    /// Returns true if `button` is held in `self`.
+    fn is_held(self, button: Self) -> bool;
+
+    /// Returns `self` with only the bits in `mask`.
+    fn masked(self, mask: Self) -> Self;
+}
+
+impl ButtonFlags for steering_wheel::Buttons {
+    const BUTTONS: &'static [Self] = &[
+        Self::DPAD_LEFT,
+        Self::MYSTERY_BTN_0,
+        Self::DPAD_DOWN,
+        Self::MYSTERY_BTN_1,
+        Self::DPAD_UP,
+        Self::MYSTERY_BTN_2,
+        Self::DPAD_RIGHT,
+        Self::MYSTERY_BTN_3,
+        Self::BACK_INPUT_BUTTON,
+        Self::MYSTERY_BTN_4,
+        Self::BACK_VOL_UP,
+        Self::BACK_VOL_DOWN,
+        Self::BACK_TRACK_SKIP,
+        Self::BACK_TRACK_REWIND,
+        Self::BACK_SEEK_BUTTON,
+        Self::MYSTERY_BTN_5,
+    ];
+
+    #[inline]
+    fn is_held(self, button: Self) -> bool {
+        self.contains(button)
+    }
+
+    #[inline]
+    fn masked(self, mask: Self) -> Self {
+        self.intersection(mask)
+    }
+}
+
+impl ButtonFlags for control_panel::Buttons {
+    const BUTTONS: &'static [Self] = &[
+        Self::TRACTION_CONTROL,
+        Self::RADIO_POWER,
+        Self::AC,
+        Self::RECIRCULATION,
+        Self::VENT_MODE,
+        Self::HVAC_POWER,
+        Self::AUTO,
+        Self::DRIVER_TEMP_UP,
+        Self::DRIVER_TEMP_DOWN,
+        Self::PASSENGER_TEMP_UP,
+        Self::PASSENGER_TEMP_DOWN,
+        Self::REAR_DEFROSTER,
+        Self::FRONT_DEFROSTER,
+        Self::MUTE,
+        Self::SCREEN,
+        Self::ESS_MAX_REGEN,
+    ];
+
+    #[inline]
+    fn is_held(self, button: Self) -> bool {
+        // NOTE: Every flag shares some high bits, so `contains` is the only
+        // correct test here. Testing bits individually would not be.
+        self.contains(button)
+    }
+
+    #[inline]
+    fn masked(self, mask: Self) -> Self {
+        self.intersection(mask)
+    }
+}
+
+impl ButtonFlags for control_panel::Warmers {
+    const BUTTONS: &'static [Self] = &[
+        Self::DRIVER_BUTT,
+        Self::PASSENGER_BUTT,
+        Self::STEERING_WHEEL,
+    ];
+
+    #[inline]
+    fn is_held(self, button: Self) -> bool {
+        self.contains(button)
+    }
+
+    #[inline]
+    fn masked(self, mask: Self) -> Self {
+        self.intersection(mask)
+    }
+}
+
+/// A single button changing state.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Edge<F> {
+    /// The button went from released to held.
+    Pressed(F),
+    /// The button went from held to released.
+    Released(F),
+}
+
+/// Tracks the last state of one source of [`ButtonFlags`] and reports
+/// [`Edge`]s.
+#[derive(Debug, Clone)]
+pub struct Edges<F> {
+    last: Option<F>,
+    mask: Option<F>,
+}
+
+impl<F> Default for Edges<F> {
+    fn default() -> Self {
+        Self {
+            last: None,
+            mask: None,
+        }
+    }
+}
+
+impl<F: ButtonFlags> Edges<F> {
+    /// Create new [`Edges`] that ignore any buttons not in `mask`.
+    pub fn with_mask(mask: F) -> Self {
+        Self {
+            last: None,
+            mask: Some(mask),
+        }
+    }
+
+    /// Update with the currently `held` buttons, returning an iterator of
+    /// every [`Edge`] since the last update. The very first update is a
+    /// baseline and yields no edges, since we can't know when buttons that
+    /// are already held were pressed.
+    pub fn update(&mut self, held: F) -> impl Iterator<Item = Edge<F>> {
+        let held = match self.mask {
+            Some(mask) => held.masked(mask),
+            None => held,
+        };
+        let last = self.last.replace(held).unwrap_or(held);
+
+        F::BUTTONS.iter().filter_map(move |&button| {
+            match (last.is_held(button), held.is_held(button)) {
+                (false, true) => Some(Edge::Pressed(button)),
+                (true, false) => Some(Edge::Released(button)),
+                _ => None,
+            }
+        })
+    }
+
+    /// Forget the last state, so the next update is a new baseline.
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+/// An [`Edge`] from any button source, along with the [`Timestamp`] of the
+/// frame in which the change was seen.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(PartialEq, Debug, Clone)]
+pub enum ButtonEdge {
+    /// [`steering_wheel::Buttons`] from `0x318`.
+    SteeringWheel(Edge<steering_wheel::Buttons>, Timestamp),
+    /// [`control_panel::Buttons`] from `0x2d3`.
+    ControlPanel(Edge<control_panel::Buttons>, Timestamp),
+    /// [`control_panel::Warmers`] from `0x2d4`.
+    Warmers(Edge<control_panel::Warmers>, Timestamp),
+}
+
+/// Detects press and release [`Edge`]s from every button source in a stream
+/// of [`Event`]s. Each source ID is tracked separately.
+#[derive(Debug, Clone, Default)]
+pub struct EdgeDetector {
+    steering_wheel: Edges<steering_wheel::Buttons>,
+    control_panel: Edges<control_panel::Buttons>,
+    warmers: Edges<control_panel::Warmers>,
+}
+
+impl EdgeDetector {
+    /// Create a new [`EdgeDetector`] that ignores the `MYSTERY_BTN_*` bits of
+    /// [`steering_wheel::Buttons`] (by applying
+    /// [`steering_wheel::Buttons::STOCK_BUTTONS`]).
+    pub fn stock_buttons_only() -> Self {
+        Self {
+            steering_wheel: Edges::with_mask(
+                steering_wheel::Buttons::STOCK_BUTTONS,
+            ),
+            ..Default::default()
+        }
+    }
+
+    /// Update with an [`Event`] received `at` some time, returning every
+    /// [`ButtonEdge`]. Events other than buttons are ignored.
+    pub fn update(&mut self, event: &Event, at: Timestamp) -> Vec<ButtonEdge> {
+        match event {
+            Event::SteeringWheel(held) => self
+                .steering_wheel
+                .update(*held)
+                .map(|edge| ButtonEdge::SteeringWheel(edge, at))
+                .collect(),
+            Event::ControlPanel(ControlPanel::Buttons(held)) => self
+                .control_panel
+                .update(*held)
+                .map(|edge| ButtonEdge::ControlPanel(edge, at))
+                .collect(),
+            Event::ControlPanel(ControlPanel::Warmers(held)) => self
+                .warmers
+                .update(*held)
+                .map(|edge| ButtonEdge::Warmers(edge, at))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use steering_wheel::Buttons;
+
+    #[test]
+    fn test_press_release() {
+        let mut edges = Edges::default();
+        assert_eq!(edges.update(Buttons::empty()).count(), 0);
+
+        let pressed: Vec<_> = edges
+            .update(Buttons::DPAD_UP | Buttons::MYSTERY_BTN_0)
+            .collect();
+        assert_eq!(
+            pressed,
+            [
+                Edge::Pressed(Buttons::MYSTERY_BTN_0),
+                Edge::Pressed(Buttons::DPAD_UP)
+            ]
+        );
+
+        let changed: Vec<_> = edges
+            .update(Buttons::DPAD_UP | Buttons::BACK_VOL_UP)
+            .collect();
+        assert_eq!(
+            changed,
+            [
+                Edge::Released(Buttons::MYSTERY_BTN_0),
+                Edge::Pressed(Buttons::BACK_VOL_UP)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stock_mask() {
+        let mut detector = EdgeDetector::stock_buttons_only();
+        let t = Duration::ZERO;
+        detector.update(&Event::SteeringWheel(Buttons::empty()), t);
+        let edges = detector.update(&Event::SteeringWheel(Buttons::all()), t);
+
+        assert_eq!(edges.len(), 10);
+        assert!(!edges.contains(&ButtonEdge::SteeringWheel(
+            Edge::Pressed(Buttons::MYSTERY_BTN_5),
+            t
+        )));
+    }
+
+    #[test]
+    fn test_control_panel_shared_bits() {
+        use control_panel::Buttons;
+
+        let mut edges = Edges::default();
+        // 0x07 is set in the high bits even when nothing is pressed.
+        let idle = Buttons::from_bits(0x07000000_00000000).unwrap();
+        assert_eq!(edges.update(idle).count(), 0);
+        let pressed: Vec<_> = edges.update(Buttons::MUTE).collect();
+
+        assert_eq!(pressed, [Edge::Pressed(Buttons::MUTE)]);
+    }
+}
@@ -21,13 +21,19 @@
 // SOFTWARE.
 
 use super::{Display, ParseError};
-use crate::frame::Frame;
+use crate::{
+    frame::Frame,
+    units::{Quantity, Ratio, UnitSystem},
+};
 
 /// The 12v (starter) battery under the hood that powers the "Aux" stuff.
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
 pub struct Aux([u8; 4]);
 impl Aux {
+    /// Create from the raw data from the frame.
+    pub const fn from_raw(raw: [u8; 4]) -> Self {
+        Self(raw)
+    }
     /// The raw data from the frame, the first two bytes of which are
     /// unidentified. Notes in spreadsheet say "Unknown. Charge? Load?".
     /// If you can figure out what they do, please write accessor methods
@@ -39,17 +45,35 @@ impl Aux {
     pub const fn raw_volts(self) -> u8 {
         self.0[2]
     }
+    /// The exact voltage of the Aux battery.
+    pub const fn voltage(self) -> Ratio {
+        Ratio::new(self.raw_volts() as i64, 10)
+    }
     /// The voltage of the Aux battery.
     pub fn volts(self) -> f32 {
-        f32::from(self.raw_volts()) / 100.0
+        self.voltage().to_f32()
+    }
+}
+
+impl Quantity for Aux {
+    const NATIVE: UnitSystem = UnitSystem::Metric;
+
+    fn value(self, _: UnitSystem) -> Ratio {
+        self.voltage()
+    }
+
+    fn symbol(_: UnitSystem) -> &'static str {
+        "V"
     }
 }
 
 impl std::fmt::Display for Aux {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("{}({:.2})", stringify!(Self), self.volts()))
+        std::fmt::Display::fmt(&self.display(Self::NATIVE), f)
     }
 }
+#[cfg(feature = "serde")]
+crate::units::impl_quantity_serde!(Aux, [u8; 4], "volts");
 
 impl TryFrom<Frame> for Aux {
     type Error = ParseError;
@@ -96,3 +120,22 @@ impl TryFrom<Frame> for Battery {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Aux, Battery, Frame};
+
+    #[test]
+    fn test_aux() {
+        // The third byte is 1/10ths of a volt.
+        let frame =
+            Frame::from_id_slice(0x2c2, &[0x00, 0x00, 126, 0x00]).unwrap();
+        let Battery::Aux(aux) = Battery::try_from(frame).unwrap();
+        assert_eq!(aux.raw_volts(), 126);
+        assert_eq!(aux.volts(), 12.6);
+        assert_eq!(aux.to_string(), "12.60 V");
+
+        let short = Frame::from_id_slice(0x2c2, &[0x00, 0x00, 126]).unwrap();
+        assert!(Aux::try_from(short).is_err());
+    }
+}
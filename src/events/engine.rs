@@ -25,17 +25,29 @@ use super::{
     OneOrMany::{Many, One},
     ParseError,
 };
+use crate::units::{Quantity, Ratio, UnitSystem, KM_PER_MILE};
 
-/// The Jeep's speed in legacy units. This can be converted to and from [`KPH`]
-/// losslessly with `from` and `into`.
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(PartialEq, Debug, Clone, Copy)]
+/// The Jeep's speed in legacy units, in 1/200ths of a mph. This can be
+/// converted to and from [`KPH`] losslessly with `from` and `into`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub struct MPH(pub(crate) u16);
 impl MPH {
+    /// Create from the raw u16 value, which is 200x the actual MPH.
+    pub const fn from_raw(raw: u16) -> Self {
+        Self(raw)
+    }
     /// The raw u16 value, which is 200x the actual MPH.
     pub const fn raw(self) -> u16 {
         self.0
     }
+    /// The exact speed in miles per hour.
+    pub const fn mph(self) -> Ratio {
+        Ratio::new(self.0 as i64, 200)
+    }
+    /// The exact speed in kilometers per hour.
+    pub fn kph(self) -> Ratio {
+        self.mph() * KM_PER_MILE
+    }
 }
 impl From<u8> for MPH {
     /// Convert from a single byte, which assumes an integer value (0-255).
@@ -46,25 +58,41 @@ impl From<u8> for MPH {
 impl From<MPH> for f32 {
     /// Convert the [`MPH`] into a [`f32`] value.
     fn from(mph: MPH) -> Self {
-        // FIXME(mdegans): use fractions in the future. It's likely cheaper and
-        // the float usage is just begging for some accumulation errors -- like
-        // Jeep may have done with the wildly innacurate MPG (at least on 4xE).
-        f32::from(mph.0) / 200.0
+        mph.mph().to_f32()
+    }
+}
+impl Quantity for MPH {
+    const NATIVE: UnitSystem = UnitSystem::Imperial;
+
+    fn value(self, system: UnitSystem) -> Ratio {
+        match system {
+            UnitSystem::Metric => self.kph(),
+            UnitSystem::Imperial => self.mph(),
+        }
+    }
+
+    fn symbol(system: UnitSystem) -> &'static str {
+        match system {
+            UnitSystem::Metric => "km/h",
+            UnitSystem::Imperial => "mph",
+        }
     }
 }
 impl std::fmt::Display for MPH {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let value: f32 = self.clone().into();
-        f.write_fmt(format_args!("MPH({:.2})", value))
+        std::fmt::Display::fmt(&self.display(Self::NATIVE), f)
     }
 }
+#[cfg(feature = "serde")]
+crate::units::impl_quantity_serde!(MPH, u16, "kph", "mph");
 
 impl TryFrom<Frame> for MPH {
     type Error = ParseError;
 
     fn try_from(frame: Frame) -> Result<Self, Self::Error> {
+        // This frame only has whole mph.
         if let Some(&value) = frame.data().get(7) {
-            Ok(Self(value.into()))
+            Ok(Self::from(value))
         } else {
             Err(ParseError::Len { frame, expected: 8 })
         }
@@ -72,13 +100,22 @@ impl TryFrom<Frame> for MPH {
 }
 
 /// The Jeep's speed in modern units.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub struct KPH(pub(crate) MPH);
 impl KPH {
+    /// Create from the raw u16 value, which is 200x the actual **MPH**.
+    pub const fn from_raw(raw: u16) -> Self {
+        Self(MPH(raw))
+    }
     /// The raw u16 value, which is 200x the actual **MPH**. Do not use this
     /// expecting KPH.
     pub const fn raw(self) -> u16 {
         self.0.raw()
     }
+    /// The exact speed in kilometers per hour.
+    pub fn kph(self) -> Ratio {
+        self.0.kph()
+    }
 }
 impl From<MPH> for KPH {
     /// Convert from [`MPH`] to [`KPH`] losslessly.
@@ -86,12 +123,36 @@ impl From<MPH> for KPH {
         KPH(mph)
     }
 }
+impl From<KPH> for MPH {
+    /// Convert from [`KPH`] to [`MPH`] losslessly.
+    fn from(kph: KPH) -> Self {
+        kph.0
+    }
+}
 impl From<KPH> for f32 {
     /// Convert from [`KPH`] into a [`f32`] value.
     fn from(kph: KPH) -> Self {
-        f32::from(kph.0) * 1.60934
+        kph.kph().to_f32()
     }
 }
+impl Quantity for KPH {
+    const NATIVE: UnitSystem = UnitSystem::Metric;
+
+    fn value(self, system: UnitSystem) -> Ratio {
+        self.0.value(system)
+    }
+
+    fn symbol(system: UnitSystem) -> &'static str {
+        MPH::symbol(system)
+    }
+}
+impl std::fmt::Display for KPH {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.display(Self::NATIVE), f)
+    }
+}
+#[cfg(feature = "serde")]
+crate::units::impl_quantity_serde!(KPH, u16, "kph", "mph");
 
 /// The Jeep's [`Engine`] rpms.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -196,3 +257,50 @@ impl From<OneOrMany<Engine>> for OneOrMany<Event> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Engine, Frame, OneOrMany, MPH};
+
+    #[test]
+    fn test_gps_speed() {
+        // 0x340 only has whole mph, in the last byte.
+        let frame =
+            Frame::from_id_data_len(0x340, [0, 0, 0, 0, 0, 0, 0, 45], 8)
+                .unwrap();
+        let mph = match OneOrMany::<Engine>::try_from(frame).unwrap() {
+            OneOrMany::One(Engine::MPH(mph)) => mph,
+            other => panic!("expected MPH, got {other:?}"),
+        };
+        assert_eq!(mph.raw(), 45 * 200);
+        assert_eq!(f32::from(mph), 45.0);
+        assert_eq!(mph.to_string(), "45.00 mph");
+
+        let short = Frame::from_id_slice(0x340, &[0; 7]).unwrap();
+        assert!(MPH::try_from(short).is_err());
+    }
+
+    #[test]
+    fn test_approx_speed() {
+        // 0x322 has rpms, then 1/200ths of a mph, big endian.
+        let frame = Frame::from_id_data_len(
+            0x322,
+            [0x07, 0xd0, 0x17, 0xd4, 0, 0, 0, 0],
+            8,
+        )
+        .unwrap();
+        match OneOrMany::<Engine>::try_from(frame).unwrap() {
+            OneOrMany::Many(events) => {
+                assert_eq!(events.len(), 2);
+                match events[1] {
+                    Engine::ApproxMPH(mph) => {
+                        assert_eq!(mph.raw(), 6100);
+                        assert_eq!(mph.to_string(), "30.50 mph");
+                    }
+                    ref other => panic!("expected ApproxMPH, got {other:?}"),
+                }
+            }
+            other => panic!("expected Many, got {other:?}"),
+        }
+    }
+}
@@ -26,6 +26,13 @@ use super::{Display, Frame, Front, FrontOrRear, ParseError, Rear};
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(PartialEq, Debug, Clone)]
 pub struct RoadFeedback([u8; 8]);
+impl RoadFeedback {
+    /// The raw data from the frame.
+    // FIXME: decode accelerometer values.
+    pub const fn raw(&self) -> [u8; 8] {
+        self.0
+    }
+}
 impl TryFrom<Frame> for FrontOrRear<RoadFeedback> {
     type Error = ParseError;
 
@@ -21,20 +21,62 @@
 // SOFTWARE.
 
 use super::{Display, Frame, ParseError};
+use crate::units::{Quantity, Ratio, UnitSystem};
 
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(PartialEq, Debug, Display, Clone)]
+/// A temperature, in 1/100ths of a degree Celsius above -40°C.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub struct Temperature(u16);
 impl Temperature {
+    /// Create from the raw value, in 1/100ths of a degree above -40°C.
+    pub const fn from_raw(raw: u16) -> Self {
+        Self(raw)
+    }
+    /// The raw value, in 1/100ths of a degree above -40°C.
+    pub const fn raw(self) -> u16 {
+        self.0
+    }
+    /// The exact temperature in degrees Celsius.
+    pub const fn celsius(self) -> Ratio {
+        Ratio::new(self.0 as i64 - 4000, 100)
+    }
+    /// The exact temperature in degrees Fahrenheit.
+    pub fn fahrenheit(self) -> Ratio {
+        self.celsius() * Ratio::new(9, 5) + Ratio::from_integer(32)
+    }
+    /// The temperature in degrees Celsius.
     pub fn in_celsius(self) -> f32 {
-        let value = f32::from(self.0);
-
-        (value / 100.0) - 40.0
+        self.celsius().to_f32()
     }
+    /// The temperature in degrees Fahrenheit.
+    #[deprecated(note = "use `fahrenheit` (exact) instead")]
     pub fn in_farenheit(self) -> f32 {
-        (self.in_celsius() * (9.0 / 5.0)) + 32.0
+        self.fahrenheit().to_f32()
+    }
+}
+impl Quantity for Temperature {
+    const NATIVE: UnitSystem = UnitSystem::Metric;
+
+    fn value(self, system: UnitSystem) -> Ratio {
+        match system {
+            UnitSystem::Metric => self.celsius(),
+            UnitSystem::Imperial => self.fahrenheit(),
+        }
+    }
+
+    fn symbol(system: UnitSystem) -> &'static str {
+        match system {
+            UnitSystem::Metric => "°C",
+            UnitSystem::Imperial => "°F",
+        }
+    }
+}
+impl std::fmt::Display for Temperature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.display(Self::NATIVE), f)
     }
 }
+#[cfg(feature = "serde")]
+crate::units::impl_quantity_serde!(Temperature, u16, "celsius", "fahrenheit");
 
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(PartialEq, Debug, Display, Clone)]
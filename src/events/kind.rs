@@ -0,0 +1,203 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use super::{Display, Event};
+
+/// The category of an [`Event`](super::Event), without any of its data. There
+/// is one [`EventKind`] for every variant of [`Event`](super::Event).
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(PartialEq, Eq, Hash, Debug, Display, Copy, Clone)]
+pub enum EventKind {
+    /// [`Event::Battery`](super::Event::Battery)
+    Battery,
+    /// [`Event::Remote`](super::Event::Remote)
+    Remote,
+    /// [`Event::Ignition`](super::Event::Ignition)
+    Ignition,
+    /// [`Event::SteeringWheel`](super::Event::SteeringWheel)
+    SteeringWheel,
+    /// [`Event::ControlPanel`](super::Event::ControlPanel)
+    ControlPanel,
+    /// [`Event::Lights`](super::Event::Lights)
+    Lights,
+    /// [`Event::Doors`](super::Event::Doors)
+    Doors,
+    /// [`Event::Locks`](super::Event::Locks)
+    Locks,
+    /// [`Event::Force`](super::Event::Force)
+    Force,
+    /// [`Event::Camera`](super::Event::Camera)
+    Camera,
+    /// [`Event::Engine`](super::Event::Engine)
+    Engine,
+    /// [`Event::HVAC`](super::Event::HVAC)
+    HVAC,
+    /// [`Event::DateTime`](super::Event::DateTime)
+    DateTime,
+    /// [`Event::Odometer`](super::Event::Odometer)
+    Odometer,
+    /// [`Event::Bus`](super::Event::Bus)
+    Bus,
+}
+
+bitflags::bitflags! {
+    /// A set of [`EventKind`]s, used to select which events to decode.
+    ///
+    /// ```
+    /// use jeep::events::{EventKind, EventKinds};
+    ///
+    /// let kinds = EventKinds::DOORS | EventKinds::LOCKS;
+    /// assert!(kinds.contains_kind(EventKind::Doors));
+    /// assert!(!kinds.contains_kind(EventKind::Remote));
+    /// assert_eq!(EventKinds::from(EventKind::Doors), EventKinds::DOORS);
+    /// ```
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct EventKinds: u16 {
+        const BATTERY           = 0b00000000_00000001;
+        const REMOTE            = 0b00000000_00000010;
+        const IGNITION          = 0b00000000_00000100;
+        const STEERING_WHEEL    = 0b00000000_00001000;
+        const CONTROL_PANEL     = 0b00000000_00010000;
+        const LIGHTS            = 0b00000000_00100000;
+        const DOORS             = 0b00000000_01000000;
+        const LOCKS             = 0b00000000_10000000;
+        const FORCE             = 0b00000001_00000000;
+        const CAMERA            = 0b00000010_00000000;
+        const ENGINE            = 0b00000100_00000000;
+        const HVAC              = 0b00001000_00000000;
+        const DATETIME          = 0b00010000_00000000;
+        const ODOMETER          = 0b00100000_00000000;
+        const BUS               = 0b01000000_00000000;
+    }
+}
+
+impl EventKinds {
+    /// Returns true if `kind` is in the set.
+    #[inline]
+    pub const fn contains_kind(self, kind: EventKind) -> bool {
+        self.contains(kind.flag())
+    }
+}
+
+impl EventKind {
+    /// Every [`EventKind`], in the same order as [`Event`](super::Event).
+    pub const ALL: [EventKind; 15] = [
+        EventKind::Battery,
+        EventKind::Remote,
+        EventKind::Ignition,
+        EventKind::SteeringWheel,
+        EventKind::ControlPanel,
+        EventKind::Lights,
+        EventKind::Doors,
+        EventKind::Locks,
+        EventKind::Force,
+        EventKind::Camera,
+        EventKind::Engine,
+        EventKind::HVAC,
+        EventKind::DateTime,
+        EventKind::Odometer,
+        EventKind::Bus,
+    ];
+
+    /// The [`EventKinds`] flag for this kind.
+    pub const fn flag(self) -> EventKinds {
+        match self {
+            EventKind::Battery => EventKinds::BATTERY,
+            EventKind::Remote => EventKinds::REMOTE,
+            EventKind::Ignition => EventKinds::IGNITION,
+            EventKind::SteeringWheel => EventKinds::STEERING_WHEEL,
+            EventKind::ControlPanel => EventKinds::CONTROL_PANEL,
+            EventKind::Lights => EventKinds::LIGHTS,
+            EventKind::Doors => EventKinds::DOORS,
+            EventKind::Locks => EventKinds::LOCKS,
+            EventKind::Force => EventKinds::FORCE,
+            EventKind::Camera => EventKinds::CAMERA,
+            EventKind::Engine => EventKinds::ENGINE,
+            EventKind::HVAC => EventKinds::HVAC,
+            EventKind::DateTime => EventKinds::DATETIME,
+            EventKind::Odometer => EventKinds::ODOMETER,
+            EventKind::Bus => EventKinds::BUS,
+        }
+    }
+}
+
+/// An unrecognized [`EventKind`] name.
+#[derive(Debug, Display, derive_more::Error)]
+#[display(fmt = "unknown event kind: `{}`", _0)]
+pub struct UnknownKind(#[error(not(source))] pub String);
+
+impl std::str::FromStr for EventKind {
+    type Err = UnknownKind;
+
+    /// Parse an [`EventKind`] from its name, ignoring case (eg. `"doors"`).
+    ///
+    /// ```
+    /// use jeep::events::EventKind;
+    ///
+    /// assert_eq!("doors".parse::<EventKind>().unwrap(), EventKind::Doors);
+    /// assert!("windows".parse::<EventKind>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        EventKind::ALL
+            .into_iter()
+            .find(|kind| kind.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| UnknownKind(s.to_owned()))
+    }
+}
+
+impl From<EventKind> for EventKinds {
+    #[inline(always)] // because single function call
+    fn from(kind: EventKind) -> Self {
+        kind.flag()
+    }
+}
+
+impl FromIterator<EventKind> for EventKinds {
+    fn from_iter<I: IntoIterator<Item = EventKind>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(EventKinds::empty(), |kinds, kind| kinds | kind.flag())
+    }
+}
+
+impl Event {
+    /// The [`EventKind`] of this [`Event`].
+    pub const fn kind(&self) -> EventKind {
+        match self {
+            Event::Battery(_) => EventKind::Battery,
+            Event::Remote(_) => EventKind::Remote,
+            Event::Ignition(_) => EventKind::Ignition,
+            Event::SteeringWheel(_) => EventKind::SteeringWheel,
+            Event::ControlPanel(_) => EventKind::ControlPanel,
+            Event::Lights(_) => EventKind::Lights,
+            Event::Doors(_) => EventKind::Doors,
+            Event::Locks(_) => EventKind::Locks,
+            Event::Force(_) => EventKind::Force,
+            Event::Camera(_) => EventKind::Camera,
+            Event::Engine(_) => EventKind::Engine,
+            Event::HVAC(_) => EventKind::HVAC,
+            Event::DateTime(_) => EventKind::DateTime,
+            Event::Odometer(_) => EventKind::Odometer,
+            Event::Bus(_) => EventKind::Bus,
+        }
+    }
+}
@@ -26,6 +26,11 @@ use super::{Display, Frame, ParseError};
 #[derive(PartialEq, Debug, Display, Copy, Clone)]
 pub struct ParkingLights(u8);
 impl ParkingLights {
+    /// The raw byte (1 is on).
+    #[inline]
+    pub const fn raw(self) -> u8 {
+        self.0
+    }
     /// Returns true if the parking lights are on.
     #[inline]
     pub const fn are_on(self) -> bool {
@@ -87,6 +92,12 @@ impl Dimmer {
     // if MIN > MAX, this will not compile
     const RANGE: u8 = Self::MAX - Self::MIN;
 
+    /// The raw byte.
+    #[inline]
+    pub const fn raw(self) -> u8 {
+        self.0
+    }
+
     #[inline]
     pub fn percent(self) -> f32 {
         f32::from(self.0 - Self::MIN) / f32::from(Self::RANGE)
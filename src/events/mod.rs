@@ -90,8 +90,11 @@ use static_assertions as sa;
 
 use crate::Frame;
 
+mod kind;
+pub use kind::{EventKind, EventKinds, UnknownKind};
 mod parse_error;
 pub use parse_error::ParseError;
+pub mod registry;
 
 pub mod battery;
 pub mod bus;
@@ -166,8 +169,10 @@ impl Event {
     /// Parse [`OneOrMany<Event>`] from compatible input.
     ///
     /// As of writing that includes:
-    /// * [`libc::can_frame`] - is always supported.
-    /// * [`socketcan::CANFrame`] - if the `socketcan` feature is enabled.
+    /// * [`Frame`] - is always supported.
+    /// * `libc::can_frame` - on Linux only.
+    /// * `socketcan::CANFrame` - on Linux, if the `socketcan` feature is
+    ///   enabled.
     #[inline(always)] // because single function call
     pub fn parse<I, E>(input: I) -> Result<OneOrMany<Event>, E>
     where
@@ -225,103 +230,18 @@ impl<T> Iterator for IntoIter<T> {
 impl TryFrom<Frame> for OneOrMany<Event> {
     type Error = ParseError;
 
-    /// Parse a CAN frame into [`OneOrMany<Event>`]
+    /// Parse a CAN frame into [`OneOrMany<Event>`] using the decoder from the
+    /// [`registry`].
     fn try_from(frame: Frame) -> Result<Self, Self::Error> {
-        match frame.id() {
-            0x2c2 => Ok(One(Event::Battery(frame.try_into()?))),
-            0x1c0 => Ok(One(Event::Remote(frame.try_into()?))),
-            0x122 => Ok(One(Event::Ignition(frame.try_into()?))),
-            0x318 => Ok(One(Event::SteeringWheel(frame.try_into()?))),
-            0x2d3 | 0x2d4 | 0x273 => {
-                Ok(One(Event::ControlPanel(frame.try_into()?)))
-            }
-            // lights / locks / doors (multiple events come from this source in
-            // a single frame)
-            0x2fa => {
-                // 0x2fa is the odd one out with multiple message catgegories
-                // from the same source, so this fucker here is the entire
-                // reason for the Many variant and heap allocation.
-                // Otherwise events live on the stack. And as a result the
-                // Messages iterator has to be more complex than otherwise, but
-                // the flexibility is probably a good idea anyway.
-
-                // the expected frame length
-                const LEN: usize = 8;
-
-                // FIXME(mdegans): these should be moved somewher else, and they
-                // don't seem to work, which means some more time in the jeep.
-                let mut events = Vec::new();
-                let mut errors = Vec::new();
-
-                let data: [u8; LEN] = match frame.data().try_into() {
-                    Ok(data) => data,
-                    Err(_) => {
-                        return Err(ParseError::Len {
-                            frame,
-                            expected: LEN,
-                        })
-                    }
-                };
-
-                // unwrap can never panic since every bit has a flag
-                let doors = doors::Doors::from_bits(data[0]).unwrap();
-                events.push(Event::Doors(doors));
-
-                // NOTE(mdegans): This and `Dimmer` does not work on my 4xE. Not
-                // sure why. I find it odd parking lights would be in this frame.
-                match lights::ParkingLights::try_from(frame.clone()) {
-                    Ok(parking_lights) => events.push(Event::Lights(
-                        lights::Lights::ParkingLights(parking_lights),
-                    )),
-                    Err(parse_error) => errors.push(parse_error),
-                };
-
-                match lights::Dimmer::try_from(frame.clone()) {
-                    Ok(dimmer) => events
-                        .push(Event::Lights(lights::Lights::Dimmer(dimmer))),
-                    // somehow the dimmer value was out of range
-                    Err(parse_error) => errors.push(parse_error),
-                };
-
-                // unwrap can never panic since every bit has a flag
-                // FIXME(mdegans): It's unclear if locks are actually bitflags.
-                // More investigation is needed.
-                let locks = locks::Locks::from_bits(frame.data()[3]).unwrap();
-                events.push(Event::Locks(locks));
-
-                if errors.is_empty() {
-                    Ok(Many(events))
-                } else {
-                    // FIXME(make ParseError support multiple errors. At least
-                    // these won't pass silently for now.
-                    Err(ParseError::Data {
-                        frame,
-                        detail: format!("There were error(s) parsing a frame from `0x2fa`: {errors:?}"),
-                    })
-                }
-            }
-            // Force sensors
-            0x24e | 0x252 => Ok(One(Event::Force(frame.try_into()?))),
-            // Camera view (from uconnect?)
-            0x302 => Ok(One(Event::Camera(frame.try_into()?))),
-            // Engine event (RPMs, MPH, Adjusted MPH)
-            0x322 | 0x340 => {
-                Ok(OneOrMany::<engine::Engine>::try_from(frame)?.into())
-            }
-            // HVAC event
-            0x33a => Ok(One(Event::HVAC(frame.try_into()?))),
-            // Vehicle date and time (TODO(mdegans): bus id?)
-            0x350 => Ok(One(Event::DateTime(frame.try_into()?))),
-            0x3d2 => Ok(One(Event::Odometer(frame.try_into()?))),
-            // 0x4xx series messages
-            0x401 => Ok(One(Event::Bus(frame.try_into()?))),
+        match registry::lookup(frame.id()) {
+            Some(info) => (info.decoder)(frame),
             // Something to implement.
-            _ => Err(ParseError::Id { frame }),
+            None => Err(ParseError::Id { frame }),
         }
     }
 }
 
-#[cfg(feature = "socketcan")]
+#[cfg(all(target_os = "linux", feature = "socketcan"))]
 impl TryFrom<socketcan::CANFrame> for OneOrMany<Event> {
     type Error = CanFrameError;
 
@@ -332,6 +252,7 @@ impl TryFrom<socketcan::CANFrame> for OneOrMany<Event> {
     }
 }
 
+#[cfg(target_os = "linux")]
 impl TryFrom<libc::can_frame> for OneOrMany<Event> {
     type Error = CanFrameError;
 
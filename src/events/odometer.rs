@@ -20,26 +20,63 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use super::{Display, Frame, ParseError};
+use super::{Frame, ParseError};
+use crate::units::{Quantity, Ratio, UnitSystem, KM_PER_MILE};
 
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(PartialEq, Debug, Display, Copy, Clone)]
+/// The odometer, in 1/100ths of a kilometer.
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
 #[repr(align(8))]
-pub struct Odometer(u32);
+pub struct Odometer(pub(crate) u32);
 impl Odometer {
+    /// Create from the raw value, in 100ths of a kilometer.
+    pub const fn from_raw(raw: u32) -> Self {
+        Self(raw)
+    }
+    /// The exact distance in kilometers.
+    pub const fn km(self) -> Ratio {
+        Ratio::new(self.0 as i64, 100)
+    }
+    /// The exact distance in miles.
+    pub fn mi(self) -> Ratio {
+        self.km() / KM_PER_MILE
+    }
     /// value as kilometers, down to the 100th kilometer.
     pub fn kilometers(self) -> f64 {
-        f64::from(self.0) / 100.0
+        self.km().to_f64()
     }
     /// value as miles, down to the 100th kilometer.
     pub fn miles(self) -> f64 {
-        self.kilometers() * 0.621371
+        self.mi().to_f64()
     }
     /// raw odometer bits as 100ths of a kilometer
     pub const fn raw(self) -> u32 {
         self.0
     }
 }
+impl Quantity for Odometer {
+    const NATIVE: UnitSystem = UnitSystem::Metric;
+
+    fn value(self, system: UnitSystem) -> Ratio {
+        match system {
+            UnitSystem::Metric => self.km(),
+            UnitSystem::Imperial => self.mi(),
+        }
+    }
+
+    fn symbol(system: UnitSystem) -> &'static str {
+        match system {
+            UnitSystem::Metric => "km",
+            UnitSystem::Imperial => "mi",
+        }
+    }
+}
+impl std::fmt::Display for Odometer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.display(Self::NATIVE), f)
+    }
+}
+#[cfg(feature = "serde")]
+crate::units::impl_quantity_serde!(Odometer, u32, "km", "miles");
 
 impl TryFrom<Frame> for Odometer {
     type Error = ParseError;
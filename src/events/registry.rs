@@ -0,0 +1,512 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! A static registry of every CAN ID this crate knows how to decode, along
+//! with some metadata about each. This is what the top-level [`Event`] parser
+//! uses to dispatch a [`Frame`] to the right decoder, but it's also useful for
+//! building filters or printing a catalog of known IDs:
+//!
+//! ```
+//! use jeep::events::registry;
+//!
+//! for info in registry::MESSAGES {
+//!     println!("{info}");
+//! }
+//!
+//! let doors = registry::lookup(0x2fa).unwrap();
+//! assert_eq!(doors.dlc, 8);
+//! assert!(registry::lookup(0x7ff).is_none());
+//! ```
+
+use std::time::Duration;
+
+use super::{
+    doors, engine, lights, locks, Display, Event, EventKind, EventKinds, Frame,
+    OneOrMany,
+    OneOrMany::{Many, One},
+    ParseError,
+};
+
+/// A function that decodes a [`Frame`] from a known ID into
+/// [`OneOrMany<Event>`].
+pub type Decoder = fn(Frame) -> Result<OneOrMany<Event>, ParseError>;
+
+/// How much we trust a decoder.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(PartialEq, Eq, Debug, Display, Copy, Clone)]
+pub enum Status {
+    /// Tested in an actual Jeep and behaves as documented.
+    Verified,
+    /// Decoded according to available documentation, but not yet tested in an
+    /// actual Jeep.
+    Untested,
+    /// Known or strongly suspected to be wrong, at least in part. See the
+    /// `FIXME`s in the relevant module.
+    Suspect,
+}
+
+/// Metadata about a CAN ID this crate can decode.
+#[derive(Debug, Clone, Copy)]
+pub struct MessageInfo {
+    /// The CAN ID (without flags).
+    pub id: u32,
+    /// Human readable name for frames from this ID.
+    pub name: &'static str,
+    /// The expected len (`can_dlc`) of frames from this ID.
+    pub dlc: usize,
+    /// The kind(s) of [`Event`] that can be decoded from this ID. Most IDs
+    /// have only one.
+    pub kinds: &'static [EventKind],
+    /// Approximate expected period if the ID is sent periodically, or `None`
+    /// if it's only sent in response to something happening.
+    pub period: Option<Duration>,
+    /// Decodes a [`Frame`] from this ID.
+    pub decoder: Decoder,
+    /// How much the `decoder` can be trusted.
+    pub status: Status,
+}
+
+impl MessageInfo {
+    /// Decode a [`Frame`] using this ID's decoder. It's up to the decoder to
+    /// reject frames from other IDs.
+    #[inline(always)] // because single function call
+    pub fn decode(&self, frame: Frame) -> Result<OneOrMany<Event>, ParseError> {
+        (self.decoder)(frame)
+    }
+
+    /// The `kinds` as a set.
+    pub fn kind_set(&self) -> EventKinds {
+        self.kinds.iter().copied().collect()
+    }
+}
+
+impl std::fmt::Display for MessageInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:#05X} {} (dlc: {}, kinds: ",
+            self.id, self.name, self.dlc
+        )?;
+        for (i, kind) in self.kinds.iter().enumerate() {
+            if i > 0 {
+                f.write_str("|")?;
+            }
+            write!(f, "{kind}")?;
+        }
+        match self.period {
+            Some(period) => write!(f, ", period: {period:?}")?,
+            None => f.write_str(", period: None")?,
+        }
+        write!(f, ", status: {})", self.status)
+    }
+}
+
+/// Look up the [`MessageInfo`] for a CAN `id`, if it's known.
+pub fn lookup(id: u32) -> Option<&'static MessageInfo> {
+    MESSAGES
+        .binary_search_by_key(&id, |info| info.id)
+        .ok()
+        .map(|i| &MESSAGES[i])
+}
+
+/// An iterator through all known CAN IDs, in ascending order.
+pub fn ids() -> impl Iterator<Item = u32> {
+    MESSAGES.iter().map(|info| info.id)
+}
+
+/// An iterator through known CAN IDs which can produce any of the `kinds`, in
+/// ascending order. Useful for building (kernel) filters.
+pub fn ids_for(kinds: EventKinds) -> impl Iterator<Item = u32> {
+    MESSAGES
+        .iter()
+        .filter(move |info| kinds.intersects(info.kind_set()))
+        .map(|info| info.id)
+}
+
+/// Decode only the selected `kinds` of [`Event`] from a [`Frame`].
+///
+/// Returns `None` without running any decoder if the frame's ID is unknown or
+/// can't produce any of the `kinds`. Otherwise events of other kinds from the
+/// same frame are dropped.
+///
+/// ```
+/// use jeep::{events::{registry, EventKinds}, Frame};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let frame = Frame::from_id_data_len(0x2fa, [1, 0, 0, 0, 0, 0, 0, 0], 8)?;
+/// let events: Vec<_> = registry::decode_kinds(frame.clone(), EventKinds::DOORS)
+///     .unwrap()?
+///     .into_iter()
+///     .collect();
+/// assert_eq!(events.len(), 1);
+///
+/// assert!(registry::decode_kinds(frame, EventKinds::REMOTE).is_none());
+/// # Ok(())
+/// # }
+/// ```
+pub fn decode_kinds(
+    frame: Frame,
+    kinds: EventKinds,
+) -> Option<Result<OneOrMany<Event>, ParseError>> {
+    let info = lookup(frame.id())?;
+    if !kinds.intersects(info.kind_set()) {
+        return None;
+    }
+
+    match info.decode(frame) {
+        Ok(Many(events)) => Some(Ok(Many(
+            events
+                .into_iter()
+                .filter(|event| kinds.contains_kind(event.kind()))
+                .collect(),
+        ))),
+        other => Some(other),
+    }
+}
+
+/// Every known CAN ID, **sorted by `id`** (so [`lookup`] can do a binary
+/// search).
+// NOTE: Periods are approximate. If you measure them in your Jeep and they
+// differ, please submit a PR.
+pub static MESSAGES: &[MessageInfo] = &[
+    MessageInfo {
+        id: 0x122,
+        name: "Ignition",
+        dlc: 4,
+        kinds: &[EventKind::Ignition],
+        period: Some(Duration::from_millis(100)),
+        decoder: decode_one::<super::ignition::Ignition>,
+        status: Status::Untested,
+    },
+    MessageInfo {
+        id: 0x1c0,
+        name: "Remote",
+        dlc: 6,
+        kinds: &[EventKind::Remote],
+        period: Some(Duration::from_millis(100)),
+        decoder: decode_one::<super::remote::Remote>,
+        // Only tested with the KeyFob.
+        status: Status::Verified,
+    },
+    MessageInfo {
+        id: 0x24e,
+        name: "Front RoadFeedback",
+        dlc: 8,
+        kinds: &[EventKind::Force],
+        period: Some(Duration::from_millis(20)),
+        decoder: decode_one::<super::force::Force>,
+        status: Status::Untested,
+    },
+    MessageInfo {
+        id: 0x252,
+        name: "Rear RoadFeedback",
+        dlc: 8,
+        kinds: &[EventKind::Force],
+        period: Some(Duration::from_millis(20)),
+        decoder: decode_one::<super::force::Force>,
+        status: Status::Untested,
+    },
+    MessageInfo {
+        id: 0x273,
+        name: "ControlPanel Knobs",
+        dlc: 8,
+        kinds: &[EventKind::ControlPanel],
+        period: None,
+        decoder: decode_one::<super::control_panel::ControlPanel>,
+        status: Status::Untested,
+    },
+    MessageInfo {
+        id: 0x2c2,
+        name: "Aux Battery",
+        dlc: 4,
+        kinds: &[EventKind::Battery],
+        period: Some(Duration::from_millis(100)),
+        decoder: decode_one::<super::battery::Battery>,
+        status: Status::Untested,
+    },
+    MessageInfo {
+        id: 0x2d3,
+        name: "ControlPanel Buttons",
+        dlc: 8,
+        kinds: &[EventKind::ControlPanel],
+        period: Some(Duration::from_millis(100)),
+        decoder: decode_one::<super::control_panel::ControlPanel>,
+        status: Status::Untested,
+    },
+    MessageInfo {
+        id: 0x2d4,
+        name: "ControlPanel Warmers",
+        dlc: 8,
+        kinds: &[EventKind::ControlPanel],
+        period: Some(Duration::from_millis(100)),
+        decoder: decode_one::<super::control_panel::ControlPanel>,
+        status: Status::Untested,
+    },
+    MessageInfo {
+        id: 0x2fa,
+        name: "Doors/Lights/Locks",
+        dlc: 8,
+        kinds: &[EventKind::Doors, EventKind::Lights, EventKind::Locks],
+        period: Some(Duration::from_millis(100)),
+        decoder: decode_doors_lights_locks,
+        // Doors are tested, but `Locks`, `ParkingLights` and `Dimmer` are not
+        // working on a 2021 4xE Sahara.
+        status: Status::Suspect,
+    },
+    MessageInfo {
+        id: 0x302,
+        name: "Camera",
+        dlc: 8,
+        kinds: &[EventKind::Camera],
+        period: Some(Duration::from_millis(100)),
+        decoder: decode_one::<super::camera::Camera>,
+        status: Status::Untested,
+    },
+    MessageInfo {
+        id: 0x318,
+        name: "SteeringWheel Buttons",
+        dlc: 8,
+        kinds: &[EventKind::SteeringWheel],
+        period: Some(Duration::from_millis(100)),
+        decoder: decode_one::<super::steering_wheel::Buttons>,
+        status: Status::Untested,
+    },
+    MessageInfo {
+        id: 0x322,
+        name: "Engine RPMs/ApproxMPH",
+        dlc: 8,
+        kinds: &[EventKind::Engine],
+        period: Some(Duration::from_millis(20)),
+        decoder: decode_engine,
+        status: Status::Untested,
+    },
+    MessageInfo {
+        id: 0x33a,
+        name: "HVAC Cabin Temperature",
+        dlc: 8,
+        kinds: &[EventKind::HVAC],
+        period: Some(Duration::from_millis(1000)),
+        decoder: decode_one::<super::hvac::HVAC>,
+        status: Status::Untested,
+    },
+    MessageInfo {
+        id: 0x340,
+        name: "Engine MPH",
+        dlc: 8,
+        kinds: &[EventKind::Engine],
+        period: Some(Duration::from_millis(100)),
+        decoder: decode_engine,
+        status: Status::Untested,
+    },
+    MessageInfo {
+        id: 0x350,
+        name: "DateTime",
+        dlc: 8,
+        kinds: &[EventKind::DateTime],
+        period: Some(Duration::from_millis(1000)),
+        decoder: decode_one::<super::datetime::DateTime>,
+        status: Status::Untested,
+    },
+    MessageInfo {
+        id: 0x3d2,
+        name: "Odometer",
+        dlc: 4,
+        kinds: &[EventKind::Odometer],
+        period: Some(Duration::from_millis(1000)),
+        decoder: decode_one::<super::odometer::Odometer>,
+        status: Status::Untested,
+    },
+    MessageInfo {
+        id: 0x401,
+        name: "Bus Wake",
+        dlc: 8,
+        kinds: &[EventKind::Bus],
+        period: None,
+        decoder: decode_one::<super::bus::Bus>,
+        // FIXME(mdegans): this is almost assuredly wrong
+        status: Status::Suspect,
+    },
+];
+
+/// Decoder for IDs that always produce exactly one sub-event `T`.
+fn decode_one<T>(frame: Frame) -> Result<OneOrMany<Event>, ParseError>
+where
+    T: TryFrom<Frame, Error = ParseError> + Into<Event>,
+{
+    Ok(One(T::try_from(frame)?.into()))
+}
+
+/// Decoder for [`engine::Engine`] events (RPMs, MPH, Adjusted MPH).
+fn decode_engine(frame: Frame) -> Result<OneOrMany<Event>, ParseError> {
+    Ok(OneOrMany::<engine::Engine>::try_from(frame)?.into())
+}
+
+/// Decoder for lights / locks / doors (multiple events come from this source
+/// in a single frame).
+fn decode_doors_lights_locks(
+    frame: Frame,
+) -> Result<OneOrMany<Event>, ParseError> {
+    // 0x2fa is the odd one out with multiple message catgegories
+    // from the same source, so this fucker here is the entire
+    // reason for the Many variant and heap allocation.
+    // Otherwise events live on the stack. And as a result the
+    // Messages iterator has to be more complex than otherwise, but
+    // the flexibility is probably a good idea anyway.
+
+    // the expected `frame.id` for this event.
+    const ID: u32 = 0x2fa;
+    // the expected frame length
+    const LEN: usize = 8;
+
+    if frame.id() != ID {
+        return Err(ParseError::Id { frame });
+    }
+
+    // FIXME(mdegans): these should be moved somewher else, and they
+    // don't seem to work, which means some more time in the jeep.
+    let mut events = Vec::new();
+    let mut errors = Vec::new();
+
+    let data: [u8; LEN] = match frame.data().try_into() {
+        Ok(data) => data,
+        Err(_) => {
+            return Err(ParseError::Len {
+                frame,
+                expected: LEN,
+            })
+        }
+    };
+
+    // unwrap can never panic since every bit has a flag
+    let doors = doors::Doors::from_bits(data[0]).unwrap();
+    events.push(Event::Doors(doors));
+
+    // NOTE(mdegans): This and `Dimmer` does not work on my 4xE. Not
+    // sure why. I find it odd parking lights would be in this frame.
+    match lights::ParkingLights::try_from(frame.clone()) {
+        Ok(parking_lights) => events
+            .push(Event::Lights(lights::Lights::ParkingLights(parking_lights))),
+        Err(parse_error) => errors.push(parse_error),
+    };
+
+    match lights::Dimmer::try_from(frame.clone()) {
+        Ok(dimmer) => {
+            events.push(Event::Lights(lights::Lights::Dimmer(dimmer)))
+        }
+        // somehow the dimmer value was out of range
+        Err(parse_error) => errors.push(parse_error),
+    };
+
+    // unwrap can never panic since every bit has a flag
+    // FIXME(mdegans): It's unclear if locks are actually bitflags.
+    // More investigation is needed.
+    let locks = locks::Locks::from_bits(data[3]).unwrap();
+    events.push(Event::Locks(locks));
+
+    if errors.is_empty() {
+        Ok(Many(events))
+    } else {
+        // FIXME(make ParseError support multiple errors. At least
+        // these won't pass silently for now.
+        Err(ParseError::Data {
+            frame,
+            detail: format!(
+                "There were error(s) parsing a frame from `0x2fa`: {errors:?}"
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sorted_and_unique() {
+        for pair in MESSAGES.windows(2) {
+            assert!(pair[0].id < pair[1].id, "{} >= {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn test_lookup() {
+        for info in MESSAGES {
+            assert_eq!(lookup(info.id).unwrap().id, info.id);
+        }
+        assert!(lookup(0x000).is_none());
+        assert!(lookup(0x7ff).is_none());
+    }
+
+    #[test]
+    fn test_decoders_reject_bad_len() {
+        // Every decoder should reject a frame of the wrong length with
+        // ParseError::Len (rather than, say, panicking).
+        for info in MESSAGES {
+            let frame = Frame::from_id_slice(info.id, &[]).unwrap();
+            match info.decode(frame) {
+                Err(ParseError::Len { expected, .. }) => {
+                    assert_eq!(expected, info.dlc, "{info}")
+                }
+                other => panic!("{info}: unexpected {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_kinds_match_decoders() {
+        // Every event a decoder produces should be one of its `kinds`.
+        for info in MESSAGES {
+            let frame =
+                Frame::from_id_data_len(info.id, [0; 8], info.dlc as u8)
+                    .unwrap();
+            if let Ok(events) = info.decode(frame) {
+                for event in events {
+                    assert!(info.kinds.contains(&event.kind()), "{info}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_ids_for() {
+        let ids: Vec<_> =
+            ids_for(EventKinds::DOORS | EventKinds::ENGINE).collect();
+        assert_eq!(ids, [0x2fa, 0x322, 0x340]);
+    }
+
+    #[test]
+    fn test_dispatch() {
+        let frame = Frame::from_id_data_len(
+            0x2fa,
+            [doors::Doors::DRIVER.bits(), 0, 0, 0, 0, 0, 0, 0],
+            8,
+        )
+        .unwrap();
+        let events: Vec<Event> =
+            Event::parse(frame).unwrap().into_iter().collect();
+        assert!(events.contains(&Event::Doors(doors::Doors::DRIVER)));
+        assert!(events.contains(&Event::Locks(locks::Locks::empty())));
+
+        let frame = Frame::from_id_slice(0x7ff, &[]).unwrap();
+        assert!(matches!(Event::parse(frame), Err(ParseError::Id { .. })));
+    }
+}
@@ -0,0 +1,353 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Exporters for charting decoded events (eg. in Grafana).
+//!
+//! * [`Influx`] turns [`Event`]s into InfluxDB line protocol, with one field
+//!   per [`Column`] (the same signals as a [`crate::table::Table`]).
+//! * [`Metrics`] keeps the latest [`VehicleState`] and frame counters, and
+//!   displays as a Prometheus `/metrics` page.
+//!
+//! ```
+//! use jeep::export::{Influx, Metrics};
+//! use jeep::Frame;
+//! use std::time::Duration;
+//!
+//! let influx = Influx::default();
+//! let mut metrics = Metrics::new();
+//!
+//! let frame = Frame::from_id_slice(0x340, &[0, 0, 0, 0, 0, 0, 0, 30])?;
+//! for event in metrics.update(&frame, Duration::from_secs(1)) {
+//!     let line = influx.line(&event, Duration::from_secs(1)).unwrap();
+//!     assert_eq!(line, "jeep,kind=engine mph=30 1000000000");
+//! }
+//! assert!(metrics.to_string().contains("\njeep_speed_mph 30\n"));
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::{collections::BTreeMap, fmt};
+
+use crate::{
+    events::{OneOrMany, ParseError},
+    table::{Column, Value},
+    Event, Frame, Timestamp, VehicleState,
+};
+
+/// Configuration for [`Influx`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InfluxConfig {
+    /// The measurement every line is written to (default: `jeep`).
+    pub measurement: String,
+    /// Extra tags added to every line (eg. `vehicle=blue`). The event kind is
+    /// always added as `kind`.
+    pub tags: BTreeMap<String, String>,
+}
+
+impl Default for InfluxConfig {
+    fn default() -> Self {
+        Self {
+            measurement: "jeep".to_owned(),
+            tags: BTreeMap::new(),
+        }
+    }
+}
+
+/// Escape a measurement, tag key or tag value for line protocol.
+fn escape(s: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c == '\\' || special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Writes [`Event`]s as InfluxDB line protocol.
+#[derive(Debug, Clone)]
+pub struct Influx {
+    config: InfluxConfig,
+    /// The measurement and extra tags, already escaped.
+    prefix: String,
+}
+
+impl Default for Influx {
+    fn default() -> Self {
+        Self::new(InfluxConfig::default())
+    }
+}
+
+impl Influx {
+    /// Create a new [`Influx`] from an [`InfluxConfig`].
+    pub fn new(config: InfluxConfig) -> Self {
+        let mut prefix = escape(&config.measurement, &[',', ' ']);
+        for (key, value) in &config.tags {
+            prefix.push(',');
+            prefix.push_str(&escape(key, &[',', '=', ' ']));
+            prefix.push('=');
+            prefix.push_str(&escape(value, &[',', '=', ' ']));
+        }
+        Self { config, prefix }
+    }
+
+    /// Get the [`InfluxConfig`].
+    pub fn config(&self) -> &InfluxConfig {
+        &self.config
+    }
+
+    /// The line (without the newline) for an `event` that happened `at` some
+    /// time since the Unix epoch, or `None` if the `event` has no value in
+    /// any [`Column`] (eg. a button press).
+    pub fn line(&self, event: &Event, at: Timestamp) -> Option<String> {
+        let fields: Vec<String> = Column::ALL
+            .into_iter()
+            .filter_map(|column| {
+                let value = match column.value(event)? {
+                    Value::Number(n) => n.to_string(),
+                    Value::Bits(bits) => format!("{bits}i"),
+                    // Text values never contain `"` or `\`.
+                    Value::Text(text) => format!("\"{text}\""),
+                };
+                Some(format!("{column}={value}"))
+            })
+            .collect();
+        if fields.is_empty() {
+            return None;
+        }
+
+        let kind = event.kind().to_string().to_lowercase();
+        Some(format!(
+            "{},kind={kind} {} {}",
+            self.prefix,
+            fields.join(","),
+            at.as_nanos()
+        ))
+    }
+}
+
+/// The latest [`VehicleState`] and frame counters, for a Prometheus
+/// `/metrics` endpoint. Displays in the Prometheus text format.
+///
+/// Gauges are left out until their value has been seen. Per-id frame counts
+/// are counters, so the rate of an id is eg.
+/// `rate(jeep_id_frames_total{id="2FA"}[1m])`.
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    state: VehicleState,
+    frames: u64,
+    unknown_id: u64,
+    errors: u64,
+    ids: BTreeMap<u32, u64>,
+}
+
+impl Metrics {
+    /// Create new, empty, [`Metrics`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Update with a `frame` received `at` some time. Returns the decoded
+    /// [`Event`]s, if any, so the frame only needs to be parsed once.
+    pub fn update(&mut self, frame: &Frame, at: Timestamp) -> Vec<Event> {
+        self.frames += 1;
+        *self.ids.entry(frame.id()).or_default() += 1;
+
+        let events = match Event::parse(frame.clone()) {
+            Ok(OneOrMany::One(event)) => vec![event],
+            Ok(OneOrMany::Many(events)) => events,
+            Err(ParseError::Id { .. }) => {
+                self.unknown_id += 1;
+                return Vec::new();
+            }
+            Err(_) => {
+                self.errors += 1;
+                return Vec::new();
+            }
+        };
+        for event in &events {
+            self.state.update(event, at);
+        }
+        events
+    }
+
+    /// The latest [`VehicleState`].
+    pub fn state(&self) -> &VehicleState {
+        &self.state
+    }
+
+    /// Total frames counted.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+}
+
+impl fmt::Display for Metrics {
+    /// Format the metrics in the Prometheus text exposition format.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut gauge = |name: &str, help: &str, value: Option<f64>| match value
+        {
+            Some(value) => write!(
+                f,
+                "# HELP {name} {help}\n# TYPE {name} gauge\n{name} {value}\n"
+            ),
+            None => Ok(()),
+        };
+        let state = &self.state;
+        gauge(
+            "jeep_speed_mph",
+            "GPS corrected speed in mph.",
+            state.mph().map(|s| s.value.mph().to_f64()),
+        )?;
+        gauge(
+            "jeep_approx_speed_mph",
+            "Approximate speed in mph.",
+            state.approx_mph().map(|s| s.value.mph().to_f64()),
+        )?;
+        gauge(
+            "jeep_engine_rpm",
+            "Engine RPMs (0 while the engine is off).",
+            state.rpms().map(|s| f64::from(s.value.get().unwrap_or(0))),
+        )?;
+        gauge(
+            "jeep_cabin_temperature_celsius",
+            "Cabin temperature in Celsius.",
+            state
+                .cabin_temperature()
+                .map(|s| s.value.celsius().to_f64()),
+        )?;
+        gauge(
+            "jeep_aux_battery_volts",
+            "Aux (12V) battery voltage.",
+            state.aux().map(|s| s.value.voltage().to_f64()),
+        )?;
+        gauge(
+            "jeep_odometer_kilometers",
+            "Odometer in kilometers.",
+            state.odometer().map(|s| s.value.kilometers()),
+        )?;
+
+        let counters = [
+            ("jeep_frames_total", "Frames received.", self.frames),
+            (
+                "jeep_unknown_id_frames_total",
+                "Frames with an id that has no decoder.",
+                self.unknown_id,
+            ),
+            (
+                "jeep_parse_errors_total",
+                "Frames that failed to parse (other than unknown ids).",
+                self.errors,
+            ),
+        ];
+        for (name, help, value) in counters {
+            writeln!(f, "# HELP {name} {help}\n# TYPE {name} counter")?;
+            writeln!(f, "{name} {value}")?;
+        }
+
+        let name = "jeep_id_frames_total";
+        writeln!(f, "# HELP {name} Frames received by id.")?;
+        writeln!(f, "# TYPE {name} counter")?;
+        for (id, count) in &self.ids {
+            writeln!(f, "{name}{{id=\"{id:X}\"}} {count}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{
+        doors::Doors, hvac::Temperature, hvac::HVAC, ignition::Ignition,
+        lights::Lights,
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn test_influx_line() {
+        let influx = Influx::new(InfluxConfig {
+            measurement: "my jeep".to_owned(),
+            tags: [("vehicle".to_owned(), "blue,4xe".to_owned())].into(),
+        });
+        let at = Duration::new(2, 5);
+        let line = |event| influx.line(&event, at);
+
+        assert_eq!(
+            line(Event::Doors(Doors::DRIVER | Doors::PASSENGER)).unwrap(),
+            "my\\ jeep,vehicle=blue\\,4xe,kind=doors doors=3i 2000000005"
+        );
+        assert_eq!(
+            line(Event::Ignition(Ignition::Run)).unwrap(),
+            "my\\ jeep,vehicle=blue\\,4xe,kind=ignition ignition=\"run\" 2000000005"
+        );
+        assert_eq!(
+            line(Event::HVAC(HVAC::Cabin(Temperature::from_raw(6550))))
+                .unwrap(),
+            "my\\ jeep,vehicle=blue\\,4xe,kind=hvac cabin_c=25.5 2000000005"
+        );
+        assert!(line(Event::Lights(Lights::HazardsOnOff)).is_none());
+    }
+
+    #[test]
+    fn test_influx_default() {
+        let influx = Influx::default();
+        assert_eq!(influx.config(), &InfluxConfig::default());
+        assert_eq!(
+            influx
+                .line(&Event::Ignition(Ignition::Off), Duration::from_secs(1))
+                .unwrap(),
+            "jeep,kind=ignition ignition=\"off\" 1000000000"
+        );
+    }
+
+    #[test]
+    fn test_metrics() {
+        let mut metrics = Metrics::new();
+        let at = Duration::ZERO;
+        let frame = |id, data: &[u8]| Frame::from_id_slice(id, data).unwrap();
+
+        assert!(metrics.to_string().contains("\njeep_frames_total 0\n"));
+        assert!(!metrics.to_string().contains("jeep_speed_mph"));
+
+        let speed = frame(0x340, &[0, 0, 0, 0, 0, 0, 0, 30]);
+        assert_eq!(metrics.update(&speed, at).len(), 1);
+        assert!(metrics.update(&frame(0x123, &[]), at).is_empty());
+        assert!(metrics.update(&frame(0x340, &[0]), at).is_empty());
+
+        let text = metrics.to_string();
+        for expected in [
+            "# TYPE jeep_speed_mph gauge\njeep_speed_mph 30\n",
+            "\njeep_frames_total 3\n",
+            "\njeep_unknown_id_frames_total 1\n",
+            "\njeep_parse_errors_total 1\n",
+            "\njeep_id_frames_total{id=\"123\"} 1\n",
+            "\njeep_id_frames_total{id=\"340\"} 2\n",
+        ] {
+            assert!(text.contains(expected), "{expected:?} not in:\n{text}");
+        }
+        // Every sample has a HELP and TYPE.
+        assert_eq!(text.matches("# HELP").count(), 5);
+        assert_eq!(text.matches("# TYPE").count(), 5);
+    }
+}
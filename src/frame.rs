@@ -20,37 +20,60 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-//! Contains our own CAN [`Frame`], which wraps a [`libc::can_frame`].
+//! Contains our own CAN [`Frame`], which has the same layout as a Linux
+//! `can_frame`, but does not depend on `libc`, so frames can be parsed on any
+//! platform (eg. logs on macOS, Windows, or `wasm32`). Conversions to and from
+//! `libc::can_frame` and `socketcan::CANFrame` are only available on Linux.
+//!
+//! With the `serde` feature, a [`Frame`] can be (de)serialized in any of the
+//! [`SerdeRepr`] forms. The default is the [`SerdeRepr::Struct`] form; use
+//! [`Frame::with_serde_repr`] to select another:
+//!
+//! ```
+//! # #[cfg(feature = "serde")]
+//! # {
+//! use jeep::{frame::SerdeRepr, Frame};
+//!
+//! let frame = Frame::from_id_slice(0x2fa, &[1, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+//! let json = Frame::with_serde_repr(SerdeRepr::Hex, || {
+//!     serde_json::to_string(&frame).unwrap()
+//! });
+//! assert_eq!(json, r#"{"id":"2FA","data":"0100000000000000"}"#);
+//! # }
+//! ```
+
+use std::str::FromStr;
 
 use static_assertions as sa;
 
-// Some compile time sanity checks to ensure socketcan and can_frame haven't
-// changed somehow. These should probably never break.
-sa::const_assert_eq!(std::mem::size_of::<libc::can_frame>(), 16);
-#[cfg(feature = "socketcan")]
-sa::assert_eq_size!(libc::can_frame, socketcan::CANFrame);
-// note: socketcan alignment is not the same, however the field order and size
-// still is.
+// Some compile time sanity checks to ensure our layout matches the kernel's
+// `struct can_frame`. These should probably never break.
+sa::const_assert_eq!(std::mem::size_of::<Frame>(), 16);
+sa::const_assert_eq!(std::mem::align_of::<Frame>(), 8);
+sa::assert_eq_size!(CanFrame, Frame);
+sa::assert_eq_align!(CanFrame, Frame);
+#[cfg(target_os = "linux")]
 sa::assert_eq_size!(libc::can_frame, Frame);
+#[cfg(target_os = "linux")]
 sa::assert_eq_align!(libc::can_frame, Frame);
-sa::assert_eq_size!(libc::can_frame, CanFrameWrapper);
-sa::assert_eq_align!(libc::can_frame, CanFrameWrapper);
+// note: socketcan alignment is not the same, however the field order and size
+// still is.
+#[cfg(all(target_os = "linux", feature = "socketcan"))]
+sa::assert_eq_size!(libc::can_frame, socketcan::CANFrame);
 
-/// A [`Frame`] is a wrapper for a [`libc::can_frame`] struct.
+/// A [`Frame`] is a platform independent CAN frame.
 ///
-/// It is guaranteed to have the same size and layout. This will not change.
+/// It is guaranteed to have the same size and layout as a Linux
+/// `libc::can_frame`. This will not change.
 // Class invariants:
 // 1) self.0.can_dlc <= 8 - necessary for data() slice accessor.
 #[repr(transparent)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone)]
-pub struct Frame(
-    #[cfg_attr(feature = "serde", serde(with = "CanFrameWrapper"))]
-    libc::can_frame,
-);
+pub struct Frame(CanFrame);
 
 impl Frame {
-    const ID_MASK: u32 = 0x7FF;
+    /// Mask for the (standard, 11 bit) CAN ID, without any flags.
+    pub const ID_MASK: u32 = 0x7FF;
     const DATA_LEN: usize = 8;
 
     /// Helper function to validate that a [`Frame`] is validly constructed.
@@ -67,15 +90,22 @@ impl Frame {
 
     /// Create a new [`Frame`] from a [`libc::can_frame`].
     #[inline(always)] // because trivial
+    #[cfg(target_os = "linux")]
     pub const fn from_libc_can_frame(
         frame: libc::can_frame,
     ) -> Result<Self, BadLen> {
-        Self(frame).validate()
+        Self::from_id_data_len(frame.can_id, frame.data, frame.can_dlc)
     }
 
+    /// Convert into a [`libc::can_frame`].
     #[inline(always)] // because trivial
+    #[cfg(target_os = "linux")]
     pub const fn into_libc_can_frame(self) -> libc::can_frame {
-        self.0
+        // SAFETY: The static assertions at the top of this module guarantee
+        // the size and alignment are the same and both structs are repr(C)
+        // with the same field order. Padding is always zeroed by our
+        // constructors.
+        unsafe { std::mem::transmute(self) }
     }
 
     /// Create a new frame from id (with flags), data, and len.
@@ -84,20 +114,15 @@ impl Frame {
         data: [u8; 8],
         len: u8,
     ) -> Result<Self, BadLen> {
-        // SAFETY: there is no "safe" way to construct a libc::can_frame with
-        // private fields, and zeroing out a struct is the proper way to do so.
-        // std::mem::zeroed() is not const (yet), but we can use transmute.
-        // Transmute is safe because zeroes transmuted into a libc::can_frame is
-        // valid for it's type.
-        let mut inner: libc::can_frame = unsafe {
-            std::mem::transmute([0u8; std::mem::size_of::<libc::can_frame>()])
-        };
-
-        inner.can_id = id_flags;
-        inner.can_dlc = len;
-        inner.data = data;
-
-        Self(inner).validate()
+        Self(CanFrame {
+            can_id: id_flags,
+            can_dlc: len,
+            __pad: 0,
+            __res0: 0,
+            __res1: 0,
+            data,
+        })
+        .validate()
     }
 
     /// Create a new frame from id_flags and a data slice.
@@ -123,7 +148,7 @@ impl Frame {
 
     /// Create a new [`Frame`] from a [`socketcan::CANFrame`].
     #[inline(always)] // because single function call
-    #[cfg(feature = "socketcan")]
+    #[cfg(all(target_os = "linux", feature = "socketcan"))]
     pub fn from_socketcan(frame: socketcan::CANFrame) -> Result<Self, BadLen> {
         // TODO(mdegans): looks like socketcan's "master" branch is also wrapping
         // a libc::can_frame, so in the future we can probably bypass this constructor
@@ -133,7 +158,7 @@ impl Frame {
 
     /// Convert into a [`socketcan::CANFrame`]
     #[inline(always)] // because single function call
-    #[cfg(feature = "socketcan")]
+    #[cfg(all(target_os = "linux", feature = "socketcan"))]
     pub fn into_socketcan(
         self,
     ) -> Result<socketcan::CANFrame, socketcan::ConstructionError> {
@@ -154,6 +179,19 @@ impl Frame {
         self.0.can_dlc as usize
     }
 
+    /// Format as a `candump` style `ID#DATA` string (eg. `2FA#0100`), which
+    /// can be parsed back with [`str::parse`].
+    pub fn to_candump(&self) -> String {
+        use std::fmt::Write;
+
+        let mut text = format!("{:03X}#", self.0.can_id);
+        for byte in self.data() {
+            // Writing to a String can't fail.
+            let _ = write!(text, "{byte:02X}");
+        }
+        text
+    }
+
     /// CAN frame's data as slice.
     #[inline(always)] // because trivial accessor (in release)
     pub const fn data(&self) -> &[u8] {
@@ -173,6 +211,32 @@ impl Frame {
     }
 }
 
+impl FromStr for Frame {
+    type Err = ParseFrameError;
+
+    /// Parse a `candump` style `ID#DATA` string (eg. `2FA#0100`), with the id
+    /// and data in hex.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        // The data is sliced by byte offsets below.
+        if !text.is_ascii() {
+            return Err(ParseFrameError::Syntax);
+        }
+        let (id, data) = text.split_once('#').ok_or(ParseFrameError::Syntax)?;
+        let id =
+            u32::from_str_radix(id, 16).map_err(|_| ParseFrameError::Syntax)?;
+        if data.len() % 2 != 0 {
+            return Err(ParseFrameError::Syntax);
+        }
+        let data = (0..data.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&data[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| ParseFrameError::Syntax)?;
+
+        Ok(Frame::from_id_slice(id, &data)?)
+    }
+}
+
 impl core::hash::Hash for Frame {
     /// This implementation of hash ignores any padding to avoid, for example,
     /// "duplicate" frames in a collection that differ.
@@ -254,7 +318,7 @@ impl std::fmt::Display for Frame {
     }
 }
 
-#[cfg(feature = "socketcan")]
+#[cfg(all(target_os = "linux", feature = "socketcan"))]
 impl TryFrom<socketcan::CANFrame> for Frame {
     type Error = BadLen;
 
@@ -264,12 +328,111 @@ impl TryFrom<socketcan::CANFrame> for Frame {
     }
 }
 
-/// Exists because serde's derive macros demand it for remote private fields,
-/// even when it's skip, and even when a conversion exists. Bug?
-#[inline(always)] // because we want 0 inlined everywhere this is called
+/// How a [`Frame`] is represented by `serde`. See
+/// [`Frame::with_serde_repr`].
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, derive_more::Display)]
+pub enum SerdeRepr {
+    /// `{"can_id":1,"can_dlc":8,"data":[2,3,4,5,6,7,8,9]}`, the layout of the
+    /// frame itself. Bytes past `can_dlc` are included.
+    #[default]
+    Struct,
+    /// `{"id":"2FA","data":"0100000000000000"}`, with only `can_dlc` bytes of
+    /// data.
+    Hex,
+    /// `"2FA#0100000000000000"`, as in a `candump -L` line.
+    Candump,
+}
+
+#[cfg(feature = "serde")]
+thread_local! {
+    static SERDE_REPR: std::cell::Cell<SerdeRepr> =
+        const { std::cell::Cell::new(SerdeRepr::Struct) };
+}
+
+#[cfg(feature = "serde")]
+impl Frame {
+    /// The [`SerdeRepr`] currently used on this thread.
+    pub fn serde_repr() -> SerdeRepr {
+        SERDE_REPR.with(|repr| repr.get())
+    }
+
+    /// Call `f` with [`Frame`]s (including those inside events and errors)
+    /// (de)serialized as `repr` on this thread. Deserializing expects the same
+    /// form a frame was serialized with.
+    pub fn with_serde_repr<T>(repr: SerdeRepr, f: impl FnOnce() -> T) -> T {
+        /// Restores the previous repr, even if `f` panics.
+        struct Restore(SerdeRepr);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                SERDE_REPR.with(|repr| repr.set(self.0));
+            }
+        }
+
+        let _restore = Restore(SERDE_REPR.with(|old| old.replace(repr)));
+        f()
+    }
+}
+
+/// The [`SerdeRepr::Struct`] form.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "Frame")]
+struct StructRepr(CanFrame);
+
+/// The [`SerdeRepr::Hex`] form.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "Frame")]
+struct HexRepr {
+    #[serde(with = "serde_hex::SerHex::<serde_hex::CompactCap>")]
+    id: u32,
+    #[serde(with = "serde_hex::SerHexSeq::<serde_hex::StrictCap>")]
+    data: Vec<u8>,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Frame {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match Frame::serde_repr() {
+            SerdeRepr::Struct => StructRepr(self.0).serialize(serializer),
+            SerdeRepr::Hex => HexRepr {
+                id: self.0.can_id,
+                data: self.data().to_vec(),
+            }
+            .serialize(serializer),
+            SerdeRepr::Candump => serializer.serialize_str(&self.to_candump()),
+        }
+    }
+}
+
 #[cfg(feature = "serde")]
-const fn _zero(_frame: &libc::can_frame) -> u8 {
-    0
+impl<'de> serde::Deserialize<'de> for Frame {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        match Frame::serde_repr() {
+            SerdeRepr::Struct => {
+                Frame(StructRepr::deserialize(deserializer)?.0)
+                    .validate()
+                    .map_err(D::Error::custom)
+            }
+            SerdeRepr::Hex => {
+                let hex = HexRepr::deserialize(deserializer)?;
+                Frame::from_id_slice(hex.id, &hex.data)
+                    .map_err(D::Error::custom)
+            }
+            SerdeRepr::Candump => String::deserialize(deserializer)?
+                .parse()
+                .map_err(D::Error::custom),
+        }
+    }
 }
 
 /// A custom deserializer for the frame's len value
@@ -298,12 +461,13 @@ where
     deserialize_len::<'de, D, 8>(d)
 }
 
-/// A Wrapper for a [`libc::can_frame`] that enables serialization
+/// Our own equivalent of the Linux `struct can_frame`, with the same field
+/// order, size and alignment, so it can be used on any platform.
 #[repr(C, align(8))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[cfg_attr(feature = "serde", serde(remote = "libc::can_frame"))]
-struct CanFrameWrapper {
-    /// The raw Id + flags first member of the
+#[derive(Clone, Copy)]
+struct CanFrame {
+    /// The raw Id + flags.
     can_id: u32,
     /// Class invariant: this is guaranteed by all constructors to be between
     /// 0 and 8
@@ -313,13 +477,11 @@ struct CanFrameWrapper {
     )]
     can_dlc: u8,
     /// Padding/reserved. Ignored by Serialize, Deserialize, Hash, PartialEq, etc.
-    // `skip` alone doesn't work, unfortunately, so we need a fake getter to inline 0
-    // at the callsites and then it works.
-    #[cfg_attr(feature = "serde", serde(skip, getter = "_zero"))]
+    #[cfg_attr(feature = "serde", serde(skip))]
     __pad: u8,
-    #[cfg_attr(feature = "serde", serde(skip, getter = "_zero"))]
+    #[cfg_attr(feature = "serde", serde(skip))]
     __res0: u8,
-    #[cfg_attr(feature = "serde", serde(skip, getter = "_zero"))]
+    #[cfg_attr(feature = "serde", serde(skip))]
     __res1: u8,
     /// The CAN payload data. Bytes past `len` are invalid, but will be
     /// Serialized and Deserialized if that feature is enabled.
@@ -333,34 +495,32 @@ struct CanFrameWrapper {
     data: [u8; 8],
 }
 
-impl CanFrameWrapper {
-    #[inline(always)] // because single function call
-    pub const fn into_libc_can_frame(self) -> libc::can_frame {
-        // SAFETY: The compiler guarantees the size is the same and serde's
-        // `remote_type` guarantees the layout is the same.
-        // Both structs are repr(C)
-        unsafe { std::mem::transmute(self) }
-    }
-}
-
-impl From<CanFrameWrapper> for libc::can_frame {
-    #[inline(always)] // because single function call
-    fn from(frame: CanFrameWrapper) -> Self {
-        frame.into_libc_can_frame()
-    }
-}
-
 /// Invalid CAN frame len (`can_dlc`). It would be unsafe to construct a [`Frame`].
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(derive_more::Display, Debug, derive_more::Error)]
 #[display = "Len (`can_dlc`) was > 8"]
 pub struct BadLen;
 
+/// An error parsing a [`Frame`] from a `candump` style `ID#DATA` string.
+#[derive(
+    derive_more::Display, Debug, derive_more::Error, derive_more::From,
+)]
+pub enum ParseFrameError {
+    /// Not `ID#DATA` with the id and data in hex.
+    #[display(fmt = "expected `ID#DATA` in hex")]
+    Syntax,
+    /// More than 8 bytes of data.
+    BadLen(BadLen),
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Frame;
+    #[cfg(feature = "serde")]
+    use super::SerdeRepr;
+    use super::{Frame, ParseFrameError};
 
     #[test]
+    #[cfg(target_os = "linux")]
     fn test_from_libc() {
         // SAFETY: Zeroing out the struct is the proper way to construct a
         // can_frame.
@@ -373,11 +533,15 @@ mod tests {
         assert_eq!(
             frame.data(),
             &libc_frame.data[0..libc_frame.can_dlc as usize]
-        )
+        );
+        let round_trip = frame.into_libc_can_frame();
+        assert_eq!(round_trip.can_id, libc_frame.can_id);
+        assert_eq!(round_trip.can_dlc, libc_frame.can_dlc);
+        assert_eq!(round_trip.data, libc_frame.data);
     }
 
     #[test]
-    #[cfg(feature = "socketcan")]
+    #[cfg(all(target_os = "linux", feature = "socketcan"))]
     fn test_from_socketcan() {
         let sc_frame =
             socketcan::CANFrame::new(1, &[2, 3, 4], false, false).unwrap();
@@ -424,4 +588,75 @@ mod tests {
         let err = serde_json::from_str::<Frame>(BAD_DATA).unwrap_err();
         assert_eq!(err.to_string(), "trailing characters at line 1 column 49");
     }
+
+    #[test]
+    fn test_candump_string() {
+        let frame = Frame::from_id_slice(0x2fa, &[1, 0, 0xab]).unwrap();
+        assert_eq!(frame.to_candump(), "2FA#0100AB");
+        assert_eq!("2FA#0100AB".parse::<Frame>().unwrap(), frame);
+        assert_eq!("2fa#0100ab".parse::<Frame>().unwrap(), frame);
+        assert!("7FF#".parse::<Frame>().unwrap().data().is_empty());
+
+        assert!(matches!(
+            "2FA#000000000000000000".parse::<Frame>(),
+            Err(ParseFrameError::BadLen(_))
+        ));
+        for bad in ["2FA", "2FA#0", "XYZ#00", "2FA#0G", "#00", "123#aéb"] {
+            assert!(matches!(
+                bad.parse::<Frame>(),
+                Err(ParseFrameError::Syntax)
+            ));
+        }
+        assert!("(1.0) can0 123#aéb"
+            .parse::<crate::candump::Record>()
+            .is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_frame_serde_repr() {
+        let frame = Frame::from_id_data_len(0x2fa, [1, 0, 0, 0, 0, 0, 0, 9], 8)
+            .unwrap();
+        let short = Frame::from_id_slice(0x7ff, &[]).unwrap();
+        let round_trip = |repr, frame: &Frame| {
+            Frame::with_serde_repr(repr, || {
+                let json = serde_json::to_string(frame).unwrap();
+                let back: Frame = serde_json::from_str(&json).unwrap();
+                assert_eq!(&back, frame);
+                json
+            })
+        };
+
+        assert_eq!(
+            round_trip(SerdeRepr::Hex, &frame),
+            r#"{"id":"2FA","data":"0100000000000009"}"#
+        );
+        assert_eq!(
+            round_trip(SerdeRepr::Hex, &short),
+            r#"{"id":"7FF","data":""}"#
+        );
+        assert_eq!(
+            round_trip(SerdeRepr::Candump, &frame),
+            r#""2FA#0100000000000009""#
+        );
+        assert_eq!(round_trip(SerdeRepr::Candump, &short), r#""7FF#""#);
+        // The repr is restored afterwards.
+        assert_eq!(Frame::serde_repr(), SerdeRepr::Struct);
+
+        // Every form enforces BadLen.
+        Frame::with_serde_repr(SerdeRepr::Hex, || {
+            let err = serde_json::from_str::<Frame>(
+                r#"{"id":"2FA","data":"000000000000000000"}"#,
+            )
+            .unwrap_err();
+            assert!(err.to_string().starts_with("BadLen"));
+        });
+        Frame::with_serde_repr(SerdeRepr::Candump, || {
+            let err =
+                serde_json::from_str::<Frame>(r#""2FA#000000000000000000""#)
+                    .unwrap_err();
+            assert!(err.to_string().starts_with("BadLen"));
+            assert!(serde_json::from_str::<Frame>(r#""2FA#0""#).is_err());
+        });
+    }
 }
@@ -0,0 +1,426 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! A configurable [`GestureRecognizer`] for [`Buttons`] that
+//! tells a short [`Gesture::Press`] from a [`Gesture::LongPress`], a
+//! [`Gesture::DoubleTap`] or a [`Gesture::Chord`] of several buttons held
+//! together. Useful for controlling things like an aftermarket head unit with
+//! the rear steering wheel buttons.
+//!
+//! Time is whatever [`Timestamp`] the caller supplies, so gestures can be
+//! recognized from replayed logs and tested with synthetic timestamps:
+//!
+//! ```
+//! use std::time::Duration;
+//! use jeep::{
+//!     events::steering_wheel::Buttons,
+//!     gestures::{Gesture, GestureConfig, GestureRecognizer},
+//! };
+//!
+//! let ms = Duration::from_millis;
+//! let mut recognizer = GestureRecognizer::new(GestureConfig {
+//!     chords: vec![Buttons::BACK_VOL_UP | Buttons::BACK_VOL_DOWN],
+//!     ..Default::default()
+//! });
+//!
+//! recognizer.update(Buttons::empty(), ms(0));
+//! recognizer.update(Buttons::BACK_VOL_UP, ms(100));
+//! let gestures =
+//!     recognizer.update(Buttons::BACK_VOL_UP | Buttons::BACK_VOL_DOWN, ms(200));
+//! assert_eq!(
+//!     gestures,
+//!     [(Gesture::Chord(Buttons::BACK_VOL_UP | Buttons::BACK_VOL_DOWN), ms(200))]
+//! );
+//! ```
+
+use std::time::Duration;
+
+use crate::{
+    edges::{ButtonFlags, Edge, Edges},
+    events::steering_wheel::Buttons,
+    Event, Timestamp,
+};
+
+/// A recognized [`Gesture`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Gesture {
+    /// A button was pressed and released quickly, once.
+    Press(Buttons),
+    /// A button has been held for at least
+    /// [`GestureConfig::long_press`]. Emitted once, while still held, or on
+    /// release if nothing advanced time while it was held.
+    LongPress(Buttons),
+    /// A button was pressed twice quickly.
+    DoubleTap(Buttons),
+    /// All buttons in one of the [`GestureConfig::chords`] are held at once.
+    /// Buttons that are part of a chord produce no other gestures until they
+    /// are released.
+    Chord(Buttons),
+}
+
+/// Timing thresholds and chords for a [`GestureRecognizer`].
+#[derive(Debug, Clone)]
+pub struct GestureConfig {
+    /// How long a button must be held to be a [`Gesture::LongPress`].
+    pub long_press: Duration,
+    /// Maximum time between releasing a button and pressing it again for a
+    /// [`Gesture::DoubleTap`]. A [`Gesture::Press`] is only emitted after
+    /// this much time passes without a second press, so set this to zero to
+    /// disable double taps and get presses as soon as the button is released.
+    pub double_tap: Duration,
+    /// Combinations of buttons to recognize as a [`Gesture::Chord`].
+    pub chords: Vec<Buttons>,
+    /// Ignore the `MYSTERY_BTN_*` bits by applying
+    /// [`Buttons::STOCK_BUTTONS`].
+    pub stock_buttons_only: bool,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        Self {
+            long_press: Duration::from_millis(600),
+            double_tap: Duration::from_millis(300),
+            chords: Vec::new(),
+            stock_buttons_only: false,
+        }
+    }
+}
+
+/// State of a single button.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    /// Not held, nothing pending.
+    Idle,
+    /// Held since some time.
+    Down(Timestamp),
+    /// Held long enough that a [`Gesture::LongPress`] was already emitted.
+    LongPressed,
+    /// Released at some time after a short press. Waiting to see if it's a
+    /// [`Gesture::DoubleTap`].
+    Up(Timestamp),
+    /// Pressed a second time within the double tap window.
+    SecondDown,
+    /// Part of a [`Gesture::Chord`]. Ignored until released.
+    Chorded,
+}
+
+/// Recognizes [`Gesture`]s from a stream of [`steering_wheel::Buttons`]
+/// states.
+///
+/// [`steering_wheel::Buttons`]: crate::events::steering_wheel::Buttons
+#[derive(Debug, Clone)]
+pub struct GestureRecognizer {
+    config: GestureConfig,
+    edges: Edges<Buttons>,
+    /// One state per button in [`ButtonFlags::BUTTONS`], same order.
+    states: Vec<State>,
+    /// Chords (by index in `config.chords`) which are currently held.
+    active_chords: Vec<bool>,
+}
+
+impl GestureRecognizer {
+    /// Create a new [`GestureRecognizer`] from a [`GestureConfig`].
+    pub fn new(config: GestureConfig) -> Self {
+        let edges = if config.stock_buttons_only {
+            Edges::with_mask(Buttons::STOCK_BUTTONS)
+        } else {
+            Edges::default()
+        };
+        let active_chords = vec![false; config.chords.len()];
+
+        Self {
+            config,
+            edges,
+            states: vec![State::Idle; Buttons::BUTTONS.len()],
+            active_chords,
+        }
+    }
+
+    /// The [`GestureConfig`] in use.
+    pub fn config(&self) -> &GestureConfig {
+        &self.config
+    }
+
+    /// Update with the currently `held` buttons at time `at`. Returns any
+    /// [`Gesture`]s recognized, along with the time they were recognized.
+    pub fn update(
+        &mut self,
+        held: Buttons,
+        at: Timestamp,
+    ) -> Vec<(Gesture, Timestamp)> {
+        // Time passed since the last update, so anything that became due
+        // before this state must be handled before its edges.
+        let mut gestures = self.tick(at);
+
+        let held = if self.config.stock_buttons_only {
+            held.stock_buttons_pressed()
+        } else {
+            held
+        };
+
+        // Chords take priority over anything else the buttons might be doing.
+        for (i, &chord) in self.config.chords.iter().enumerate() {
+            let is_held = !chord.is_empty() && held.contains(chord);
+            if is_held && !self.active_chords[i] {
+                gestures.push((Gesture::Chord(chord), at));
+                for (button, state) in
+                    Buttons::BUTTONS.iter().zip(self.states.iter_mut())
+                {
+                    if chord.contains(*button) {
+                        *state = State::Chorded;
+                    }
+                }
+            }
+            self.active_chords[i] = is_held;
+        }
+
+        let edges: Vec<_> = self.edges.update(held).collect();
+        for edge in edges {
+            match edge {
+                Edge::Pressed(button) => self.pressed(button, at),
+                Edge::Released(button) => {
+                    self.released(button, at, &mut gestures)
+                }
+            }
+        }
+
+        // Eg. presses, with double taps disabled.
+        gestures.extend(self.tick(at));
+
+        gestures
+    }
+
+    /// Update with an [`Event`]. [`Event::SteeringWheel`] events are passed
+    /// to [`GestureRecognizer::update`], while any other event just advances
+    /// time with [`GestureRecognizer::tick`].
+    pub fn update_event(
+        &mut self,
+        event: &Event,
+        at: Timestamp,
+    ) -> Vec<(Gesture, Timestamp)> {
+        match event {
+            Event::SteeringWheel(held) => self.update(*held, at),
+            _ => self.tick(at),
+        }
+    }
+
+    /// Advance time to `now` without a new button state, returning any
+    /// [`Gesture`]s that became due (long presses and single presses whose
+    /// double tap window expired).
+    pub fn tick(&mut self, now: Timestamp) -> Vec<(Gesture, Timestamp)> {
+        let mut gestures = Vec::new();
+
+        for (button, state) in
+            Buttons::BUTTONS.iter().zip(self.states.iter_mut())
+        {
+            match *state {
+                State::Down(since)
+                    if now.saturating_sub(since) >= self.config.long_press =>
+                {
+                    gestures.push((Gesture::LongPress(*button), now));
+                    *state = State::LongPressed;
+                }
+                State::Up(released)
+                    if now.saturating_sub(released)
+                        >= self.config.double_tap =>
+                {
+                    gestures.push((Gesture::Press(*button), now));
+                    *state = State::Idle;
+                }
+                _ => {}
+            }
+        }
+
+        gestures
+    }
+
+    /// The state for `button`.
+    fn state(&mut self, button: Buttons) -> &mut State {
+        // unwrap can never panic because `button` always comes from
+        // `Buttons::BUTTONS` (via `Edges`) and states has the same len.
+        let i = Buttons::BUTTONS.iter().position(|&b| b == button).unwrap();
+        &mut self.states[i]
+    }
+
+    fn pressed(&mut self, button: Buttons, at: Timestamp) {
+        let state = self.state(button);
+        *state = match *state {
+            State::Up(_) => State::SecondDown,
+            State::Chorded => State::Chorded,
+            _ => State::Down(at),
+        };
+    }
+
+    fn released(
+        &mut self,
+        button: Buttons,
+        at: Timestamp,
+        gestures: &mut Vec<(Gesture, Timestamp)>,
+    ) {
+        let state = self.state(button);
+        *state = match *state {
+            State::Down(_) => State::Up(at),
+            State::SecondDown => {
+                gestures.push((Gesture::DoubleTap(button), at));
+                State::Idle
+            }
+            _ => State::Idle,
+        };
+    }
+}
+
+impl Default for GestureRecognizer {
+    fn default() -> Self {
+        Self::new(GestureConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn test_press() {
+        let mut r = GestureRecognizer::default();
+        assert!(r.update(Buttons::empty(), ms(0)).is_empty());
+        assert!(r.update(Buttons::DPAD_UP, ms(100)).is_empty());
+        assert!(r.update(Buttons::empty(), ms(200)).is_empty());
+        // still waiting for a possible double tap
+        assert!(r.update(Buttons::empty(), ms(400)).is_empty());
+        assert_eq!(
+            r.tick(ms(500)),
+            [(Gesture::Press(Buttons::DPAD_UP), ms(500))]
+        );
+        assert!(r.tick(ms(600)).is_empty());
+    }
+
+    #[test]
+    fn test_press_without_double_tap() {
+        let mut r = GestureRecognizer::new(GestureConfig {
+            double_tap: Duration::ZERO,
+            ..Default::default()
+        });
+        r.update(Buttons::empty(), ms(0));
+        r.update(Buttons::DPAD_UP, ms(100));
+        assert_eq!(
+            r.update(Buttons::empty(), ms(200)),
+            [(Gesture::Press(Buttons::DPAD_UP), ms(200))]
+        );
+    }
+
+    #[test]
+    fn test_long_press() {
+        let mut r = GestureRecognizer::default();
+        r.update(Buttons::empty(), ms(0));
+        r.update(Buttons::BACK_SEEK_BUTTON, ms(100));
+        assert!(r.update(Buttons::BACK_SEEK_BUTTON, ms(600)).is_empty());
+        assert_eq!(
+            r.update(Buttons::BACK_SEEK_BUTTON, ms(700)),
+            [(Gesture::LongPress(Buttons::BACK_SEEK_BUTTON), ms(700))]
+        );
+        // only once, and no press on release
+        assert!(r.update(Buttons::BACK_SEEK_BUTTON, ms(800)).is_empty());
+        assert!(r.update(Buttons::empty(), ms(900)).is_empty());
+        assert!(r.tick(ms(2000)).is_empty());
+    }
+
+    #[test]
+    fn test_double_tap() {
+        let mut r = GestureRecognizer::default();
+        r.update(Buttons::empty(), ms(0));
+        r.update(Buttons::MYSTERY_BTN_3, ms(100));
+        r.update(Buttons::empty(), ms(200));
+        assert!(r.update(Buttons::MYSTERY_BTN_3, ms(300)).is_empty());
+        assert_eq!(
+            r.update(Buttons::empty(), ms(400)),
+            [(Gesture::DoubleTap(Buttons::MYSTERY_BTN_3), ms(400))]
+        );
+        assert!(r.tick(ms(2000)).is_empty());
+    }
+
+    #[test]
+    fn test_long_press_from_updates_only() {
+        let mut r = GestureRecognizer::default();
+        r.update(Buttons::empty(), ms(0));
+        r.update(Buttons::DPAD_UP, ms(100));
+        // No updates while held, then released 2 s later.
+        assert_eq!(
+            r.update(Buttons::empty(), ms(2100)),
+            [(Gesture::LongPress(Buttons::DPAD_UP), ms(2100))]
+        );
+        assert!(r.tick(ms(5000)).is_empty());
+    }
+
+    #[test]
+    fn test_slow_taps_from_updates_only() {
+        let mut r = GestureRecognizer::default();
+        r.update(Buttons::empty(), ms(0));
+        r.update(Buttons::DPAD_UP, ms(100));
+        r.update(Buttons::empty(), ms(200));
+        // Pressed again long after the double tap window.
+        assert_eq!(
+            r.update(Buttons::DPAD_UP, ms(2000)),
+            [(Gesture::Press(Buttons::DPAD_UP), ms(2000))]
+        );
+        assert!(r.update(Buttons::empty(), ms(2100)).is_empty());
+        assert_eq!(
+            r.tick(ms(2400)),
+            [(Gesture::Press(Buttons::DPAD_UP), ms(2400))]
+        );
+    }
+
+    #[test]
+    fn test_chord_suppresses_buttons() {
+        let chord = Buttons::BACK_VOL_UP | Buttons::BACK_VOL_DOWN;
+        let mut r = GestureRecognizer::new(GestureConfig {
+            chords: vec![chord],
+            ..Default::default()
+        });
+        r.update(Buttons::empty(), ms(0));
+        r.update(Buttons::BACK_VOL_UP, ms(100));
+        assert_eq!(
+            r.update(chord, ms(200)),
+            [(Gesture::Chord(chord), ms(200))]
+        );
+        // holding the chord for a long time is not a long press
+        assert!(r.update(chord, ms(2000)).is_empty());
+        assert!(r.update(Buttons::empty(), ms(2100)).is_empty());
+        assert!(r.tick(ms(5000)).is_empty());
+    }
+
+    #[test]
+    fn test_stock_buttons_only() {
+        let mut r = GestureRecognizer::new(GestureConfig {
+            double_tap: Duration::ZERO,
+            stock_buttons_only: true,
+            ..Default::default()
+        });
+        r.update(Buttons::empty(), ms(0));
+        r.update(Buttons::MYSTERY_BTN_0, ms(100));
+        assert!(r.update(Buttons::empty(), ms(200)).is_empty());
+    }
+}
@@ -28,11 +28,40 @@
 //!
 //! See this crates's examples as well as the doctests in various modules.
 
+pub mod analysis;
+pub mod candump;
+pub mod dispatch;
+pub mod edges;
 pub mod events;
 pub use events::Event;
+pub mod export;
 pub mod frame;
 pub use frame::Frame;
-#[cfg(feature = "socketcan")]
+pub mod gestures;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+pub mod on_change;
+#[cfg(feature = "rules")]
+pub mod rules;
+#[cfg(feature = "serde")]
+pub mod schema;
+pub mod security;
+pub mod state;
+pub use state::VehicleState;
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+pub mod stream;
+pub mod table;
+pub mod trip;
+pub mod units;
+pub mod watchdog;
+
+/// A point in time, as a [`Duration`](std::time::Duration) since some epoch
+/// chosen by the caller (eg. [`UNIX_EPOCH`](std::time::UNIX_EPOCH) or the
+/// start of a `candump` log). Using a caller supplied time rather than
+/// [`Instant`](std::time::Instant) makes it possible to replay logs and write
+/// tests with synthetic timestamps.
+pub type Timestamp = std::time::Duration;
+#[cfg(all(target_os = "linux", feature = "socketcan"))]
 pub mod listener;
-#[cfg(feature = "socketcan")]
+#[cfg(all(target_os = "linux", feature = "socketcan"))]
 pub use listener::Listener;
@@ -23,12 +23,22 @@
 //! Contains a [`socketcan`]-powered event [`Listener`] to handle events from
 //! a Linux socketcan interface. Requires the `socketcan` feature.
 
+use std::{
+    sync::{Mutex, PoisonError},
+    time::Instant,
+};
+
 use derive_more::{Display, Error as DeriveError, From};
 use socketcan::CANSocket;
+use static_assertions as sa;
 
 use crate::{
-    events::{CanFrameError, Event, OneOrMany, ParseError},
+    events::{
+        registry, CanFrameError, Event, EventKinds, OneOrMany, ParseError,
+    },
     frame::BadLen,
+    on_change::ChangeFilter,
+    Frame,
 };
 use OneOrMany::{Many, One};
 
@@ -61,6 +71,9 @@ pub type Message = Result<Event, Error>;
 pub struct Messages<'a> {
     sock: &'a CANSocket,
     pending: Vec<Event>,
+    on_change: Option<&'a Mutex<ChangeFilter>>,
+    kinds: Option<EventKinds>,
+    epoch: Instant,
 }
 
 /// An [`Iterator`] through [`Messages`] (`Vec<Result<Event, Error>>`) from the [`Listener`]
@@ -68,6 +81,28 @@ impl<'a> Iterator for Messages<'a> {
     type Item = Message;
 
     fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match (self.next_unfiltered()?, self.on_change) {
+                (Ok(event), Some(filter)) => {
+                    let at = self.epoch.elapsed();
+                    // The filter only remembers recent events, so it's still
+                    // usable if another thread panicked while holding it.
+                    let mut filter =
+                        filter.lock().unwrap_or_else(PoisonError::into_inner);
+                    if filter.check(&event, at) {
+                        return Some(Ok(event));
+                    }
+                }
+                // Without a filter, every message is yielded.
+                (message, _) => return Some(message),
+            }
+        }
+    }
+}
+
+impl<'a> Messages<'a> {
+    /// The next [`Message`], ignoring any [`ChangeFilter`].
+    fn next_unfiltered(&mut self) -> Option<Message> {
         // If there are pending events that have not been yielded, yield them
         // before doing any IO and getting an new frame.
         if !self.pending.is_empty() {
@@ -78,33 +113,79 @@ impl<'a> Iterator for Messages<'a> {
             // can get cleaner.
             return self.pending.pop().map(|event| Ok(event));
         }
-        match self.sock.read_frame() {
+        loop {
+            let frame = match self.sock.read_frame() {
+                Ok(frame) => frame,
+                // Some kind of IO error from `read_frame`
+                Err(err) => match err.kind() {
+                    // Reading would block and we're set to non-blocking, so
+                    // we're done iterating for now (poll for some more
+                    // messages later).
+                    std::io::ErrorKind::WouldBlock => return None,
+                    // Any other IO error we wrap in an err. A simpler design
+                    // just returns None for any err, but then there's no way
+                    // to tell the difference between IOError and WouldBlock,
+                    // and some IO errors might be recoverable if the socket is
+                    // still open.
+                    _ => return Some(Err(Error::from(err))),
+                },
+            };
+
             // We got a frame, so try to parse One or Many Events from it.
-            Ok(frame) => match Event::parse(frame) {
+            let parsed = match self.kinds {
+                // Every kind is selected, so parse everything.
+                None => Event::parse(frame).map_err(Error::from),
+                Some(kinds) => {
+                    let frame = match Frame::from_socketcan(frame) {
+                        Ok(frame) => frame,
+                        Err(err) => return Some(Err(err.into())),
+                    };
+                    match registry::decode_kinds(frame, kinds) {
+                        Some(parsed) => parsed.map_err(Error::from),
+                        // Not one of the selected kinds, so skip decoding
+                        // and read the next frame.
+                        None => continue,
+                    }
+                }
+            };
+
+            match parsed {
                 // Many events from a single CANFrame
                 Ok(Many(events)) => {
                     self.pending = events;
-                    // Unwrap here can never panic because the parsing code
-                    // in every  `try_from` always returns at least one event
-                    // inside a `Many` variant (unless that's broke).
-                    Some(Ok(self.pending.pop().unwrap()))
+                    // The parsing code in every `try_from` always returns at
+                    // least one event inside a `Many` variant, but some may
+                    // have been filtered out by kind.
+                    if let Some(event) = self.pending.pop() {
+                        return Some(Ok(event));
+                    }
                 }
                 // One `Event` from a single CANFrame
-                Ok(One(event)) => Some(Ok(event)),
+                Ok(One(event)) => return Some(Ok(event)),
                 // ParseError from a CANFrame
-                Err(err) => Some(Err(err.into())),
-            },
-            // Some kind of IO error from `read_frame`
-            Err(err) => match err.kind() {
-                // Reading would block and we're set to non-blocking, so we're
-                // done iterating for now (poll for some more messages later).
-                std::io::ErrorKind::WouldBlock => None,
-                // Any other IO error we wrap in an err. A simpler design just
-                // returns None for any err, but then there's no way to tell the
-                // difference between IOError and WouldBlock, and some IO errors
-                // might be recoverable if the socket is still open.
-                _ => Some(Err(Error::from(err))),
-            },
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+/// An iterator through raw [`Frame`]s (or [`Error`]s) from the [`Listener`],
+/// without decoding them. Useful for reverse engineering.
+pub struct Frames<'a> {
+    sock: &'a CANSocket,
+}
+
+impl<'a> Iterator for Frames<'a> {
+    type Item = Result<Frame, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.sock.read_frame() {
+            Ok(frame) => {
+                Some(Frame::from_socketcan(frame).map_err(Error::from))
+            }
+            // Same as `Messages`, we're done for now.
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => None,
+            Err(err) => Some(Err(Error::from(err))),
         }
     }
 }
@@ -112,7 +193,13 @@ impl<'a> Iterator for Messages<'a> {
 /// A Listener's job is to listen for CAN [`Messages`].
 pub struct Listener {
     sock: CANSocket,
+    on_change: Option<Mutex<ChangeFilter>>,
+    kinds: Option<EventKinds>,
+    epoch: Instant,
 }
+// So it can be shared between threads (eg. in an `Arc`), since
+// `Listener::messages` only needs `&self`.
+sa::assert_impl_all!(Listener: Send, Sync);
 
 impl Listener {
     /// Connect the `Listener` to a can `interface` like `"can1"`.
@@ -130,7 +217,40 @@ impl Listener {
         let sock = CANSocket::open(interface)?;
         sock.set_nonblocking(!blocking)?;
 
-        Ok(Listener { sock })
+        Ok(Listener {
+            sock,
+            on_change: None,
+            kinds: None,
+            epoch: Instant::now(),
+        })
+    }
+
+    /// Only yield [`Event`]s that pass a [`ChangeFilter`] (eg. that are
+    /// different from the last one from the same source). The filter's state
+    /// is kept between calls to [`Listener::messages`].
+    pub fn with_change_filter(mut self, filter: ChangeFilter) -> Self {
+        self.on_change = Some(Mutex::new(filter));
+        self
+    }
+
+    /// Only decode the selected `kinds` of [`Event`]. Decoders for other kinds
+    /// are skipped entirely, and a kernel filter is set on the socket so
+    /// frames from other IDs (including unknown ones) are never even
+    /// received.
+    pub fn with_kinds(
+        mut self,
+        kinds: EventKinds,
+    ) -> Result<Self, std::io::Error> {
+        let filters = registry::ids_for(kinds)
+            .map(|id| socketcan::CANFilter::new(id, Frame::ID_MASK))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, err)
+            })?;
+        self.sock.set_filter(&filters)?;
+        self.kinds = Some(kinds);
+
+        Ok(self)
     }
 
     /// Iterate through all [`Event`] (or [`Error`]) waiting on the
@@ -144,6 +264,17 @@ impl Listener {
         Messages {
             sock: &self.sock,
             pending: Vec::new(),
+            on_change: self.on_change.as_ref(),
+            kinds: self.kinds,
+            epoch: self.epoch,
         }
     }
+
+    /// Iterate through raw [`Frame`]s (or [`Error`]s) waiting on the CAN bus,
+    /// like [`Listener::messages`] but without decoding. Any
+    /// [`ChangeFilter`] is not applied, but a kernel filter set by
+    /// [`Listener::with_kinds`] still is.
+    pub fn frames<'a>(&'a self) -> Frames<'a> {
+        Frames { sock: &self.sock }
+    }
 }
@@ -0,0 +1,754 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! An MQTT [`Bridge`] that publishes decoded [`Event`]s and the latest
+//! [`VehicleState`]. Requires the `mqtt` feature.
+//!
+//! With the default [`MqttConfig`] and `name = "blue"`, the topics are:
+//!
+//! * `jeep/blue/<type>` - every [`Event`], as a versioned
+//!   [`schema::Record`] (eg. `jeep/blue/doors`), where `<type>` is the
+//!   record's `type`. Not retained.
+//! * `jeep/blue/state/<field>` - the same record, whenever it changes a
+//!   [`VehicleState`] field (eg. `jeep/blue/state/cabin_temperature`).
+//!   Retained, so new subscribers get the latest values right away.
+//! * `jeep/blue/status` - `online` once connected, and `offline` when the
+//!   bridge disconnects or (as the last will) drops off. Retained.
+//!
+//! While the broker is unreachable, messages are buffered (up to
+//! [`MqttConfig::buffer`]) and the bridge keeps retrying.
+//!
+//! ```
+//! # use jeep::mqtt::{MqttConfig, Qos};
+//! let config = MqttConfig::from_toml(r#"
+//!     host = "broker.local"
+//!     name = "blue"
+//!     event_qos = 0
+//!     state_qos = 1
+//! "#).unwrap();
+//! assert_eq!(config.port, 1883);
+//! assert_eq!(config.state_qos, Qos::AtLeastOnce);
+//! ```
+
+use std::{
+    fmt,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use derive_more::From;
+use serde::Deserialize;
+
+use crate::{schema, state::Fields, Event, Timestamp, VehicleState};
+
+/// MQTT quality of service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "u8")]
+pub enum Qos {
+    /// `0`: at most once (fire and forget).
+    AtMostOnce,
+    /// `1`: at least once.
+    AtLeastOnce,
+    /// `2`: exactly once.
+    ExactlyOnce,
+}
+
+impl TryFrom<u8> for Qos {
+    type Error = String;
+
+    fn try_from(qos: u8) -> Result<Self, Self::Error> {
+        match qos {
+            0 => Ok(Qos::AtMostOnce),
+            1 => Ok(Qos::AtLeastOnce),
+            2 => Ok(Qos::ExactlyOnce),
+            _ => Err(format!("qos must be 0, 1 or 2, not {qos}")),
+        }
+    }
+}
+
+impl From<Qos> for rumqttc::QoS {
+    fn from(qos: Qos) -> Self {
+        match qos {
+            Qos::AtMostOnce => rumqttc::QoS::AtMostOnce,
+            Qos::AtLeastOnce => rumqttc::QoS::AtLeastOnce,
+            Qos::ExactlyOnce => rumqttc::QoS::ExactlyOnce,
+        }
+    }
+}
+
+/// Configuration for a [`Bridge`]. Usually loaded from TOML, where every
+/// field is optional.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MqttConfig {
+    /// Broker host (default: `localhost`).
+    pub host: String,
+    /// Broker port (default: `1883`).
+    pub port: u16,
+    /// MQTT client id (default: `jeep-<name>`).
+    pub client_id: Option<String>,
+    /// The vehicle's VIN or name, used in topics (default: `jeep`).
+    pub name: String,
+    /// First topic level (default: `jeep`).
+    pub prefix: String,
+    /// Username, if the broker needs one (requires `password`).
+    pub username: Option<String>,
+    /// Password, if the broker needs one (requires `username`).
+    pub password: Option<String>,
+    /// Seconds between keep alive pings (default: `30`).
+    pub keep_alive: u64,
+    /// Seconds to wait before reconnecting (default: `5`).
+    pub retry: f64,
+    /// Messages to buffer while the broker is unreachable (default: `1000`).
+    /// More than this are dropped (see [`Bridge::dropped`]).
+    pub buffer: usize,
+    /// [`Qos`] for event topics (default: `0`).
+    pub event_qos: Qos,
+    /// [`Qos`] for state and status topics (default: `1`).
+    pub state_qos: Qos,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_owned(),
+            port: 1883,
+            client_id: None,
+            name: "jeep".to_owned(),
+            prefix: "jeep".to_owned(),
+            username: None,
+            password: None,
+            keep_alive: 30,
+            retry: 5.0,
+            buffer: 1000,
+            event_qos: Qos::AtMostOnce,
+            state_qos: Qos::AtLeastOnce,
+        }
+    }
+}
+
+impl MqttConfig {
+    /// Parse and validate an [`MqttConfig`] from a TOML string.
+    pub fn from_toml(s: &str) -> Result<Self, Error> {
+        let config: Self = toml::from_str(s)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Read, parse and validate an [`MqttConfig`] from a TOML file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    /// Check the config makes sense.
+    pub fn validate(&self) -> Result<(), Error> {
+        for (field, level) in [("name", &self.name), ("prefix", &self.prefix)] {
+            if level.is_empty() || level.contains(['/', '+', '#']) {
+                return Err(Error::Invalid(format!(
+                    "`{field}` must be a single topic level without wildcards"
+                )));
+            }
+        }
+        if self.buffer == 0 {
+            return Err(Error::Invalid("`buffer` can't be 0".to_owned()));
+        }
+        if !(self.retry.is_finite() && self.retry >= 0.0) {
+            return Err(Error::Invalid("`retry` must be >= 0".to_owned()));
+        }
+        if self.keep_alive == 0 {
+            return Err(Error::Invalid("`keep_alive` can't be 0".to_owned()));
+        }
+        if self.username.is_some() != self.password.is_some() {
+            return Err(Error::Invalid(
+                "`username` and `password` must be set together".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The full topic for `suffix` (eg. `jeep/<name>/doors`).
+    pub fn topic(&self, suffix: &str) -> String {
+        format!("{}/{}/{suffix}", self.prefix, self.name)
+    }
+}
+
+/// An error loading an [`MqttConfig`].
+#[derive(Debug, From)]
+pub enum Error {
+    /// The config file couldn't be read.
+    Io(std::io::Error),
+    /// The config file isn't valid TOML or has unknown or invalid fields.
+    Toml(toml::de::Error),
+    /// The config is well formed, but doesn't make sense.
+    #[from(ignore)]
+    Invalid(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "could not read mqtt config: {err}"),
+            Error::Toml(err) => write!(f, "could not parse mqtt config: {err}"),
+            Error::Invalid(detail) => {
+                write!(f, "invalid mqtt config: {detail}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Toml(err) => Some(err),
+            Error::Invalid(_) => None,
+        }
+    }
+}
+
+/// A message to publish.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Publication {
+    /// Full topic.
+    pub topic: String,
+    /// Message body.
+    pub payload: Vec<u8>,
+    /// Quality of service.
+    pub qos: Qos,
+    /// Whether the broker should retain the message.
+    pub retain: bool,
+}
+
+/// [`VehicleState`] fields and their state topic names.
+const STATE_TOPICS: [(Fields, &str); 12] = [
+    (Fields::DOORS, "doors"),
+    (Fields::LOCKS, "locks"),
+    (Fields::IGNITION, "ignition"),
+    (Fields::RPMS, "rpms"),
+    (Fields::APPROX_MPH, "approx_mph"),
+    (Fields::MPH, "mph"),
+    (Fields::CABIN_TEMPERATURE, "cabin_temperature"),
+    (Fields::AUX, "aux"),
+    (Fields::ODOMETER, "odometer"),
+    (Fields::DATETIME, "datetime"),
+    (Fields::CAMERA, "camera"),
+    (Fields::REMOTE, "remote"),
+];
+
+/// Turns [`Event`]s into [`Publication`]s, without any networking. Used by
+/// [`Bridge`].
+#[derive(Debug, Clone)]
+pub struct Publisher {
+    config: MqttConfig,
+    state: VehicleState,
+}
+
+impl Publisher {
+    /// Create a new [`Publisher`] from an [`MqttConfig`].
+    pub fn new(config: MqttConfig) -> Self {
+        Self {
+            config,
+            state: VehicleState::default(),
+        }
+    }
+
+    /// Get the [`MqttConfig`].
+    pub fn config(&self) -> &MqttConfig {
+        &self.config
+    }
+
+    /// The latest [`VehicleState`].
+    pub fn state(&self) -> &VehicleState {
+        &self.state
+    }
+
+    /// The retained status message (`online` or `offline`).
+    pub fn status(&self, online: bool) -> Publication {
+        Publication {
+            topic: self.config.topic("status"),
+            payload: if online { "online" } else { "offline" }.into(),
+            qos: self.config.state_qos,
+            retain: true,
+        }
+    }
+
+    /// The [`Publication`]s for an `event` that happened `at` some time since
+    /// the Unix epoch: the event itself and any state topics it changed.
+    pub fn publications(
+        &mut self,
+        event: &Event,
+        at: Timestamp,
+    ) -> Vec<Publication> {
+        let record = schema::Record::new(event, at);
+        let payload =
+            serde_json::to_vec(&record).expect("records always serialize");
+
+        let mut publications = vec![Publication {
+            topic: self.config.topic(record.event.kind()),
+            payload: payload.clone(),
+            qos: self.config.event_qos,
+            retain: false,
+        }];
+        let changed = self.state.update(event, at);
+        for (field, name) in STATE_TOPICS {
+            if changed.contains(field) {
+                publications.push(Publication {
+                    topic: self.config.topic(&format!("state/{name}")),
+                    payload: payload.clone(),
+                    qos: self.config.state_qos,
+                    retain: true,
+                });
+            }
+        }
+        publications
+    }
+}
+
+/// Publishes [`Event`]s to an MQTT broker. See the [module docs](self).
+pub struct Bridge {
+    publisher: Publisher,
+    client: rumqttc::Client,
+    connected: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+    dropped: u64,
+}
+
+impl Bridge {
+    /// Start a [`Bridge`] from an [`MqttConfig`]. This doesn't wait for the
+    /// connection: messages are buffered until the broker is reachable.
+    pub fn connect(config: MqttConfig) -> Result<Self, Error> {
+        config.validate()?;
+        let publisher = Publisher::new(config);
+        let config = publisher.config();
+
+        let client_id = config
+            .client_id
+            .clone()
+            .unwrap_or_else(|| format!("jeep-{}", config.name));
+        let mut options =
+            rumqttc::MqttOptions::new(client_id, &config.host, config.port);
+        options.set_keep_alive(Duration::from_secs(config.keep_alive));
+        if let (Some(username), Some(password)) =
+            (&config.username, &config.password)
+        {
+            options.set_credentials(username, password);
+        }
+        let offline = publisher.status(false);
+        options.set_last_will(rumqttc::LastWill::new(
+            offline.topic,
+            offline.payload,
+            offline.qos.into(),
+            offline.retain,
+        ));
+
+        let (client, mut connection) =
+            rumqttc::Client::new(options, config.buffer);
+        let connected = Arc::new(AtomicBool::new(false));
+        let online = publisher.status(true);
+        let retry = Duration::from_secs_f64(config.retry);
+        let thread = {
+            let client = client.clone();
+            let connected = connected.clone();
+            std::thread::spawn(move || {
+                use rumqttc::{Event::*, Outgoing, Packet};
+
+                for event in connection.iter() {
+                    match event {
+                        Ok(Incoming(Packet::ConnAck(_))) => {
+                            connected.store(true, Ordering::Relaxed);
+                            // If this doesn't fit, the buffer is full of
+                            // newer news anyway.
+                            let _ = client.try_publish(
+                                online.topic.clone(),
+                                online.qos.into(),
+                                online.retain,
+                                online.payload.clone(),
+                            );
+                        }
+                        Ok(Outgoing(Outgoing::Disconnect)) => break,
+                        Ok(_) => {}
+                        Err(_) => {
+                            connected.store(false, Ordering::Relaxed);
+                            std::thread::sleep(retry);
+                        }
+                    }
+                }
+                connected.store(false, Ordering::Relaxed);
+            })
+        };
+
+        Ok(Self {
+            publisher,
+            client,
+            connected,
+            thread,
+            dropped: 0,
+        })
+    }
+
+    /// Get the [`Publisher`] (eg. for the latest [`VehicleState`]).
+    pub fn publisher(&self) -> &Publisher {
+        &self.publisher
+    }
+
+    /// Whether the broker is currently connected.
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// Messages dropped because the buffer was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    fn send(&mut self, publication: Publication) {
+        let sent = self.client.try_publish(
+            publication.topic,
+            publication.qos.into(),
+            publication.retain,
+            publication.payload,
+        );
+        if sent.is_err() {
+            self.dropped += 1;
+        }
+    }
+
+    /// Publish an `event` that happened `at` some time since the Unix epoch,
+    /// and any state it changed.
+    pub fn publish(&mut self, event: &Event, at: Timestamp) {
+        for publication in self.publisher.publications(event, at) {
+            self.send(publication);
+        }
+    }
+
+    /// Publish the `offline` status and disconnect, waiting up to `timeout`
+    /// for buffered messages to be sent. Returns false if that timed out, or
+    /// if the disconnect couldn't be queued because the buffer is full (eg.
+    /// because the broker is unreachable).
+    pub fn disconnect(mut self, timeout: Duration) -> bool {
+        let offline = self.publisher.status(false);
+        self.send(offline);
+        if self.client.try_disconnect().is_err() {
+            return false;
+        }
+
+        let deadline = Instant::now() + timeout;
+        while !self.thread.is_finished() {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{doors::Doors, lights::Lights};
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+        sync::Mutex,
+    };
+
+    /// What the [`StandIn`] broker received.
+    #[derive(Debug, Default)]
+    struct Received {
+        /// (topic, payload, retain) of every PUBLISH.
+        published: Vec<(String, String, bool)>,
+        /// (topic, payload, retain) of the last will of the last CONNECT.
+        will: Option<(String, String, bool)>,
+    }
+
+    /// A minimal in-process MQTT 3.1.1 broker that records what it's sent.
+    struct StandIn {
+        received: Arc<Mutex<Received>>,
+    }
+
+    impl StandIn {
+        fn start(listener: TcpListener) -> Self {
+            let received = Arc::new(Mutex::new(Received::default()));
+            let shared = received.clone();
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let shared = shared.clone();
+                    std::thread::spawn(move || {
+                        let _ = Self::serve(stream?, &shared);
+                        std::io::Result::Ok(())
+                    });
+                }
+            });
+            Self { received }
+        }
+
+        fn serve(
+            mut stream: TcpStream,
+            received: &Mutex<Received>,
+        ) -> std::io::Result<()> {
+            loop {
+                let mut header = [0u8; 1];
+                stream.read_exact(&mut header)?;
+                let (mut len, mut shift) = (0usize, 0);
+                loop {
+                    let mut byte = [0u8; 1];
+                    stream.read_exact(&mut byte)?;
+                    len |= usize::from(byte[0] & 0x7f) << shift;
+                    shift += 7;
+                    if byte[0] & 0x80 == 0 {
+                        break;
+                    }
+                }
+                let mut body = vec![0u8; len];
+                stream.read_exact(&mut body)?;
+                let mut at = 0;
+                let field = |at: &mut usize| {
+                    let len = usize::from(body[*at]) << 8
+                        | usize::from(body[*at + 1]);
+                    *at += 2 + len;
+                    body[*at - len..*at].to_vec()
+                };
+                let text = |bytes: Vec<u8>| String::from_utf8(bytes).unwrap();
+
+                match header[0] >> 4 {
+                    // CONNECT
+                    1 => {
+                        field(&mut at); // protocol name
+                        let flags = body[at + 1];
+                        at += 4; // level, flags, keep alive
+                        field(&mut at); // client id
+                        if flags & 0x04 != 0 {
+                            let topic = text(field(&mut at));
+                            let message = text(field(&mut at));
+                            received.lock().unwrap().will =
+                                Some((topic, message, flags & 0x20 != 0));
+                        }
+                        stream.write_all(&[0x20, 2, 0, 0])?;
+                    }
+                    // PUBLISH
+                    3 => {
+                        let qos = (header[0] >> 1) & 3;
+                        let topic = text(field(&mut at));
+                        if qos > 0 {
+                            let id = [body[at], body[at + 1]];
+                            at += 2;
+                            // PUBACK or PUBREC
+                            let kind = if qos == 1 { 0x40 } else { 0x50 };
+                            stream.write_all(&[kind, 2, id[0], id[1]])?;
+                        }
+                        let payload = text(body[at..].to_vec());
+                        received.lock().unwrap().published.push((
+                            topic,
+                            payload,
+                            header[0] & 1 != 0,
+                        ));
+                    }
+                    // PUBREL -> PUBCOMP
+                    6 => stream.write_all(&[0x70, 2, body[0], body[1]])?,
+                    // PINGREQ -> PINGRESP
+                    12 => stream.write_all(&[0xd0, 0])?,
+                    // DISCONNECT
+                    14 => return Ok(()),
+                    _ => {}
+                }
+            }
+        }
+
+        /// Wait until `n` messages were published, returning them.
+        fn wait_for(&self, n: usize) -> Vec<(String, String, bool)> {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            loop {
+                let published = self.received.lock().unwrap().published.clone();
+                if published.len() >= n || Instant::now() > deadline {
+                    return published;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+    }
+
+    fn config(port: u16) -> MqttConfig {
+        MqttConfig {
+            port,
+            host: "127.0.0.1".to_owned(),
+            name: "test".to_owned(),
+            retry: 0.05,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_config() {
+        assert_eq!(MqttConfig::from_toml("").unwrap(), MqttConfig::default());
+        let config = MqttConfig::from_toml("retry = 1\nevent_qos = 2").unwrap();
+        assert_eq!(config.retry, 1.0);
+        assert_eq!(config.event_qos, Qos::ExactlyOnce);
+        for bad in [
+            "event_qos = 3",
+            "name = \"a/b\"",
+            "buffer = 0",
+            "color = 1",
+            "username = \"jeep\"",
+            "password = \"hunter2\"",
+        ] {
+            assert!(MqttConfig::from_toml(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_publications() {
+        let mut publisher = Publisher::new(config(1883));
+        let at = Duration::from_secs(1);
+        let topics = |publications: Vec<Publication>| {
+            publications
+                .into_iter()
+                .map(|p| (p.topic, p.retain))
+                .collect::<Vec<_>>()
+        };
+
+        let doors = Event::Doors(Doors::DRIVER);
+        let publications = publisher.publications(&doors, at);
+        assert_eq!(
+            String::from_utf8(publications[0].payload.clone()).unwrap(),
+            r#"{"version":1,"timestamp":1.0,"type":"doors","open":["driver"],"all_closed":false,"raw":1}"#
+        );
+        assert_eq!(
+            topics(publications),
+            [
+                ("jeep/test/doors".to_owned(), false),
+                ("jeep/test/state/doors".to_owned(), true)
+            ]
+        );
+        // Unchanged state isn't published again.
+        assert_eq!(
+            topics(publisher.publications(&doors, at)),
+            [("jeep/test/doors".to_owned(), false)]
+        );
+        // Neither is an event without state.
+        assert_eq!(
+            topics(
+                publisher
+                    .publications(&Event::Lights(Lights::HazardsOnOff), at)
+            ),
+            [("jeep/test/hazards".to_owned(), false)]
+        );
+    }
+
+    #[test]
+    fn test_bridge() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let broker = StandIn::start(listener);
+
+        let mut bridge = Bridge::connect(config(port)).unwrap();
+        bridge.publish(&Event::Doors(Doors::DRIVER), Duration::ZERO);
+        let mut published = broker.wait_for(3);
+        assert!(bridge.disconnect(Duration::from_secs(5)));
+
+        published.extend(broker.wait_for(4).into_iter().skip(3));
+        let summary: Vec<_> = published
+            .iter()
+            .map(|(topic, payload, retain)| {
+                let payload = if payload.starts_with('{') {
+                    "{..}"
+                } else {
+                    payload
+                };
+                (topic.as_str(), payload, *retain)
+            })
+            .collect();
+        // The bridge marks itself online once connected, so that can come
+        // before or after the event.
+        assert!(summary.contains(&("jeep/test/status", "online", true)));
+        assert!(summary.contains(&("jeep/test/doors", "{..}", false)));
+        assert!(summary.contains(&("jeep/test/state/doors", "{..}", true)));
+        assert_eq!(
+            summary.last(),
+            Some(&("jeep/test/status", "offline", true))
+        );
+
+        let will = broker.received.lock().unwrap().will.clone();
+        assert_eq!(
+            will,
+            Some(("jeep/test/status".to_owned(), "offline".to_owned(), true))
+        );
+    }
+
+    #[test]
+    fn test_bridge_buffers_until_broker_is_up() {
+        // Find a free port, with nothing listening on it yet.
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let mut bridge = Bridge::connect(config(port)).unwrap();
+        bridge.publish(&Event::Doors(Doors::DRIVER), Duration::ZERO);
+        bridge.publish(&Event::Doors(Doors::empty()), Duration::ZERO);
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(!bridge.is_connected());
+
+        let broker =
+            StandIn::start(TcpListener::bind(("127.0.0.1", port)).unwrap());
+        let published = broker.wait_for(5);
+        let doors = published
+            .iter()
+            .filter(|(topic, ..)| topic == "jeep/test/doors")
+            .count();
+        assert_eq!(doors, 2);
+        assert!(bridge.is_connected());
+        assert_eq!(bridge.dropped(), 0);
+        assert!(bridge.disconnect(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_disconnect_with_full_buffer() {
+        // Nothing ever listens on this port.
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let mut bridge = Bridge::connect(MqttConfig {
+            buffer: 2,
+            ..config(port)
+        })
+        .unwrap();
+        for _ in 0..5 {
+            bridge.publish(&Event::Doors(Doors::DRIVER), Duration::ZERO);
+        }
+        assert!(bridge.dropped() > 0);
+
+        // This must return rather than block on the full buffer.
+        let start = Instant::now();
+        assert!(!bridge.disconnect(Duration::from_secs(5)));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
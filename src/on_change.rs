@@ -0,0 +1,307 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Emit [`Event`]s only when they change.
+//!
+//! Many frames (eg. doors and locks from `0x2fa`, cabin temperature from
+//! `0x33a` or [`Remote::Idle`](crate::events::remote::Remote::Idle)) are sent
+//! periodically whether anything changed or not. A [`ChangeFilter`] remembers
+//! the last [`Event`] emitted from each source and suppresses identical ones,
+//! optionally re-emitting a heartbeat and ignoring small changes to analog
+//! values.
+//!
+//! ```
+//! use std::time::Duration;
+//! use jeep::{
+//!     events::doors::Doors,
+//!     on_change::{ChangeFilter, OnChangeConfig},
+//!     Event,
+//! };
+//!
+//! let mut filter = ChangeFilter::new(OnChangeConfig {
+//!     heartbeat: Some(Duration::from_secs(10)),
+//!     ..Default::default()
+//! });
+//! let closed = Event::Doors(Doors::empty());
+//!
+//! assert!(filter.check(&closed, Duration::from_secs(0)));
+//! assert!(!filter.check(&closed, Duration::from_secs(1)));
+//! assert!(filter.check(&Event::Doors(Doors::DRIVER), Duration::from_secs(2)));
+//! // Heartbeat
+//! assert!(filter.check(&Event::Doors(Doors::DRIVER), Duration::from_secs(12)));
+//! ```
+//!
+//! [`OnChange`] adapts any iterator of `Result<Event, E>` (like
+//! [`Messages`](crate::listener::Messages)), and a
+//! [`Listener`](crate::Listener) can also filter on its own with
+//! `Listener::with_change_filter`.
+
+use std::{
+    collections::HashMap,
+    mem::{discriminant, Discriminant},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    events::{
+        battery::Battery, control_panel::ControlPanel, engine::Engine,
+        force::Force, hvac::HVAC, lights::Lights, FrontOrRear,
+    },
+    Event, Timestamp,
+};
+
+/// Settings for a [`ChangeFilter`].
+#[derive(Debug, Clone, Default)]
+pub struct OnChangeConfig {
+    /// If set, re-emit an unchanged [`Event`] when this much time has passed
+    /// since it was last emitted.
+    pub heartbeat: Option<Duration>,
+    /// Cabin [`Temperature`](crate::events::hvac::Temperature) changes of this
+    /// many degrees Celsius or less are ignored.
+    pub temperature_deadband: f32,
+    /// [`Aux`](crate::events::battery::Aux) voltage changes of this many volts
+    /// or less are ignored.
+    pub volts_deadband: f32,
+}
+
+/// Identifies where an [`Event`] came from, so events from the same source can
+/// be compared. This is finer grained than a CAN ID since a single frame (eg.
+/// `0x2fa`) can produce several different events.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+struct Source(Discriminant<Event>, u8);
+
+impl From<&Event> for Source {
+    fn from(event: &Event) -> Self {
+        let sub = match event {
+            Event::Engine(Engine::RPMs(_)) => 0,
+            Event::Engine(Engine::ApproxMPH(_)) => 1,
+            Event::Engine(Engine::MPH(_)) => 2,
+            Event::Lights(Lights::HazardsOnOff) => 0,
+            Event::Lights(Lights::ParkingLights(_)) => 1,
+            Event::Lights(Lights::Dimmer(_)) => 2,
+            Event::ControlPanel(ControlPanel::Buttons(_)) => 0,
+            Event::ControlPanel(ControlPanel::Warmers(_)) => 1,
+            Event::ControlPanel(ControlPanel::Knobs(_)) => 2,
+            Event::Force(Force::RoadFeedback(FrontOrRear::Front(_))) => 0,
+            Event::Force(Force::RoadFeedback(FrontOrRear::Rear(_))) => 1,
+            _ => 0,
+        };
+        Source(discriminant(event), sub)
+    }
+}
+
+/// Suppresses [`Event`]s identical to the last one emitted from the same
+/// source.
+#[derive(Debug, Clone, Default)]
+pub struct ChangeFilter {
+    config: OnChangeConfig,
+    /// The last emitted event from each source, and when.
+    last: HashMap<Source, (Event, Timestamp)>,
+}
+
+impl ChangeFilter {
+    /// Create a new [`ChangeFilter`] from an [`OnChangeConfig`].
+    pub fn new(config: OnChangeConfig) -> Self {
+        Self {
+            config,
+            last: HashMap::new(),
+        }
+    }
+
+    /// The [`OnChangeConfig`] in use.
+    pub fn config(&self) -> &OnChangeConfig {
+        &self.config
+    }
+
+    /// Returns true if `event`, received `at` some time, should be emitted.
+    /// If so, it's remembered as the last event from its source.
+    pub fn check(&mut self, event: &Event, at: Timestamp) -> bool {
+        let source = Source::from(event);
+
+        let emit = match self.last.get(&source) {
+            None => true,
+            Some((last, emitted)) => {
+                !self.same(last, event)
+                    || self.config.heartbeat.is_some_and(|heartbeat| {
+                        at.saturating_sub(*emitted) >= heartbeat
+                    })
+            }
+        };
+
+        if emit {
+            self.last.insert(source, (event.clone(), at));
+        }
+
+        emit
+    }
+
+    /// Forget every event, so the next one from each source is emitted.
+    pub fn reset(&mut self) {
+        self.last.clear();
+    }
+
+    /// Returns true if `a` and `b` are the same, within any deadbands.
+    fn same(&self, a: &Event, b: &Event) -> bool {
+        match (a, b) {
+            (Event::HVAC(HVAC::Cabin(a)), Event::HVAC(HVAC::Cabin(b)))
+                if self.config.temperature_deadband > 0.0 =>
+            {
+                let delta = (a.celsius() - b.celsius()).to_f32();
+                delta.abs() <= self.config.temperature_deadband
+            }
+            (
+                Event::Battery(Battery::Aux(a)),
+                Event::Battery(Battery::Aux(b)),
+            ) if self.config.volts_deadband > 0.0 => {
+                let delta = (a.voltage() - b.voltage()).to_f32();
+                delta.abs() <= self.config.volts_deadband
+            }
+            _ => a == b,
+        }
+    }
+}
+
+/// An [`Iterator`] adapter that only yields [`Event`]s that pass a
+/// [`ChangeFilter`]. Errors are always yielded. Time is measured from when the
+/// adapter was created.
+pub struct OnChange<I> {
+    inner: I,
+    filter: ChangeFilter,
+    epoch: Instant,
+}
+
+impl<I> OnChange<I> {
+    /// Wrap an iterator of `Result<Event, E>` with a [`ChangeFilter`].
+    pub fn new(inner: I, filter: ChangeFilter) -> Self {
+        Self {
+            inner,
+            filter,
+            epoch: Instant::now(),
+        }
+    }
+
+    /// Unwrap into the inner iterator and the [`ChangeFilter`] (so its state
+    /// can be reused).
+    pub fn into_inner(self) -> (I, ChangeFilter) {
+        (self.inner, self.filter)
+    }
+}
+
+impl<I, E> Iterator for OnChange<I>
+where
+    I: Iterator<Item = Result<Event, E>>,
+{
+    type Item = Result<Event, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for message in self.inner.by_ref() {
+            match message {
+                Ok(event) => {
+                    if self.filter.check(&event, self.epoch.elapsed()) {
+                        return Some(Ok(event));
+                    }
+                }
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{events::doors::Doors, Frame};
+
+    fn s(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    fn hvac(raw: u16) -> Event {
+        let [hi, lo] = raw.to_be_bytes();
+        let frame =
+            Frame::from_id_data_len(0x33a, [hi, lo, 0, 0, 0, 0, 0, 0], 8)
+                .unwrap();
+        Event::HVAC(frame.try_into().unwrap())
+    }
+
+    fn aux(raw_volts: u8) -> Event {
+        let frame = Frame::from_id_slice(0x2c2, &[0, 0, raw_volts, 0]).unwrap();
+        Event::Battery(frame.try_into().unwrap())
+    }
+
+    #[test]
+    fn test_sources_are_separate() {
+        let mut filter = ChangeFilter::default();
+        let t = s(0);
+        assert!(filter.check(&Event::Doors(Doors::empty()), t));
+        assert!(filter.check(&hvac(6000), t));
+        assert!(!filter.check(&Event::Doors(Doors::empty()), t));
+        assert!(!filter.check(&hvac(6000), t));
+    }
+
+    #[test]
+    fn test_temperature_deadband() {
+        let mut filter = ChangeFilter::new(OnChangeConfig {
+            temperature_deadband: 0.5,
+            ..Default::default()
+        });
+        assert!(filter.check(&hvac(6000), s(0)));
+        assert!(!filter.check(&hvac(6040), s(1)));
+        // compared to the last *emitted* value, so drift is caught
+        assert!(filter.check(&hvac(6080), s(2)));
+    }
+
+    #[test]
+    fn test_volts_deadband() {
+        let mut filter = ChangeFilter::new(OnChangeConfig {
+            volts_deadband: 0.5,
+            ..Default::default()
+        });
+        assert!(filter.check(&aux(125), s(0)));
+        assert!(!filter.check(&aux(128), s(1)));
+        assert!(filter.check(&aux(131), s(2)));
+    }
+
+    #[test]
+    fn test_iterator_adapter() {
+        let messages: Vec<Result<Event, ()>> = vec![
+            Ok(Event::Doors(Doors::empty())),
+            Ok(Event::Doors(Doors::empty())),
+            Err(()),
+            Err(()),
+            Ok(Event::Doors(Doors::DRIVER)),
+        ];
+        let filtered: Vec<_> =
+            OnChange::new(messages.into_iter(), ChangeFilter::default())
+                .collect();
+        assert_eq!(
+            filtered,
+            [
+                Ok(Event::Doors(Doors::empty())),
+                Err(()),
+                Err(()),
+                Ok(Event::Doors(Doors::DRIVER))
+            ]
+        );
+    }
+}
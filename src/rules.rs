@@ -0,0 +1,620 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Config file driven [`Rules`]: "when *this* happens, do *that*", without
+//! writing a new tool for every use case. Requires the `rules` feature.
+//!
+//! Rules are written in TOML. Every field of a rule's `when` table must match
+//! for the rule to fire. After firing, a rule won't fire again until its
+//! `cooldown` (in seconds) has passed.
+//!
+//! ```
+//! # use jeep::rules::Rules;
+//! let rules = Rules::from_toml(r#"
+//!     [[rule]]
+//!     name = "panic"
+//!     when = { event = { Remote = { PanicFrom = "KeyFob" } } }
+//!     cooldown = 30
+//!     action = { run = ["/usr/local/bin/notify", "panic button pressed"] }
+//!
+//!     [[rule]]
+//!     name = "swing gate opened while locked"
+//!     when = { opened = ["SWING_GATE"], locked = true }
+//!     action = { log = "/var/log/jeep-rules.log" }
+//!
+//!     [[rule]]
+//!     name = "reversing"
+//!     when = { event = { Camera = "Reverse" } }
+//!     action = { run = ["/usr/local/bin/dashcam", "--rear"] }
+//! "#).unwrap();
+//! assert_eq!(rules.rules().len(), 3);
+//! ```
+//!
+//! ## Conditions
+//!
+//! * `event` - the [`Event`] is exactly this (eg. `{ Camera = "Reverse" }`).
+//! * `kind` - the [`Event`] is this [`EventKind`] (eg. `"Doors"`).
+//! * `opened` - any of these [`Door`]s were just opened.
+//! * `locked` - all doors are locked (`true`) or any are unlocked (`false`).
+//! * `ignition` - the [`Ignition`] is in this state (eg. `"Run"`).
+//!
+//! `locked` and `ignition` refer to the state *before* the event, so they're
+//! unknown (and don't match) until the relevant event has been seen once.
+//!
+//! ## Actions
+//!
+//! * `run` - run a command (without waiting for it). The rule name and event
+//!   are passed as environment variables (see [`Action::env`]).
+//! * `log` - append a line with the time, rule name and event (as JSON) to a
+//!   file.
+
+use std::{
+    collections::HashSet,
+    fmt,
+    io::Write,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+use derive_more::From;
+use serde::Deserialize;
+
+use crate::{
+    events::{doors::Doors, ignition::Ignition, EventKind},
+    Event, Timestamp, VehicleState,
+};
+
+/// An error loading or validating [`Rules`].
+#[derive(Debug, From)]
+pub enum Error {
+    /// The rules file couldn't be read.
+    Io(std::io::Error),
+    /// The rules file isn't valid TOML or has unknown or invalid fields.
+    Toml(toml::de::Error),
+    /// A rule is well formed, but doesn't make sense.
+    #[from(ignore)]
+    Invalid {
+        /// Name of the invalid rule.
+        rule: String,
+        /// What's wrong with it.
+        detail: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "could not read rules: {err}"),
+            Error::Toml(err) => write!(f, "could not parse rules: {err}"),
+            Error::Invalid { rule, detail } => {
+                write!(f, "invalid rule `{rule}`: {detail}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Toml(err) => Some(err),
+            Error::Invalid { .. } => None,
+        }
+    }
+}
+
+/// A door that can be used in a [`Condition`]. These are written the same as
+/// the [`Doors`] flags (eg. `"SWING_GATE"`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Door {
+    Driver,
+    Passenger,
+    RearDriver,
+    RearPassenger,
+    SwingGate,
+    /// Any of the above.
+    Any,
+}
+
+impl Door {
+    /// The [`Doors`] flag(s) for this [`Door`].
+    pub const fn flag(self) -> Doors {
+        match self {
+            Door::Driver => Doors::DRIVER,
+            Door::Passenger => Doors::PASSENGER,
+            Door::RearDriver => Doors::REAR_DRIVER,
+            Door::RearPassenger => Doors::REAR_PASSENGER,
+            Door::SwingGate => Doors::SWING_GATE,
+            Door::Any => Doors::ALL_JEEP_DOORS,
+        }
+    }
+}
+
+/// When a [`Rule`] fires. Every field that is set must match.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Condition {
+    /// The [`Event`] is exactly this.
+    pub event: Option<Event>,
+    /// The [`Event`] is of this kind.
+    pub kind: Option<EventKind>,
+    /// Any of these doors were just opened.
+    pub opened: Option<Vec<Door>>,
+    /// All doors were locked (`true`) or any were unlocked (`false`).
+    pub locked: Option<bool>,
+    /// The [`Ignition`] was in this state.
+    pub ignition: Option<Ignition>,
+}
+
+impl Condition {
+    /// Returns true if no fields are set (which would match everything).
+    pub fn is_empty(&self) -> bool {
+        self.event.is_none()
+            && self.kind.is_none()
+            && self.opened.is_none()
+            && self.locked.is_none()
+            && self.ignition.is_none()
+    }
+
+    /// Returns true if `event` matches, given the `state` before the event.
+    pub fn matches(&self, event: &Event, state: &VehicleState) -> bool {
+        if let Some(expected) = &self.event {
+            if expected != event {
+                return false;
+            }
+        }
+        if let Some(kind) = self.kind {
+            if kind != event.kind() {
+                return false;
+            }
+        }
+        if let Some(opened) = &self.opened {
+            let doors = match event {
+                Event::Doors(doors) => *doors,
+                _ => return false,
+            };
+            // Unknown previous state is treated as all closed.
+            let before = state
+                .doors()
+                .map(|stamped| stamped.value)
+                .unwrap_or_else(Doors::empty);
+            let just_opened = doors - before;
+            if !opened
+                .iter()
+                .any(|door| just_opened.intersects(door.flag()))
+            {
+                return false;
+            }
+        }
+        if let Some(locked) = self.locked {
+            match state.locks() {
+                Some(locks) if locks.value.all_locked() == locked => {}
+                _ => return false,
+            }
+        }
+        if let Some(ignition) = &self.ignition {
+            match state.ignition() {
+                Some(current) if &current.value == ignition => {}
+                _ => return false,
+            }
+        }
+
+        true
+    }
+
+    /// Check the condition can ever match.
+    fn validate(&self) -> Result<(), String> {
+        if self.is_empty() {
+            return Err("`when` must have at least one condition".to_owned());
+        }
+        if let (Some(event), Some(kind)) = (&self.event, self.kind) {
+            if event.kind() != kind {
+                return Err(format!(
+                    "`event` is a {} event, but `kind` is {kind}",
+                    event.kind()
+                ));
+            }
+        }
+        if let Some(opened) = &self.opened {
+            if opened.is_empty() {
+                return Err("`opened` must list at least one door".to_owned());
+            }
+            let kind = self
+                .event
+                .as_ref()
+                .map(Event::kind)
+                .or(self.kind)
+                .unwrap_or(EventKind::Doors);
+            if kind != EventKind::Doors {
+                return Err(format!(
+                    "`opened` only matches Doors events, not {kind}"
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// What to do when a [`Rule`] fires.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Run a command (the program followed by any arguments).
+    Run(Vec<String>),
+    /// Append a line to a log file.
+    Log(PathBuf),
+}
+
+impl Action {
+    /// Environment variables passed to a [`Action::Run`] command:
+    ///
+    /// * `JEEP_RULE` - the rule name.
+    /// * `JEEP_KIND` - the [`EventKind`].
+    /// * `JEEP_EVENT` - the [`Event`] as JSON.
+    /// * `JEEP_<FIELD>...` - every field of the [`Event`], eg.
+    ///   `JEEP_REMOTE_PANICFROM=KeyFob` or `JEEP_ENGINE_RPMS=800`.
+    pub fn env(rule: &str, event: &Event) -> Vec<(String, String)> {
+        let json = serde_json::to_value(event).unwrap_or_default();
+        let mut env = vec![
+            ("JEEP_RULE".to_owned(), rule.to_owned()),
+            ("JEEP_KIND".to_owned(), event.kind().to_string()),
+            ("JEEP_EVENT".to_owned(), json.to_string()),
+        ];
+        flatten("JEEP", &json, &mut env);
+
+        env
+    }
+
+    /// Run the action for `rule`, which was fired by `event`.
+    pub fn execute(&self, rule: &str, event: &Event) -> std::io::Result<()> {
+        match self {
+            Action::Run(command) => {
+                let mut child = Command::new(&command[0])
+                    .args(&command[1..])
+                    .envs(Self::env(rule, event))
+                    .spawn()?;
+                // Reap the child when it exits, without blocking the caller.
+                std::thread::spawn(move || child.wait());
+            }
+            Action::Log(path) => {
+                let mut file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)?;
+                writeln!(
+                    file,
+                    "{}\t{}\t{}",
+                    chrono::Local::now().to_rfc3339(),
+                    rule,
+                    serde_json::to_string(event)?
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check the action can be run.
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Action::Run(command) => match command.first() {
+                Some(program) if !program.is_empty() => Ok(()),
+                _ => Err("`run` must start with a program".to_owned()),
+            },
+            Action::Log(path) => match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() && !dir.is_dir() => {
+                    Err(format!("`log` directory {dir:?} does not exist"))
+                }
+                _ => Ok(()),
+            },
+        }
+    }
+}
+
+/// Flatten a JSON `value` into `PREFIX_KEY=value` pairs.
+fn flatten(
+    prefix: &str,
+    value: &serde_json::Value,
+    out: &mut Vec<(String, String)>,
+) {
+    use serde_json::Value;
+
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let prefix = format!("{prefix}_{}", key.to_uppercase());
+                flatten(&prefix, value, out);
+            }
+        }
+        Value::Array(values) => {
+            for (i, value) in values.iter().enumerate() {
+                flatten(&format!("{prefix}_{i}"), value, out);
+            }
+        }
+        Value::String(s) => out.push((prefix.to_owned(), s.clone())),
+        Value::Null => {}
+        other => out.push((prefix.to_owned(), other.to_string())),
+    }
+}
+
+/// A single rule from a rules file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// Unique name of the rule.
+    pub name: String,
+    /// When the rule fires.
+    pub when: Condition,
+    /// Seconds after firing before the rule can fire again.
+    #[serde(default)]
+    pub cooldown: u64,
+    /// What to do when the rule fires.
+    pub action: Action,
+}
+
+impl Rule {
+    /// The [`Rule::cooldown`] as a [`Duration`].
+    pub const fn cooldown(&self) -> Duration {
+        Duration::from_secs(self.cooldown)
+    }
+}
+
+/// The top level of a rules file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct File {
+    #[serde(default)]
+    rule: Vec<Rule>,
+}
+
+/// A validated set of [`Rule`]s along with the [`VehicleState`] needed to
+/// evaluate them.
+#[derive(Debug, Default)]
+pub struct Rules {
+    rules: Vec<Rule>,
+    last_fired: Vec<Option<Timestamp>>,
+    state: VehicleState,
+}
+
+impl Rules {
+    /// Parse and validate [`Rules`] from a TOML string.
+    pub fn from_toml(s: &str) -> Result<Self, Error> {
+        let File { rule: rules } = toml::from_str(s)?;
+
+        let mut names = HashSet::new();
+        for rule in &rules {
+            let invalid = |detail| Error::Invalid {
+                rule: rule.name.clone(),
+                detail,
+            };
+            if rule.name.is_empty() {
+                return Err(invalid("`name` can't be empty".to_owned()));
+            }
+            if !names.insert(rule.name.as_str()) {
+                return Err(invalid("`name` is not unique".to_owned()));
+            }
+            rule.when.validate().map_err(invalid)?;
+            rule.action.validate().map_err(invalid)?;
+        }
+
+        Ok(Self {
+            last_fired: vec![None; rules.len()],
+            rules,
+            state: VehicleState::default(),
+        })
+    }
+
+    /// Read, parse and validate [`Rules`] from a TOML file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    /// Replace the rules with `new` ones (eg. after the file changed), keeping
+    /// the current [`VehicleState`]. Cooldowns are reset.
+    pub fn reload(&mut self, new: Rules) {
+        self.rules = new.rules;
+        self.last_fired = new.last_fired;
+    }
+
+    /// The validated [`Rule`]s.
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// The [`VehicleState`] as of the last [`Rules::process`]ed event.
+    pub fn state(&self) -> &VehicleState {
+        &self.state
+    }
+
+    /// Process an `event` received `at` some time, returning the [`Rule`]s
+    /// that fired (and are not cooling down). The caller is responsible for
+    /// running the [`Rule::action`]s.
+    pub fn process(&mut self, event: &Event, at: Timestamp) -> Vec<&Rule> {
+        let mut fired = Vec::new();
+        for (i, rule) in self.rules.iter().enumerate() {
+            if !rule.when.matches(event, &self.state) {
+                continue;
+            }
+            if let Some(last) = self.last_fired[i] {
+                if at.saturating_sub(last) < rule.cooldown() {
+                    continue;
+                }
+            }
+            self.last_fired[i] = Some(at);
+            fired.push(i);
+        }
+        self.state.update(event, at);
+
+        fired.into_iter().map(|i| &self.rules[i]).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{
+        camera::Camera,
+        locks::Locks,
+        remote::{Remote, RemoteSource},
+    };
+
+    const RULES: &str = r#"
+        [[rule]]
+        name = "panic"
+        when = { event = { Remote = { PanicFrom = "KeyFob" } } }
+        cooldown = 30
+        action = { run = ["true"] }
+
+        [[rule]]
+        name = "swing gate"
+        when = { opened = ["SWING_GATE"], locked = true }
+        action = { log = "jeep-rules.log" }
+
+        [[rule]]
+        name = "camera"
+        when = { kind = "Camera" }
+        action = { run = ["true"] }
+    "#;
+
+    fn names(fired: Vec<&Rule>) -> Vec<&str> {
+        fired.into_iter().map(|rule| rule.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let rules = Rules::from_toml(RULES).unwrap();
+        assert_eq!(rules.rules().len(), 3);
+        assert_eq!(rules.rules()[0].cooldown(), Duration::from_secs(30));
+        assert_eq!(
+            rules.rules()[0].when.event,
+            Some(Event::Remote(Remote::PanicFrom(RemoteSource::KeyFob)))
+        );
+        assert_eq!(
+            rules.rules()[1].action,
+            Action::Log("jeep-rules.log".into())
+        );
+    }
+
+    #[test]
+    fn test_validation() {
+        let invalid = |toml: &str| match Rules::from_toml(toml) {
+            Err(Error::Invalid { detail, .. }) => detail,
+            other => panic!("expected invalid rule, got {other:?}"),
+        };
+
+        invalid(
+            r#"
+            [[rule]]
+            name = "everything"
+            when = {}
+            action = { run = ["true"] }
+            "#,
+        );
+        invalid(
+            r#"
+            [[rule]]
+            name = "twice"
+            when = { kind = "Doors" }
+            action = { run = ["true"] }
+            [[rule]]
+            name = "twice"
+            when = { kind = "Locks" }
+            action = { run = ["true"] }
+            "#,
+        );
+        invalid(
+            r#"
+            [[rule]]
+            name = "contradiction"
+            when = { kind = "Locks", opened = ["DRIVER"] }
+            action = { run = ["true"] }
+            "#,
+        );
+        invalid(
+            r#"
+            [[rule]]
+            name = "no program"
+            when = { kind = "Locks" }
+            action = { run = [] }
+            "#,
+        );
+
+        // Typos are caught by the parser.
+        assert!(matches!(
+            Rules::from_toml(
+                r#"
+                [[rule]]
+                name = "typo"
+                when = { knid = "Locks" }
+                action = { run = ["true"] }
+                "#
+            ),
+            Err(Error::Toml(_))
+        ));
+    }
+
+    #[test]
+    fn test_process() {
+        let mut rules = Rules::from_toml(RULES).unwrap();
+        let t = Duration::from_secs;
+        let panic = Event::Remote(Remote::PanicFrom(RemoteSource::KeyFob));
+
+        // Fires, then cools down.
+        assert_eq!(names(rules.process(&panic, t(0))), ["panic"]);
+        assert!(rules.process(&panic, t(10)).is_empty());
+        assert_eq!(names(rules.process(&panic, t(30))), ["panic"]);
+
+        // Swing gate opened, but locks are unknown.
+        let gate = Event::Doors(Doors::SWING_GATE);
+        assert!(rules.process(&gate, t(40)).is_empty());
+        rules.process(&Event::Doors(Doors::empty()), t(41));
+        // Now locked, so it fires, but only when opened.
+        rules.process(&Event::Locks(Locks::empty()), t(42));
+        assert_eq!(names(rules.process(&gate, t(43))), ["swing gate"]);
+        assert!(rules.process(&gate, t(44)).is_empty());
+        // Unlocked.
+        rules.process(&Event::Doors(Doors::empty()), t(45));
+        rules.process(&Event::Locks(Locks::SWING_GATE), t(46));
+        assert!(rules.process(&gate, t(47)).is_empty());
+
+        assert_eq!(
+            names(rules.process(&Event::Camera(Camera::Reverse), t(48))),
+            ["camera"]
+        );
+    }
+
+    #[test]
+    fn test_env() {
+        let event = Event::Remote(Remote::PanicFrom(RemoteSource::KeyFob));
+        let env = Action::env("panic", &event);
+        let get = |key: &str| {
+            env.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+        };
+
+        assert_eq!(get("JEEP_RULE"), Some("panic"));
+        assert_eq!(get("JEEP_KIND"), Some("Remote"));
+        assert_eq!(get("JEEP_REMOTE_PANICFROM"), Some("KeyFob"));
+    }
+}
@@ -0,0 +1,710 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! A stable, versioned wire schema for [`Event`]s, for consumers that aren't
+//! written in Rust (or don't want to track this crate's internals).
+//!
+//! The `serde` output of [`Event`] itself follows the internal types and may
+//! change with any refactor. A [`Record`] instead has:
+//!
+//! * a `version` ([`VERSION`]), bumped whenever the schema changes,
+//! * a `timestamp` in seconds (since an epoch chosen by the producer),
+//! * a snake_case `type` tag (see [`WireEvent`]),
+//! * named fields with both decoded values (eg. `mph`) and the `raw` values
+//!   they were decoded from.
+//!
+//! ```
+//! use std::time::Duration;
+//! use jeep::{events::engine::{Engine, MPH}, schema::Record, Event};
+//!
+//! let event = Event::Engine(Engine::MPH(MPH::from(30)));
+//! let record = Record::new(&event, Duration::from_millis(1500));
+//! assert_eq!(
+//!     serde_json::to_string(&record).unwrap(),
+//!     r#"{"version":1,"timestamp":1.5,"type":"speed","mph":30.0,"kph":48.28032,"approximate":false,"raw":6000}"#
+//! );
+//! ```
+//!
+//! With the `schema` feature, [`json_schema`] generates the JSON Schema for a
+//! [`Record`]. The schema for each version is kept in the `schema/` directory
+//! of the repository, and a test fails if the generated schema differs from
+//! the one for the current [`VERSION`].
+
+use crate::{
+    events::{
+        battery::Battery,
+        bus::{self, Bus},
+        camera::Camera,
+        control_panel::{self, ControlPanel, Knobs},
+        doors::Doors,
+        engine::Engine,
+        force::Force,
+        hvac::HVAC,
+        ignition::Ignition,
+        lights::Lights,
+        locks::Locks,
+        remote::{Remote, RemoteSource},
+        steering_wheel, FrontOrRear,
+    },
+    Event, Timestamp,
+};
+
+/// The version of the wire schema. Bump this (and add a new schema file) on
+/// any change to the wire types.
+pub const VERSION: u32 = 1;
+
+/// A versioned, timestamped [`WireEvent`].
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Record {
+    /// The schema [`VERSION`] the record was written with.
+    pub version: u32,
+    /// Seconds since an epoch chosen by the producer.
+    pub timestamp: f64,
+    /// The event, tagged by `type`.
+    #[serde(flatten)]
+    pub event: WireEvent,
+}
+
+impl Record {
+    /// Create a [`Record`] for an `event` received `at` some time.
+    pub fn new(event: &Event, at: Timestamp) -> Self {
+        Self {
+            version: VERSION,
+            timestamp: at.as_secs_f64(),
+            event: event.into(),
+        }
+    }
+}
+
+/// Define a wire enum naming the flags of a bitflags type.
+macro_rules! flag_names {
+    (
+        $(#[$meta:meta])*
+        $name:ident for $flags:ty {
+            $($(#[$vmeta:meta])* $variant:ident = $flag:ident),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(
+            Debug, Clone, Copy, PartialEq, Eq, serde::Serialize,
+            serde::Deserialize,
+        )]
+        #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+        #[serde(rename_all = "snake_case")]
+        pub enum $name {
+            $($(#[$vmeta])* $variant),+
+        }
+
+        impl $name {
+            /// The names of the flags set in `flags`.
+            fn names(flags: $flags) -> Vec<Self> {
+                let mut names = Vec::new();
+                $(
+                    if flags.contains(<$flags>::$flag) {
+                        names.push($name::$variant);
+                    }
+                )+
+                names
+            }
+        }
+    };
+}
+
+flag_names! {
+    /// A door (see [`Doors`]).
+    Door for Doors {
+        Driver = DRIVER,
+        Passenger = PASSENGER,
+        RearDriver = REAR_DRIVER,
+        RearPassenger = REAR_PASSENGER,
+        SwingGate = SWING_GATE,
+        /// Unidentified (see [`Doors::MYSTERY_DOOR_0`]).
+        MysteryDoor0 = MYSTERY_DOOR_0,
+        /// Unidentified (see [`Doors::MYSTERY_BIT`]).
+        MysteryBit = MYSTERY_BIT,
+        /// Unidentified (see [`Doors::MYSTERY_DOOR_2`]).
+        MysteryDoor2 = MYSTERY_DOOR_2,
+    }
+}
+
+flag_names! {
+    /// A lock (see [`Locks`]).
+    Lock for Locks {
+        Driver = DRIVER,
+        Passenger = PASSENGER,
+        RearDriver = REAR_DRIVER,
+        RearPassenger = REAR_PASSENGER,
+        SwingGate = SWING_GATE,
+        /// Unidentified.
+        MysteryDoor0 = MYSTERY_DOOR_0,
+        /// Unidentified.
+        MysteryDoor1 = MYSTERY_DOOR_1,
+        /// Unidentified.
+        MysteryDoor2 = MYSTERY_DOOR_2,
+    }
+}
+
+flag_names! {
+    /// A steering wheel button (see [`steering_wheel::Buttons`]).
+    SteeringWheelButton for steering_wheel::Buttons {
+        DpadLeft = DPAD_LEFT,
+        DpadDown = DPAD_DOWN,
+        DpadUp = DPAD_UP,
+        DpadRight = DPAD_RIGHT,
+        BackInput = BACK_INPUT_BUTTON,
+        BackVolUp = BACK_VOL_UP,
+        BackVolDown = BACK_VOL_DOWN,
+        BackTrackSkip = BACK_TRACK_SKIP,
+        BackTrackRewind = BACK_TRACK_REWIND,
+        BackSeek = BACK_SEEK_BUTTON,
+        /// Custom button.
+        MysteryBtn0 = MYSTERY_BTN_0,
+        /// Custom button.
+        MysteryBtn1 = MYSTERY_BTN_1,
+        /// Custom button.
+        MysteryBtn2 = MYSTERY_BTN_2,
+        /// Custom button.
+        MysteryBtn3 = MYSTERY_BTN_3,
+        /// Custom button.
+        MysteryBtn4 = MYSTERY_BTN_4,
+        /// Custom button.
+        MysteryBtn5 = MYSTERY_BTN_5,
+    }
+}
+
+flag_names! {
+    /// A control panel button (see [`control_panel::Buttons`]).
+    ControlPanelButton for control_panel::Buttons {
+        TractionControl = TRACTION_CONTROL,
+        RadioPower = RADIO_POWER,
+        Ac = AC,
+        Recirculation = RECIRCULATION,
+        VentMode = VENT_MODE,
+        HvacPower = HVAC_POWER,
+        Auto = AUTO,
+        DriverTempUp = DRIVER_TEMP_UP,
+        DriverTempDown = DRIVER_TEMP_DOWN,
+        PassengerTempUp = PASSENGER_TEMP_UP,
+        PassengerTempDown = PASSENGER_TEMP_DOWN,
+        RearDefroster = REAR_DEFROSTER,
+        FrontDefroster = FRONT_DEFROSTER,
+        Mute = MUTE,
+        Screen = SCREEN,
+        EssMaxRegen = ESS_MAX_REGEN,
+    }
+}
+
+flag_names! {
+    /// A warmer button (see [`control_panel::Warmers`]).
+    Warmer for control_panel::Warmers {
+        DriverSeat = DRIVER_BUTT,
+        PassengerSeat = PASSENGER_BUTT,
+        SteeringWheel = STEERING_WHEEL,
+    }
+}
+
+/// Define a wire enum mirroring a fieldless event enum.
+macro_rules! mirror {
+    (
+        $(#[$meta:meta])*
+        $name:ident for $source:ty { $($variant:ident),+ $(,)? }
+    ) => {
+        $(#[$meta])*
+        #[derive(
+            Debug, Clone, Copy, PartialEq, Eq, serde::Serialize,
+            serde::Deserialize,
+        )]
+        #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+        #[serde(rename_all = "snake_case")]
+        pub enum $name {
+            $($variant),+
+        }
+
+        impl From<&$source> for $name {
+            fn from(source: &$source) -> Self {
+                match source {
+                    $(<$source>::$variant => $name::$variant),+
+                }
+            }
+        }
+    };
+}
+
+mirror! {
+    /// Where a [`WireEvent::Remote`] came from (see [`RemoteSource`]).
+    Source for RemoteSource { App, KeyFob }
+}
+
+mirror! {
+    /// [`Ignition`] state.
+    IgnitionState for Ignition {
+        Off, Kill, Acc, Run, StartReceived, Cranking,
+    }
+}
+
+mirror! {
+    /// [`Camera`] mode.
+    CameraMode for Camera { Off, Initializing, Reverse, Cargo }
+}
+
+mirror! {
+    /// A control panel [`Knobs`] turn.
+    Knob for Knobs { FanDown, FanUp }
+}
+
+mirror! {
+    /// Cause of a [`WireEvent::BusWake`] (see [`bus::Wake`]).
+    WakeCause for bus::Wake { HoodOpen, HoodClose, Unplug, Plug }
+}
+
+/// A [`Remote`] action.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum RemoteAction {
+    Idle,
+    Lock,
+    Unlock,
+    DoubleUnlock,
+    KeylessEntry,
+    Start,
+    CancelStart,
+    Panic,
+}
+
+/// Which axle.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Axle {
+    Front,
+    Rear,
+}
+
+/// An [`Event`] on the wire, tagged by `type`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WireEvent {
+    /// 12v (Aux) battery voltage.
+    AuxBattery {
+        /// Volts.
+        volts: f64,
+        /// The raw data.
+        raw: [u8; 4],
+    },
+    /// A remote (key fob or app) request.
+    Remote {
+        /// What was requested.
+        action: RemoteAction,
+        /// Where from, if known.
+        source: Option<Source>,
+    },
+    /// Ignition state.
+    Ignition {
+        /// The state.
+        state: IgnitionState,
+    },
+    /// Steering wheel buttons pressed.
+    SteeringWheel {
+        /// The buttons.
+        buttons: Vec<SteeringWheelButton>,
+        /// The raw flags.
+        raw: u16,
+    },
+    /// Control panel buttons pressed.
+    ControlPanelButtons {
+        /// The buttons.
+        buttons: Vec<ControlPanelButton>,
+        /// The raw flags.
+        raw: u64,
+    },
+    /// Warmer buttons pressed.
+    Warmers {
+        /// The buttons.
+        buttons: Vec<Warmer>,
+        /// The raw flags.
+        raw: u16,
+    },
+    /// A control panel knob was turned.
+    Knob {
+        /// The knob.
+        knob: Knob,
+    },
+    /// The hazards button was pressed.
+    Hazards,
+    /// Parking lights.
+    ParkingLights {
+        /// Whether they're on.
+        on: bool,
+        /// The raw byte.
+        raw: u8,
+    },
+    /// Interior dimmer.
+    Dimmer {
+        /// Fraction of the maximum (0-1).
+        fraction: f64,
+        /// The raw byte.
+        raw: u8,
+    },
+    /// Door states.
+    Doors {
+        /// Open doors.
+        open: Vec<Door>,
+        /// Whether every Jeep door is closed.
+        all_closed: bool,
+        /// The raw flags.
+        raw: u8,
+    },
+    /// Lock states.
+    Locks {
+        /// Unlocked doors.
+        unlocked: Vec<Lock>,
+        /// Whether every Jeep door is locked.
+        all_locked: bool,
+        /// The raw flags.
+        raw: u8,
+    },
+    /// Axle road feedback (not decoded yet).
+    RoadFeedback {
+        /// Which axle.
+        axle: Axle,
+        /// The raw data.
+        raw: [u8; 8],
+    },
+    /// Backup camera mode.
+    Camera {
+        /// The mode.
+        mode: CameraMode,
+    },
+    /// Engine RPMs.
+    Rpms {
+        /// RPMs, or `null` if the engine is off.
+        rpms: Option<u16>,
+        /// The raw value (`0xffff` when off).
+        raw: u16,
+    },
+    /// Vehicle speed.
+    Speed {
+        /// Miles per hour.
+        mph: f64,
+        /// Kilometers per hour.
+        kph: f64,
+        /// Whether this is the approximate (not GPS corrected) speed.
+        approximate: bool,
+        /// 1/200ths of a mph.
+        raw: u16,
+    },
+    /// Cabin temperature.
+    CabinTemperature {
+        /// Degrees Celsius.
+        celsius: f64,
+        /// Degrees Fahrenheit.
+        fahrenheit: f64,
+        /// 1/100ths of a degree above -40°C.
+        raw: u16,
+    },
+    /// The Jeep's date and time.
+    DateTime {
+        /// ISO 8601 local date and time, without a timezone (eg.
+        /// `2023-01-02T03:04:05`).
+        date_time: String,
+    },
+    /// Odometer.
+    Odometer {
+        /// Kilometers.
+        km: f64,
+        /// Miles.
+        miles: f64,
+        /// 1/100ths of a kilometer.
+        raw: u32,
+    },
+    /// The bus woke up.
+    BusWake {
+        /// Why.
+        cause: WakeCause,
+    },
+}
+
+impl WireEvent {
+    /// The `type` tag the event is written with (eg. `aux_battery`).
+    pub fn kind(&self) -> &'static str {
+        match self {
+            WireEvent::AuxBattery { .. } => "aux_battery",
+            WireEvent::Remote { .. } => "remote",
+            WireEvent::Ignition { .. } => "ignition",
+            WireEvent::SteeringWheel { .. } => "steering_wheel",
+            WireEvent::ControlPanelButtons { .. } => "control_panel_buttons",
+            WireEvent::Warmers { .. } => "warmers",
+            WireEvent::Knob { .. } => "knob",
+            WireEvent::Hazards => "hazards",
+            WireEvent::ParkingLights { .. } => "parking_lights",
+            WireEvent::Dimmer { .. } => "dimmer",
+            WireEvent::Doors { .. } => "doors",
+            WireEvent::Locks { .. } => "locks",
+            WireEvent::RoadFeedback { .. } => "road_feedback",
+            WireEvent::Camera { .. } => "camera",
+            WireEvent::Rpms { .. } => "rpms",
+            WireEvent::Speed { .. } => "speed",
+            WireEvent::CabinTemperature { .. } => "cabin_temperature",
+            WireEvent::DateTime { .. } => "date_time",
+            WireEvent::Odometer { .. } => "odometer",
+            WireEvent::BusWake { .. } => "bus_wake",
+        }
+    }
+}
+
+impl From<&Event> for WireEvent {
+    fn from(event: &Event) -> Self {
+        match event {
+            Event::Battery(Battery::Aux(aux)) => WireEvent::AuxBattery {
+                volts: aux.voltage().to_f64(),
+                raw: aux.raw(),
+            },
+            Event::Remote(remote) => {
+                let (action, source) = match remote {
+                    Remote::Idle => (RemoteAction::Idle, None),
+                    Remote::LockFrom(source) => {
+                        (RemoteAction::Lock, Some(source))
+                    }
+                    Remote::UnlockFrom(source) => {
+                        (RemoteAction::Unlock, Some(source))
+                    }
+                    Remote::DoubleUnlock => (
+                        RemoteAction::DoubleUnlock,
+                        Some(&RemoteSource::KeyFob),
+                    ),
+                    Remote::KeylessEntry => (RemoteAction::KeylessEntry, None),
+                    Remote::StartFrom(source) => {
+                        (RemoteAction::Start, Some(source))
+                    }
+                    Remote::CancelStart => (RemoteAction::CancelStart, None),
+                    Remote::PanicFrom(source) => {
+                        (RemoteAction::Panic, Some(source))
+                    }
+                };
+                WireEvent::Remote {
+                    action,
+                    source: source.map(Source::from),
+                }
+            }
+            Event::Ignition(ignition) => WireEvent::Ignition {
+                state: ignition.into(),
+            },
+            Event::SteeringWheel(buttons) => WireEvent::SteeringWheel {
+                buttons: SteeringWheelButton::names(*buttons),
+                raw: buttons.bits(),
+            },
+            Event::ControlPanel(ControlPanel::Buttons(buttons)) => {
+                WireEvent::ControlPanelButtons {
+                    buttons: ControlPanelButton::names(*buttons),
+                    raw: buttons.bits(),
+                }
+            }
+            Event::ControlPanel(ControlPanel::Warmers(warmers)) => {
+                WireEvent::Warmers {
+                    buttons: Warmer::names(*warmers),
+                    raw: warmers.bits(),
+                }
+            }
+            Event::ControlPanel(ControlPanel::Knobs(knob)) => {
+                WireEvent::Knob { knob: knob.into() }
+            }
+            Event::Lights(Lights::HazardsOnOff) => WireEvent::Hazards,
+            Event::Lights(Lights::ParkingLights(lights)) => {
+                WireEvent::ParkingLights {
+                    on: lights.are_on(),
+                    raw: lights.raw(),
+                }
+            }
+            Event::Lights(Lights::Dimmer(dimmer)) => WireEvent::Dimmer {
+                fraction: f64::from(dimmer.raw()) / 255.0,
+                raw: dimmer.raw(),
+            },
+            Event::Doors(doors) => WireEvent::Doors {
+                open: Door::names(*doors),
+                all_closed: doors.all_closed(),
+                raw: doors.bits(),
+            },
+            Event::Locks(locks) => WireEvent::Locks {
+                unlocked: Lock::names(*locks),
+                all_locked: locks.all_locked(),
+                raw: locks.bits(),
+            },
+            Event::Force(Force::RoadFeedback(feedback)) => {
+                let (axle, feedback) = match feedback {
+                    FrontOrRear::Front(feedback) => (Axle::Front, feedback),
+                    FrontOrRear::Rear(feedback) => (Axle::Rear, feedback),
+                };
+                WireEvent::RoadFeedback {
+                    axle,
+                    raw: feedback.raw(),
+                }
+            }
+            Event::Camera(camera) => WireEvent::Camera {
+                mode: camera.into(),
+            },
+            Event::Engine(Engine::RPMs(rpms)) => WireEvent::Rpms {
+                rpms: rpms.get(),
+                raw: rpms.raw(),
+            },
+            Event::Engine(Engine::MPH(mph) | Engine::ApproxMPH(mph)) => {
+                WireEvent::Speed {
+                    mph: mph.mph().to_f64(),
+                    kph: mph.kph().to_f64(),
+                    approximate: matches!(
+                        event,
+                        Event::Engine(Engine::ApproxMPH(_))
+                    ),
+                    raw: mph.raw(),
+                }
+            }
+            Event::HVAC(HVAC::Cabin(temperature)) => {
+                WireEvent::CabinTemperature {
+                    celsius: temperature.celsius().to_f64(),
+                    fahrenheit: temperature.fahrenheit().to_f64(),
+                    raw: temperature.raw(),
+                }
+            }
+            Event::DateTime(date_time) => WireEvent::DateTime {
+                date_time: date_time.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            },
+            Event::Odometer(odometer) => WireEvent::Odometer {
+                km: odometer.km().to_f64(),
+                miles: odometer.mi().to_f64(),
+                raw: odometer.raw(),
+            },
+            Event::Bus(Bus::Wake(cause)) => WireEvent::BusWake {
+                cause: cause.into(),
+            },
+        }
+    }
+}
+
+/// The JSON Schema for a [`Record`], as pretty printed JSON.
+#[cfg(feature = "schema")]
+pub fn json_schema() -> String {
+    let schema = schemars::schema_for!(Record);
+    // Serializing a schema can't fail.
+    serde_json::to_string_pretty(&schema).unwrap() + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        events::{engine::RPMs, hvac::Temperature},
+        Frame,
+    };
+    use std::time::Duration;
+
+    /// Events and the exact JSON they must produce in this [`VERSION`].
+    fn samples() -> Vec<(Event, &'static str)> {
+        let event = |id, data: &[u8]| {
+            Event::parse(Frame::from_id_slice(id, data).unwrap())
+                .unwrap()
+                .into_iter()
+                .next()
+                .unwrap()
+        };
+        vec![
+            (
+                Event::Doors(Doors::DRIVER | Doors::SWING_GATE),
+                r#"{"type":"doors","open":["driver","swing_gate"],"all_closed":false,"raw":33}"#,
+            ),
+            (
+                Event::Locks(Locks::empty()),
+                r#"{"type":"locks","unlocked":[],"all_locked":true,"raw":0}"#,
+            ),
+            (
+                Event::Remote(Remote::LockFrom(RemoteSource::KeyFob)),
+                r#"{"type":"remote","action":"lock","source":"key_fob"}"#,
+            ),
+            (
+                Event::Remote(Remote::Idle),
+                r#"{"type":"remote","action":"idle","source":null}"#,
+            ),
+            (
+                Event::Ignition(Ignition::StartReceived),
+                r#"{"type":"ignition","state":"start_received"}"#,
+            ),
+            (
+                Event::Engine(Engine::RPMs(RPMs(0xffff))),
+                r#"{"type":"rpms","rpms":null,"raw":65535}"#,
+            ),
+            (
+                Event::HVAC(HVAC::Cabin(Temperature::from_raw(6500))),
+                r#"{"type":"cabin_temperature","celsius":25.0,"fahrenheit":77.0,"raw":6500}"#,
+            ),
+            (Event::Lights(Lights::HazardsOnOff), r#"{"type":"hazards"}"#),
+            (
+                event(0x2d3, &0x07000000_00000041_u64.to_be_bytes()),
+                r#"{"type":"control_panel_buttons","buttons":["traction_control","radio_power"],"raw":504403158265495617}"#,
+            ),
+            (
+                event(0x350, &[5, 4, 3, 0x07, 0xe7, 1, 2, 0]),
+                r#"{"type":"date_time","date_time":"2023-01-02T03:04:05"}"#,
+            ),
+            (
+                event(0x401, &[0, 0, 0, 0, 0x0c, 0x06, 0, 0]),
+                r#"{"type":"bus_wake","cause":"hood_open"}"#,
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_wire_format() {
+        for (event, expected) in samples() {
+            let wire = WireEvent::from(&event);
+            assert_eq!(serde_json::to_string(&wire).unwrap(), expected);
+            let tag = format!(r#"{{"type":"{}""#, wire.kind());
+            assert!(expected.starts_with(&tag), "{expected}");
+            let back: WireEvent = serde_json::from_str(expected).unwrap();
+            assert_eq!(back, wire);
+        }
+
+        let record = Record::new(&Event::Doors(Doors::empty()), Duration::ZERO);
+        let json = serde_json::to_string(&record).unwrap();
+        assert!(
+            json.starts_with(r#"{"version":1,"timestamp":0.0,"type":"doors""#)
+        );
+        assert_eq!(serde_json::from_str::<Record>(&json).unwrap(), record);
+    }
+
+    #[test]
+    #[cfg(feature = "schema")]
+    fn test_schema_is_versioned() {
+        // If this fails, the wire types changed. Bump `VERSION`, generate
+        // `schema/record.v{VERSION}.json` with the `jeep-schema` example and
+        // update this test (and `test_wire_format`) for the new version.
+        assert_eq!(VERSION, 1);
+        assert!(
+            json_schema() == include_str!("../schema/record.v1.json"),
+            "the schema changed without a `VERSION` bump"
+        );
+    }
+}
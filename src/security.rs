@@ -0,0 +1,418 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! A vehicle [`SecurityMonitor`] with arming states and an entry delay.
+//!
+//! * Locking with the remote ([`Remote::LockFrom`]) arms.
+//! * Unlocking with the remote ([`Remote::UnlockFrom`] or
+//!   [`Remote::DoubleUnlock`]) disarms, including during the entry delay.
+//! * Opening a door (or the swing gate) while armed starts the entry delay.
+//!   If the monitor isn't disarmed before it ends, the alarm is triggered.
+//! * Opening the hood or changing the ignition while armed triggers the alarm
+//!   right away.
+//!
+//! Every transition is returned as an [`AuditEntry`] for the caller to log.
+//! Since time is supplied by the caller, the state machine can be tested
+//! against recorded logs (see [`crate::candump`]).
+
+use std::{fmt, time::Duration};
+
+use crate::{
+    events::{
+        bus::{Bus, Wake},
+        doors::Doors,
+        ignition::Ignition,
+        remote::{Remote, RemoteSource},
+    },
+    Event, Timestamp,
+};
+
+/// Configuration for a [`SecurityMonitor`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecurityConfig {
+    /// How long after a door is opened there is to disarm before the alarm
+    /// is triggered. Zero triggers right away.
+    pub entry_delay: Duration,
+    /// Which [`Doors`] are monitored.
+    pub doors: Doors,
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        Self {
+            entry_delay: Duration::from_secs(30),
+            doors: Doors::ALL_JEEP_DOORS,
+        }
+    }
+}
+
+/// Why the alarm was triggered.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Alert {
+    /// These [`Doors`] were opened and the entry delay expired.
+    Doors(Doors),
+    /// The hood was opened.
+    Hood,
+    /// The [`Ignition`] changed to this state.
+    Ignition(Ignition),
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Alert::Doors(doors) => write!(f, "doors opened ({doors:?})"),
+            Alert::Hood => write!(f, "hood opened"),
+            Alert::Ignition(ignition) => {
+                write!(f, "ignition changed to {ignition}")
+            }
+        }
+    }
+}
+
+/// The arming state of a [`SecurityMonitor`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arming {
+    /// Not monitoring anything.
+    Disarmed,
+    /// Monitoring doors, hood and ignition.
+    Armed,
+    /// A door was opened while armed. The alarm will trigger `until` this
+    /// time unless disarmed.
+    EntryDelay {
+        /// The [`Doors`] that were opened.
+        doors: Doors,
+        /// When the entry delay ends.
+        until: Timestamp,
+    },
+    /// The alarm was triggered and stays that way until disarmed (or
+    /// re-armed).
+    Triggered(Alert),
+}
+
+impl Arming {
+    /// Returns true if [`Arming::Armed`] or in the [`Arming::EntryDelay`].
+    pub const fn is_armed(&self) -> bool {
+        matches!(self, Arming::Armed | Arming::EntryDelay { .. })
+    }
+}
+
+/// A transition between [`Arming`] states.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transition {
+    /// Armed by a lock from this [`RemoteSource`].
+    Armed(RemoteSource),
+    /// Disarmed by this [`Remote`] event.
+    Disarmed(Remote),
+    /// These [`Doors`] were opened and the entry delay started.
+    EntryDelay(Doors),
+    /// The alarm was triggered.
+    Triggered(Alert),
+}
+
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transition::Armed(source) => write!(f, "ARMED by {source}"),
+            Transition::Disarmed(remote) => write!(f, "DISARMED by {remote}"),
+            Transition::EntryDelay(doors) => {
+                write!(f, "ENTRY DELAY started, doors opened ({doors:?})")
+            }
+            Transition::Triggered(alert) => write!(f, "TRIGGERED: {alert}"),
+        }
+    }
+}
+
+/// An entry for the audit log. Every [`Transition`] produces one.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    /// When the transition happened.
+    pub at: Timestamp,
+    /// What happened.
+    pub transition: Transition,
+}
+
+impl fmt::Display for AuditEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{:03} {}",
+            self.at.as_secs(),
+            self.at.subsec_millis(),
+            self.transition
+        )
+    }
+}
+
+/// Monitors [`Event`]s for intrusion. See the [module docs](self) for the
+/// rules.
+#[derive(Debug, Clone)]
+pub struct SecurityMonitor {
+    config: SecurityConfig,
+    arming: Arming,
+    doors: Doors,
+    ignition: Option<Ignition>,
+}
+
+impl SecurityMonitor {
+    /// Create a new, [`Arming::Disarmed`], monitor.
+    pub fn new(config: SecurityConfig) -> Self {
+        Self {
+            config,
+            arming: Arming::Disarmed,
+            doors: Doors::empty(),
+            ignition: None,
+        }
+    }
+
+    /// The [`SecurityConfig`] in use.
+    pub fn config(&self) -> &SecurityConfig {
+        &self.config
+    }
+
+    /// The current [`Arming`] state.
+    pub fn arming(&self) -> &Arming {
+        &self.arming
+    }
+
+    /// Update the monitor with an `event` received `at` some time, returning
+    /// any transitions (usually none). An expired entry delay is checked
+    /// first, as with [`SecurityMonitor::tick`].
+    pub fn update(&mut self, event: &Event, at: Timestamp) -> Vec<AuditEntry> {
+        let mut log: Vec<AuditEntry> = self.tick(at).into_iter().collect();
+        let mut transition = |monitor: &mut Self, arming, transition| {
+            monitor.arming = arming;
+            log.push(AuditEntry { at, transition });
+        };
+
+        match event {
+            Event::Remote(Remote::LockFrom(source))
+                if self.arming != Arming::Armed =>
+            {
+                transition(
+                    self,
+                    Arming::Armed,
+                    Transition::Armed(source.clone()),
+                );
+            }
+            Event::Remote(
+                remote @ (Remote::UnlockFrom(_) | Remote::DoubleUnlock),
+            ) if self.arming != Arming::Disarmed => {
+                transition(
+                    self,
+                    Arming::Disarmed,
+                    Transition::Disarmed(remote.clone()),
+                );
+            }
+            Event::Doors(doors) => {
+                let opened = (*doors - self.doors) & self.config.doors;
+                self.doors = *doors;
+                if self.arming == Arming::Armed && !opened.is_empty() {
+                    if self.config.entry_delay.is_zero() {
+                        let alert = Alert::Doors(opened);
+                        transition(
+                            self,
+                            Arming::Triggered(alert.clone()),
+                            Transition::Triggered(alert),
+                        );
+                    } else {
+                        transition(
+                            self,
+                            Arming::EntryDelay {
+                                doors: opened,
+                                until: at + self.config.entry_delay,
+                            },
+                            Transition::EntryDelay(opened),
+                        );
+                    }
+                }
+            }
+            Event::Bus(Bus::Wake(Wake::HoodOpen)) if self.arming.is_armed() => {
+                transition(
+                    self,
+                    Arming::Triggered(Alert::Hood),
+                    Transition::Triggered(Alert::Hood),
+                );
+            }
+            Event::Ignition(ignition) => {
+                let changed =
+                    self.ignition.as_ref().is_some_and(|last| last != ignition);
+                self.ignition = Some(ignition.clone());
+                if changed && self.arming.is_armed() {
+                    let alert = Alert::Ignition(ignition.clone());
+                    transition(
+                        self,
+                        Arming::Triggered(alert.clone()),
+                        Transition::Triggered(alert),
+                    );
+                }
+            }
+            _ => {}
+        }
+
+        log
+    }
+
+    /// Check if the entry delay has expired by `now`, triggering the alarm if
+    /// so. Call this periodically if events may stop arriving (eg. with a
+    /// non-blocking [`Listener`](crate::Listener)).
+    pub fn tick(&mut self, now: Timestamp) -> Option<AuditEntry> {
+        match self.arming {
+            Arming::EntryDelay { doors, until } if now >= until => {
+                let alert = Alert::Doors(doors);
+                self.arming = Arming::Triggered(alert.clone());
+                Some(AuditEntry {
+                    at: now,
+                    transition: Transition::Triggered(alert),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Default for SecurityMonitor {
+    fn default() -> Self {
+        Self::new(SecurityConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::candump;
+
+    /// Feed a `candump -L` log to a monitor, returning the audit log.
+    fn replay(monitor: &mut SecurityMonitor, log: &str) -> Vec<AuditEntry> {
+        candump::events(log.as_bytes())
+            .filter_map(Result::ok)
+            .flat_map(|(at, event)| monitor.update(&event, at))
+            .collect()
+    }
+
+    fn transitions(log: Vec<AuditEntry>) -> Vec<Transition> {
+        log.into_iter().map(|entry| entry.transition).collect()
+    }
+
+    #[test]
+    fn test_arm_open_disarm() {
+        let mut monitor = SecurityMonitor::default();
+        let log = replay(
+            &mut monitor,
+            "\
+            (100.000000) can0 1C0#210000000000\n\
+            (101.000000) can0 1C0#000000000000\n\
+            (200.000000) can0 2FA#0100000000000000\n\
+            (210.000000) can0 1C0#230000000000\n",
+        );
+
+        assert_eq!(
+            transitions(log),
+            [
+                Transition::Armed(RemoteSource::KeyFob),
+                Transition::EntryDelay(Doors::DRIVER),
+                Transition::Disarmed(Remote::UnlockFrom(RemoteSource::KeyFob)),
+            ]
+        );
+        assert_eq!(monitor.arming(), &Arming::Disarmed);
+    }
+
+    #[test]
+    fn test_entry_delay_expires() {
+        let mut monitor = SecurityMonitor::default();
+        let log = replay(
+            &mut monitor,
+            "\
+            (100.000000) can0 1C0#210000000000\n\
+            (200.000000) can0 2FA#2000000000000000\n\
+            (229.000000) can0 1C0#000000000000\n",
+        );
+        assert_eq!(log.len(), 2);
+        assert!(monitor.arming().is_armed());
+
+        let entry = monitor.tick(Duration::from_secs(230)).unwrap();
+        assert_eq!(entry.at, Duration::from_secs(230));
+        assert_eq!(
+            entry.transition,
+            Transition::Triggered(Alert::Doors(Doors::SWING_GATE))
+        );
+        assert_eq!(
+            entry.to_string(),
+            "230.000 TRIGGERED: doors opened (SWING_GATE)"
+        );
+        // Unlocking after the fact still disarms.
+        let log = replay(&mut monitor, "(231.000000) can0 1C0#240000000000\n");
+        assert_eq!(
+            transitions(log),
+            [Transition::Disarmed(Remote::DoubleUnlock)]
+        );
+    }
+
+    #[test]
+    fn test_hood_and_ignition() {
+        let mut monitor = SecurityMonitor::new(SecurityConfig {
+            entry_delay: Duration::ZERO,
+            ..Default::default()
+        });
+        let log = replay(
+            &mut monitor,
+            "\
+            (1.000000) can0 122#00000000\n\
+            (2.000000) can0 1C0#210000000000\n\
+            (3.000000) can0 401#000000000C060000\n\
+            (4.000000) can0 1C0#810000000000\n\
+            (5.000000) can0 122#05020000\n\
+            (6.000000) can0 1C0#810000000000\n\
+            (7.000000) can0 2FA#0200000000000000\n",
+        );
+
+        assert_eq!(
+            transitions(log),
+            [
+                Transition::Armed(RemoteSource::KeyFob),
+                Transition::Triggered(Alert::Hood),
+                Transition::Armed(RemoteSource::App),
+                Transition::Triggered(Alert::Ignition(Ignition::Acc)),
+                Transition::Armed(RemoteSource::App),
+                Transition::Triggered(Alert::Doors(Doors::PASSENGER)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_disarmed_ignores_everything() {
+        let mut monitor = SecurityMonitor::default();
+        let log = replay(
+            &mut monitor,
+            "\
+            (1.000000) can0 122#00000000\n\
+            (2.000000) can0 2FA#2F00000000000000\n\
+            (3.000000) can0 401#000000000C060000\n\
+            (4.000000) can0 122#05020000\n",
+        );
+        assert!(log.is_empty());
+    }
+}
@@ -0,0 +1,369 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Contains [`VehicleState`], a snapshot of the latest known values of the
+//! Jeep's state, updated by feeding it [`Event`]s.
+//!
+//! ```
+//! use std::time::Duration;
+//! use jeep::{events::doors::Doors, state::Fields, Event, VehicleState};
+//!
+//! let mut state = VehicleState::default();
+//!
+//! let changed = state.update(&Event::Doors(Doors::DRIVER), Duration::ZERO);
+//! assert_eq!(changed, Fields::DOORS);
+//! assert_eq!(state.doors().unwrap().value, Doors::DRIVER);
+//!
+//! // The same value again is not a change, but it does refresh the timestamp.
+//! let now = Duration::from_secs(1);
+//! let changed = state.update(&Event::Doors(Doors::DRIVER), now);
+//! assert!(changed.is_empty());
+//! assert_eq!(state.doors().unwrap().updated, now);
+//! ```
+
+use std::time::Duration;
+
+use crate::{
+    events::{
+        battery::{Aux, Battery},
+        camera::Camera,
+        datetime::DateTime,
+        doors::Doors,
+        engine::{Engine, RPMs, MPH},
+        hvac::{Temperature, HVAC},
+        ignition::Ignition,
+        locks::Locks,
+        odometer::Odometer,
+        remote::Remote,
+    },
+    Event, Timestamp,
+};
+
+bitflags::bitflags! {
+    /// [`bitflags`] representing fields of a [`VehicleState`]. Used to report
+    /// which fields changed or are stale.
+    #[cfg_attr(rustfmt, rustfmt_skip)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct Fields: u16 {
+        const DOORS             = 0b00000000_00000001;
+        const LOCKS             = 0b00000000_00000010;
+        const IGNITION          = 0b00000000_00000100;
+        const RPMS              = 0b00000000_00001000;
+        const APPROX_MPH        = 0b00000000_00010000;
+        const MPH               = 0b00000000_00100000;
+        const CABIN_TEMPERATURE = 0b00000000_01000000;
+        const AUX               = 0b00000000_10000000;
+        const ODOMETER          = 0b00000001_00000000;
+        const DATETIME          = 0b00000010_00000000;
+        const CAMERA            = 0b00000100_00000000;
+        const REMOTE            = 0b00001000_00000000;
+    }
+}
+
+/// A `value` along with the [`Timestamp`] it was last `updated`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(PartialEq, Debug, Clone)]
+pub struct Stamped<T> {
+    /// The latest value.
+    pub value: T,
+    /// When the value was last received (even if it didn't change).
+    pub updated: Timestamp,
+}
+
+impl<T> Stamped<T> {
+    /// How long ago the value was `updated`, relative to `now`. If `now` is
+    /// before `updated` (eg. a log was replayed out of order), this is zero.
+    #[inline]
+    pub fn age(&self, now: Timestamp) -> Duration {
+        now.saturating_sub(self.updated)
+    }
+    /// Returns true if the value is older than `max_age` at `now`.
+    #[inline]
+    pub fn is_stale(&self, now: Timestamp, max_age: Duration) -> bool {
+        self.age(now) > max_age
+    }
+}
+
+/// The latest known state of the Jeep, aggregated from [`Event`]s. Every field
+/// is `None` until an [`Event`] containing it has been seen.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct VehicleState {
+    doors: Option<Stamped<Doors>>,
+    locks: Option<Stamped<Locks>>,
+    ignition: Option<Stamped<Ignition>>,
+    rpms: Option<Stamped<RPMs>>,
+    approx_mph: Option<Stamped<MPH>>,
+    mph: Option<Stamped<MPH>>,
+    cabin_temperature: Option<Stamped<Temperature>>,
+    aux: Option<Stamped<Aux>>,
+    odometer: Option<Stamped<Odometer>>,
+    datetime: Option<Stamped<DateTime>>,
+    camera: Option<Stamped<Camera>>,
+    remote: Option<Stamped<Remote>>,
+    updated: Option<Timestamp>,
+}
+
+/// Set `slot` to `value` at `at`, adding `field` to `changed` if the value is
+/// new or different.
+fn set<T: PartialEq>(
+    slot: &mut Option<Stamped<T>>,
+    value: T,
+    at: Timestamp,
+    field: Fields,
+    changed: &mut Fields,
+) {
+    match slot {
+        Some(stamped) if stamped.value == value => stamped.updated = at,
+        _ => {
+            *slot = Some(Stamped { value, updated: at });
+            changed.insert(field);
+        }
+    }
+}
+
+impl VehicleState {
+    /// Update the state with an [`Event`] received at `at`. Returns the
+    /// [`Fields`] that changed value (which may be empty).
+    ///
+    /// [`Event`]s that don't map to any field (eg. button presses) and
+    /// [`Remote::Idle`] are ignored.
+    pub fn update(&mut self, event: &Event, at: Timestamp) -> Fields {
+        let mut changed = Fields::empty();
+        let c = &mut changed;
+
+        match event {
+            Event::Doors(doors) => {
+                set(&mut self.doors, *doors, at, Fields::DOORS, c)
+            }
+            Event::Locks(locks) => {
+                set(&mut self.locks, *locks, at, Fields::LOCKS, c)
+            }
+            Event::Ignition(ignition) => set(
+                &mut self.ignition,
+                ignition.clone(),
+                at,
+                Fields::IGNITION,
+                c,
+            ),
+            Event::Engine(Engine::RPMs(rpms)) => {
+                set(&mut self.rpms, *rpms, at, Fields::RPMS, c)
+            }
+            Event::Engine(Engine::ApproxMPH(mph)) => {
+                set(&mut self.approx_mph, *mph, at, Fields::APPROX_MPH, c)
+            }
+            Event::Engine(Engine::MPH(mph)) => {
+                set(&mut self.mph, *mph, at, Fields::MPH, c)
+            }
+            Event::HVAC(HVAC::Cabin(temperature)) => set(
+                &mut self.cabin_temperature,
+                *temperature,
+                at,
+                Fields::CABIN_TEMPERATURE,
+                c,
+            ),
+            Event::Battery(Battery::Aux(aux)) => {
+                set(&mut self.aux, *aux, at, Fields::AUX, c)
+            }
+            Event::Odometer(odometer) => {
+                set(&mut self.odometer, *odometer, at, Fields::ODOMETER, c)
+            }
+            Event::DateTime(datetime) => {
+                set(&mut self.datetime, *datetime, at, Fields::DATETIME, c)
+            }
+            Event::Camera(camera) => {
+                set(&mut self.camera, camera.clone(), at, Fields::CAMERA, c)
+            }
+            // Idle is not an action, it's just the remote module saying hi.
+            Event::Remote(Remote::Idle) => return changed,
+            Event::Remote(remote) => {
+                set(&mut self.remote, remote.clone(), at, Fields::REMOTE, c)
+            }
+            _ => return changed,
+        }
+
+        self.updated = Some(at);
+
+        changed
+    }
+
+    /// When any field was last updated, or `None` if never.
+    #[inline]
+    pub const fn updated(&self) -> Option<Timestamp> {
+        self.updated
+    }
+
+    /// The [`Fields`] which have a value older than `max_age` at `now`. Fields
+    /// that have never been seen are not included.
+    pub fn stale(&self, now: Timestamp, max_age: Duration) -> Fields {
+        let mut stale = Fields::empty();
+        let mut check = |updated: Option<Timestamp>, field: Fields| {
+            if let Some(updated) = updated {
+                if now.saturating_sub(updated) > max_age {
+                    stale.insert(field);
+                }
+            }
+        };
+
+        check(self.doors.as_ref().map(|s| s.updated), Fields::DOORS);
+        check(self.locks.as_ref().map(|s| s.updated), Fields::LOCKS);
+        check(self.ignition.as_ref().map(|s| s.updated), Fields::IGNITION);
+        check(self.rpms.as_ref().map(|s| s.updated), Fields::RPMS);
+        check(
+            self.approx_mph.as_ref().map(|s| s.updated),
+            Fields::APPROX_MPH,
+        );
+        check(self.mph.as_ref().map(|s| s.updated), Fields::MPH);
+        check(
+            self.cabin_temperature.as_ref().map(|s| s.updated),
+            Fields::CABIN_TEMPERATURE,
+        );
+        check(self.aux.as_ref().map(|s| s.updated), Fields::AUX);
+        check(self.odometer.as_ref().map(|s| s.updated), Fields::ODOMETER);
+        check(self.datetime.as_ref().map(|s| s.updated), Fields::DATETIME);
+        check(self.camera.as_ref().map(|s| s.updated), Fields::CAMERA);
+        check(self.remote.as_ref().map(|s| s.updated), Fields::REMOTE);
+
+        stale
+    }
+
+    /// Current state of the [`Doors`].
+    #[inline]
+    pub const fn doors(&self) -> Option<&Stamped<Doors>> {
+        self.doors.as_ref()
+    }
+    /// Current state of the [`Locks`].
+    #[inline]
+    pub const fn locks(&self) -> Option<&Stamped<Locks>> {
+        self.locks.as_ref()
+    }
+    /// Current [`Ignition`] state.
+    #[inline]
+    pub const fn ignition(&self) -> Option<&Stamped<Ignition>> {
+        self.ignition.as_ref()
+    }
+    /// Current engine [`RPMs`].
+    #[inline]
+    pub const fn rpms(&self) -> Option<&Stamped<RPMs>> {
+        self.rpms.as_ref()
+    }
+    /// Current speed (Not GPS corrected).
+    #[inline]
+    pub const fn approx_mph(&self) -> Option<&Stamped<MPH>> {
+        self.approx_mph.as_ref()
+    }
+    /// Current speed (GPS corrected).
+    #[inline]
+    pub const fn mph(&self) -> Option<&Stamped<MPH>> {
+        self.mph.as_ref()
+    }
+    /// Current cabin [`Temperature`].
+    #[inline]
+    pub const fn cabin_temperature(&self) -> Option<&Stamped<Temperature>> {
+        self.cabin_temperature.as_ref()
+    }
+    /// Latest [`Aux`] battery state. Use [`Aux::volts`] for the voltage.
+    #[inline]
+    pub const fn aux(&self) -> Option<&Stamped<Aux>> {
+        self.aux.as_ref()
+    }
+    /// Latest [`Odometer`] reading.
+    #[inline]
+    pub const fn odometer(&self) -> Option<&Stamped<Odometer>> {
+        self.odometer.as_ref()
+    }
+    /// The Jeep's reported [`DateTime`].
+    #[inline]
+    pub const fn datetime(&self) -> Option<&Stamped<DateTime>> {
+        self.datetime.as_ref()
+    }
+    /// Current [`Camera`] mode.
+    #[inline]
+    pub const fn camera(&self) -> Option<&Stamped<Camera>> {
+        self.camera.as_ref()
+    }
+    /// The last [`Remote`] action (never [`Remote::Idle`]).
+    #[inline]
+    pub const fn remote(&self) -> Option<&Stamped<Remote>> {
+        self.remote.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::remote::RemoteSource;
+
+    #[test]
+    fn test_update_and_changes() {
+        let mut state = VehicleState::default();
+        assert!(state.updated().is_none());
+
+        let t0 = Duration::from_millis(0);
+        let t1 = Duration::from_millis(100);
+
+        assert_eq!(
+            state.update(&Event::Locks(Locks::DRIVER), t0),
+            Fields::LOCKS
+        );
+        assert_eq!(
+            state.update(&Event::Engine(Engine::RPMs(RPMs(800))), t0),
+            Fields::RPMS
+        );
+        assert!(state
+            .update(&Event::Engine(Engine::RPMs(RPMs(800))), t1)
+            .is_empty());
+        assert_eq!(
+            state.update(&Event::Engine(Engine::RPMs(RPMs(900))), t1),
+            Fields::RPMS
+        );
+        assert_eq!(state.rpms().unwrap().value, RPMs(900));
+        assert_eq!(state.updated(), Some(t1));
+    }
+
+    #[test]
+    fn test_remote_ignores_idle() {
+        let mut state = VehicleState::default();
+        let lock = Remote::LockFrom(RemoteSource::KeyFob);
+
+        state.update(&Event::Remote(lock.clone()), Duration::ZERO);
+        let changed =
+            state.update(&Event::Remote(Remote::Idle), Duration::from_secs(1));
+
+        assert!(changed.is_empty());
+        assert_eq!(state.remote().unwrap().value, lock);
+        assert_eq!(state.remote().unwrap().updated, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_stale() {
+        let mut state = VehicleState::default();
+        state.update(&Event::Doors(Doors::empty()), Duration::ZERO);
+        state.update(&Event::Locks(Locks::empty()), Duration::from_secs(2));
+
+        let now = Duration::from_secs(3);
+        let max_age = Duration::from_secs(2);
+        assert_eq!(state.stale(now, max_age), Fields::DOORS);
+        assert!(state.doors().unwrap().is_stale(now, max_age));
+        assert!(!state.locks().unwrap().is_stale(now, max_age));
+    }
+}
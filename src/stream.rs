@@ -0,0 +1,501 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Compact binary streams of timestamped [`Record`]s for shipping logs over
+//! slow links, where json lines are too big.
+//!
+//! Each record is written as a little-endian `u32` length followed by that
+//! many bytes of CBOR (`cbor` feature) or MessagePack (`msgpack` feature).
+//! Because of this framing, a [`Reader`] stops cleanly at a record that was
+//! cut off (eg. by power loss while writing) and everything before the cut is
+//! still readable.
+//!
+//! ```
+//! # #[cfg(feature = "cbor")]
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use jeep::stream::{Format, Reader, Record, Writer};
+//! use jeep::{events::doors::Doors, Event, Frame};
+//! use std::time::Duration;
+//!
+//! let mut writer = Writer::new(Vec::new(), Format::Cbor);
+//! writer.write(&Record::new(
+//!     Duration::from_millis(1500),
+//!     Event::Doors(Doors::DRIVER),
+//! ))?;
+//! writer.write(&Record::new(
+//!     Duration::from_millis(1510),
+//!     Frame::from_id_slice(0x2fa, &[1, 0])?,
+//! ))?;
+//! let mut bytes = writer.into_inner();
+//! // Oops, we lost power while writing the second record.
+//! bytes.truncate(bytes.len() - 2);
+//!
+//! let mut reader = Reader::new(bytes.as_slice(), Format::Cbor);
+//! let records: Vec<Record> = reader.by_ref().collect::<Result<_, _>>()?;
+//! assert_eq!(records.len(), 1);
+//! assert!(reader.is_truncated());
+//! # Ok(())
+//! # }
+//! # #[cfg(not(feature = "cbor"))]
+//! # fn main() {}
+//! ```
+//!
+//! [`Frame`]s are encoded with the current [`Frame::serde_repr`] and must be
+//! read back with the same one. [`SerdeRepr::Hex`] is not supported, because
+//! `serde-hex` writes strings but expects bytes back from binary formats, so
+//! a [`Writer`] and [`Reader`] return [`Error::HexRepr`] rather than write
+//! records that can't be read.
+
+use std::{
+    fmt,
+    io::{self, Read, Write},
+};
+
+use derive_more::From;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{events::ParseError, frame::SerdeRepr, Event, Frame, Timestamp};
+
+/// Records longer than this are rejected by a [`Reader`] rather than
+/// allocated, since a length this large is almost certainly corrupt.
+pub const MAX_LEN: u32 = 64 * 1024;
+
+/// A binary encoding for a stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, derive_more::Display)]
+pub enum Format {
+    /// [CBOR](https://cbor.io/) (RFC 8949).
+    #[cfg(feature = "cbor")]
+    #[display(fmt = "cbor")]
+    Cbor,
+    /// [MessagePack](https://msgpack.org/). Structs are written as maps (with
+    /// field names) rather than arrays, so fields can be added later.
+    #[cfg(feature = "msgpack")]
+    #[display(fmt = "msgpack")]
+    MessagePack,
+}
+
+impl Format {
+    /// Encode a single `value` (without the length prefix).
+    pub fn encode<T: Serialize + ?Sized>(
+        self,
+        value: &T,
+    ) -> Result<Vec<u8>, Error> {
+        check_repr()?;
+        let mut buf = Vec::new();
+        match self {
+            #[cfg(feature = "cbor")]
+            Format::Cbor => ciborium::into_writer(value, &mut buf)
+                .map_err(|err| Error::Encode(err.to_string()))?,
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => value
+                .serialize(
+                    &mut rmp_serde::Serializer::new(&mut buf).with_struct_map(),
+                )
+                .map_err(|err| Error::Encode(err.to_string()))?,
+        }
+        Ok(buf)
+    }
+
+    /// Decode a single value from `bytes` (without the length prefix).
+    pub fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T, Error> {
+        check_repr()?;
+        match self {
+            #[cfg(feature = "cbor")]
+            Format::Cbor => ciborium::from_reader(bytes)
+                .map_err(|err| Error::Decode(err.to_string())),
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => rmp_serde::from_slice(bytes)
+                .map_err(|err| Error::Decode(err.to_string())),
+        }
+    }
+}
+
+/// What a [`Record`] carries.
+#[derive(Debug, From, Serialize, Deserialize)]
+pub enum Payload {
+    /// A successfully parsed [`Event`].
+    Event(Event),
+    /// A raw [`Frame`] (eg. when not parsing at all).
+    Frame(Frame),
+    /// A [`Frame`] that failed to parse.
+    Error(ParseError),
+}
+
+/// A timestamped [`Payload`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Record {
+    /// When the payload was received (see [`Timestamp`]).
+    pub timestamp: Timestamp,
+    /// The [`Event`], [`Frame`] or [`ParseError`].
+    pub payload: Payload,
+}
+
+impl Record {
+    /// Create a new [`Record`] from anything convertible to a [`Payload`].
+    pub fn new(timestamp: Timestamp, payload: impl Into<Payload>) -> Self {
+        Self {
+            timestamp,
+            payload: payload.into(),
+        }
+    }
+}
+
+/// Everything that can go wrong reading or writing a stream.
+#[derive(Debug, From)]
+pub enum Error {
+    /// The underlying reader or writer failed.
+    Io(io::Error),
+    /// A value could not be encoded.
+    #[from(ignore)]
+    Encode(String),
+    /// A record could not be decoded. The [`Reader`] can continue with the
+    /// next record.
+    #[from(ignore)]
+    Decode(String),
+    /// A record's length prefix was larger than [`MAX_LEN`].
+    #[from(ignore)]
+    TooLong(u32),
+    /// [`Frame`]s are set to be (de)serialized as [`SerdeRepr::Hex`], which
+    /// binary formats can't read back.
+    #[from(ignore)]
+    HexRepr,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "stream i/o error: {err}"),
+            Error::Encode(err) => write!(f, "could not encode record: {err}"),
+            Error::Decode(err) => write!(f, "could not decode record: {err}"),
+            Error::TooLong(len) => {
+                write!(f, "record length {len} is larger than {MAX_LEN}")
+            }
+            Error::HexRepr => {
+                write!(f, "hex frames are not supported in binary streams")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// [`SerdeRepr::Hex`] frames can't be read back from a binary format.
+fn check_repr() -> Result<(), Error> {
+    match Frame::serde_repr() {
+        SerdeRepr::Hex => Err(Error::HexRepr),
+        _ => Ok(()),
+    }
+}
+
+/// Writes length-delimited records in a [`Format`].
+pub struct Writer<W> {
+    inner: W,
+    format: Format,
+}
+
+impl<W: Write> Writer<W> {
+    /// Create a new [`Writer`] writing to `inner` in `format`.
+    ///
+    /// Records are written with a single `write_all` each. Wrap `inner` in a
+    /// [`std::io::BufWriter`] if that's too many syscalls.
+    pub fn new(inner: W, format: Format) -> Self {
+        Self { inner, format }
+    }
+
+    /// The [`Format`] records are written in.
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Write a single record (usually a [`Record`]).
+    pub fn write<T: Serialize + ?Sized>(
+        &mut self,
+        record: &T,
+    ) -> Result<(), Error> {
+        let body = self.format.encode(record)?;
+        let len = u32::try_from(body.len())
+            .ok()
+            .filter(|len| *len <= MAX_LEN)
+            .ok_or(Error::TooLong(body.len() as u32))?;
+        let mut buf = Vec::with_capacity(4 + body.len());
+        buf.extend_from_slice(&len.to_le_bytes());
+        buf.extend_from_slice(&body);
+        Ok(self.inner.write_all(&buf)?)
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        Ok(self.inner.flush()?)
+    }
+
+    /// Get back the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Reads length-delimited records in a [`Format`]. This is an [`Iterator`]
+/// over decoded records (usually [`Record`]s).
+///
+/// Iteration ends at the end of the stream or at the first record that was
+/// cut off (see [`Reader::is_truncated`]). A record that fails to decode
+/// yields an [`Error::Decode`] and iteration continues with the next one. An
+/// i/o error or a corrupt length ends iteration after yielding the error.
+pub struct Reader<R, T = Record> {
+    inner: R,
+    format: Format,
+    truncated: bool,
+    done: bool,
+    _record: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<R: Read, T: DeserializeOwned> Reader<R, T> {
+    /// Create a new [`Reader`] reading from `inner` in `format`.
+    pub fn new(inner: R, format: Format) -> Self {
+        Self {
+            inner,
+            format,
+            truncated: false,
+            done: false,
+            _record: std::marker::PhantomData,
+        }
+    }
+
+    /// The [`Format`] records are read in.
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Whether the stream ended partway through a record.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Fill `buf` as far as possible, returning how many bytes were read.
+    /// Fewer than `buf.len()` means the stream ended.
+    fn fill(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match self.inner.read(&mut buf[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(filled)
+    }
+
+    /// Read the next record's bytes, or `None` at the end of the stream.
+    fn next_body(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        let mut len = [0; 4];
+        match self.fill(&mut len)? {
+            0 => return Ok(None),
+            4 => {}
+            _ => {
+                self.truncated = true;
+                return Ok(None);
+            }
+        }
+        let len = u32::from_le_bytes(len);
+        if len > MAX_LEN {
+            return Err(Error::TooLong(len));
+        }
+        let mut body = vec![0; len as usize];
+        if self.fill(&mut body)? < body.len() {
+            self.truncated = true;
+            return Ok(None);
+        }
+        Ok(Some(body))
+    }
+
+    /// Get back the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read, T: DeserializeOwned> Iterator for Reader<R, T> {
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        // Every record would fail the same way.
+        if let Err(err) = check_repr() {
+            self.done = true;
+            return Some(Err(err));
+        }
+        match self.next_body() {
+            Ok(Some(body)) => Some(self.format.decode(&body)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{doors::Doors, engine::Engine, engine::MPH};
+    use std::time::Duration;
+
+    fn formats() -> Vec<Format> {
+        vec![
+            #[cfg(feature = "cbor")]
+            Format::Cbor,
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack,
+        ]
+    }
+
+    fn records() -> Vec<Record> {
+        let frame = Frame::from_id_slice(0x2fa, &[1, 0]).unwrap();
+        vec![
+            Record::new(Duration::from_millis(1), Event::Doors(Doors::DRIVER)),
+            Record::new(
+                Duration::from_millis(2),
+                Event::Engine(Engine::MPH(MPH::from_raw(6000))),
+            ),
+            Record::new(Duration::from_millis(3), frame.clone()),
+            Record::new(Duration::from_millis(4), ParseError::Id { frame }),
+        ]
+    }
+
+    fn write_all(format: Format) -> Vec<u8> {
+        let mut writer = Writer::new(Vec::new(), format);
+        for record in records() {
+            writer.write(&record).unwrap();
+        }
+        writer.into_inner()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for format in formats() {
+            let bytes = write_all(format);
+            // Much smaller than the same records as json.
+            let json: usize = records()
+                .iter()
+                .map(|r| serde_json::to_string(r).unwrap().len())
+                .sum();
+            assert!(bytes.len() < json, "{format}: {} >= {json}", bytes.len());
+
+            let mut reader = Reader::new(bytes.as_slice(), format);
+            let read: Vec<Record> =
+                reader.by_ref().map(Result::unwrap).collect();
+            assert!(!reader.is_truncated());
+            assert_eq!(format!("{read:?}"), format!("{:?}", records()));
+        }
+    }
+
+    #[test]
+    fn test_frame_reprs() {
+        for format in formats() {
+            for repr in [SerdeRepr::Struct, SerdeRepr::Hex, SerdeRepr::Candump]
+            {
+                if repr == SerdeRepr::Hex {
+                    let bytes = write_all(format);
+                    Frame::with_serde_repr(repr, || {
+                        let mut writer = Writer::new(Vec::new(), format);
+                        assert!(matches!(
+                            writer.write(&records()[0]),
+                            Err(Error::HexRepr)
+                        ));
+                        assert!(writer.into_inner().is_empty());
+                        let mut reader: Reader<_> =
+                            Reader::new(bytes.as_slice(), format);
+                        assert!(matches!(
+                            reader.next(),
+                            Some(Err(Error::HexRepr))
+                        ));
+                        assert!(reader.next().is_none());
+                    });
+                    continue;
+                }
+                Frame::with_serde_repr(repr, || {
+                    let bytes = write_all(format);
+                    let read: Vec<Record> =
+                        Reader::new(bytes.as_slice(), format)
+                            .map(|r| {
+                                r.unwrap_or_else(|e| panic!("{repr}: {e}"))
+                            })
+                            .collect();
+                    assert_eq!(format!("{read:?}"), format!("{:?}", records()));
+                });
+            }
+        }
+    }
+
+    #[test]
+    fn test_truncated() {
+        for format in formats() {
+            let bytes = write_all(format);
+            let first =
+                4 + u32::from_le_bytes(bytes[..4].try_into().unwrap()) as usize;
+            // Cut inside the length prefix and inside a body.
+            for (cut, expected) in
+                [(0, 0), (first, 1), (first + 2, 1), (first + 6, 1)]
+            {
+                let mut reader: Reader<_> = Reader::new(&bytes[..cut], format);
+                assert_eq!(
+                    reader.by_ref().map(Result::unwrap).count(),
+                    expected
+                );
+                assert_eq!(reader.is_truncated(), cut != 0 && cut != first);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bad_records() {
+        for format in formats() {
+            let mut bytes = 3_u32.to_le_bytes().to_vec();
+            bytes.extend_from_slice(&[0xff; 3]);
+            bytes.extend(write_all(format));
+            bytes.extend_from_slice(&(MAX_LEN + 1).to_le_bytes());
+
+            let mut reader: Reader<_> = Reader::new(bytes.as_slice(), format);
+            assert!(matches!(reader.next(), Some(Err(Error::Decode(_)))));
+            assert_eq!(
+                reader.by_ref().take(4).filter(Result::is_ok).count(),
+                4
+            );
+            assert!(matches!(reader.next(), Some(Err(Error::TooLong(_)))));
+            assert!(reader.next().is_none());
+        }
+    }
+}
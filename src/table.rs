@@ -0,0 +1,451 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Wide tables (eg. CSV) of decoded signals, with one column per signal, for
+//! people who would rather have a spreadsheet than json lines.
+//!
+//! A [`Table`] is fed timestamped [`Event`]s (eg. from [`crate::candump`]) and
+//! returns [`Row`]s, either one per event ([`Rows::Sparse`]) or resampled to a
+//! fixed rate, holding the last known value of every column
+//! ([`Rows::Resample`]). A [`Row`] displays as a CSV line.
+//!
+//! ```
+//! use jeep::table::{Column, Rows, Table, TableConfig};
+//! use jeep::{events::engine::{Engine, MPH}, Event};
+//! use std::time::Duration;
+//!
+//! let mut table = Table::new(TableConfig {
+//!     columns: vec![Column::Mph, Column::Rpm],
+//!     rows: Rows::Resample(Duration::from_secs(1)),
+//!     ..Default::default()
+//! });
+//! assert_eq!(table.header(), "timestamp,mph,rpm");
+//!
+//! let speed = Event::Engine(Engine::MPH(MPH::from_raw(6000)));
+//! assert!(table.update(&speed, Duration::from_millis(500)).is_empty());
+//! let rows = table.update(&speed, Duration::from_millis(2500));
+//! let rows: Vec<String> = rows.iter().map(ToString::to_string).collect();
+//! assert_eq!(rows, ["0.500000,30,", "1.500000,30,"]);
+//! assert_eq!(table.finish().unwrap().to_string(), "2.500000,30,");
+//! ```
+
+use std::fmt;
+
+use derive_more::Display;
+
+use crate::{
+    events::{
+        battery::Battery, engine::Engine, hvac::HVAC, ignition::Ignition,
+        lights::Lights,
+    },
+    Event, Timestamp,
+};
+
+/// A decoded signal, and its column in a [`Table`].
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Column {
+    /// Engine RPMs (empty while the engine is off).
+    #[display(fmt = "rpm")]
+    Rpm,
+    /// Approximate speed in mph.
+    #[display(fmt = "approx_mph")]
+    ApproxMph,
+    /// GPS corrected speed in mph.
+    #[display(fmt = "mph")]
+    Mph,
+    /// Cabin temperature in Celsius.
+    #[display(fmt = "cabin_c")]
+    CabinC,
+    /// Aux (12V) battery voltage.
+    #[display(fmt = "aux_volts")]
+    AuxVolts,
+    /// Odometer in kilometers.
+    #[display(fmt = "odometer_km")]
+    OdometerKm,
+    /// [`Doors`](crate::events::doors::Doors) bits (open doors).
+    #[display(fmt = "doors")]
+    Doors,
+    /// [`Locks`](crate::events::locks::Locks) bits (unlocked locks).
+    #[display(fmt = "locks")]
+    Locks,
+    /// [`Ignition`] state (eg. `run`).
+    #[display(fmt = "ignition")]
+    Ignition,
+    /// Whether the parking lights are on (`1`) or off (`0`).
+    #[display(fmt = "parking_lights")]
+    ParkingLights,
+}
+
+impl Column {
+    /// Every [`Column`], in default table order.
+    pub const ALL: [Column; 10] = [
+        Column::Rpm,
+        Column::ApproxMph,
+        Column::Mph,
+        Column::CabinC,
+        Column::AuxVolts,
+        Column::OdometerKm,
+        Column::Doors,
+        Column::Locks,
+        Column::Ignition,
+        Column::ParkingLights,
+    ];
+
+    /// This column's [`Value`] from an `event`, if the `event` has one.
+    pub fn value(self, event: &Event) -> Option<Value> {
+        use Value::{Bits, Number, Text};
+
+        match (self, event) {
+            (Column::Rpm, Event::Engine(Engine::RPMs(rpms))) => {
+                rpms.get().map(|rpms| Number(f64::from(rpms)))
+            }
+            (Column::ApproxMph, Event::Engine(Engine::ApproxMPH(mph)))
+            | (Column::Mph, Event::Engine(Engine::MPH(mph))) => {
+                Some(Number(mph.mph().to_f64()))
+            }
+            (Column::CabinC, Event::HVAC(HVAC::Cabin(temperature))) => {
+                Some(Number(temperature.celsius().to_f64()))
+            }
+            (Column::AuxVolts, Event::Battery(Battery::Aux(aux))) => {
+                Some(Number(aux.voltage().to_f64()))
+            }
+            (Column::OdometerKm, Event::Odometer(odometer)) => {
+                Some(Number(odometer.kilometers()))
+            }
+            (Column::Doors, Event::Doors(doors)) => {
+                Some(Bits(u64::from(doors.bits())))
+            }
+            (Column::Locks, Event::Locks(locks)) => {
+                Some(Bits(u64::from(locks.bits())))
+            }
+            (Column::Ignition, Event::Ignition(ignition)) => {
+                Some(Text(match ignition {
+                    Ignition::Off => "off",
+                    Ignition::Kill => "kill",
+                    Ignition::Acc => "acc",
+                    Ignition::Run => "run",
+                    Ignition::StartReceived => "start_received",
+                    Ignition::Cranking => "cranking",
+                }))
+            }
+            (
+                Column::ParkingLights,
+                Event::Lights(Lights::ParkingLights(l)),
+            ) => Some(Bits(u64::from(l.are_on()))),
+            _ => None,
+        }
+    }
+}
+
+/// An unrecognized [`Column`] name.
+#[derive(Debug, Display, derive_more::Error)]
+#[display(fmt = "unknown column: `{}`", _0)]
+pub struct UnknownColumn(#[error(not(source))] pub String);
+
+impl std::str::FromStr for Column {
+    type Err = UnknownColumn;
+
+    /// Parse a [`Column`] from its name, ignoring case (eg. `"aux_volts"`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Column::ALL
+            .into_iter()
+            .find(|column| column.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| UnknownColumn(s.to_owned()))
+    }
+}
+
+/// A single cell in a [`Table`].
+#[derive(Debug, Display, Clone, Copy, PartialEq)]
+pub enum Value {
+    /// A decoded number (eg. volts).
+    Number(f64),
+    /// Raw bits or a flag (eg. doors).
+    Bits(u64),
+    /// A name (eg. an ignition state). Never contains a `,` or `"`.
+    Text(&'static str),
+}
+
+/// How a [`Table`] makes [`Row`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rows {
+    /// One row per event with a value in any column. Only the columns from
+    /// that event are filled in.
+    #[default]
+    Sparse,
+    /// One row every this often, starting at the first event (or
+    /// [`TableConfig::start`]), with the last known value of every column.
+    ///
+    /// Every period gets a row, so a small period over a log with long gaps
+    /// (eg. while the bus was asleep) makes a lot of identical rows.
+    Resample(std::time::Duration),
+}
+
+/// Configuration for a [`Table`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableConfig {
+    /// Columns, in order (default: [`Column::ALL`]).
+    pub columns: Vec<Column>,
+    /// How rows are made (default: [`Rows::Sparse`]).
+    pub rows: Rows,
+    /// Leave out rows before this time (default: none).
+    pub start: Option<Timestamp>,
+    /// Leave out rows after this time (default: none).
+    pub end: Option<Timestamp>,
+}
+
+impl Default for TableConfig {
+    fn default() -> Self {
+        Self {
+            columns: Column::ALL.to_vec(),
+            rows: Rows::default(),
+            start: None,
+            end: None,
+        }
+    }
+}
+
+/// A row of a [`Table`]. Displays as a CSV line (without the newline), with
+/// the timestamp in seconds and empty cells for missing values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    /// When this row is for.
+    pub timestamp: Timestamp,
+    /// One value per [`TableConfig::columns`].
+    pub values: Vec<Option<Value>>,
+}
+
+impl fmt::Display for Row {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{:06}",
+            self.timestamp.as_secs(),
+            self.timestamp.subsec_micros()
+        )?;
+        for value in &self.values {
+            match value {
+                Some(value) => write!(f, ",{value}")?,
+                None => write!(f, ",")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Turns [`Event`]s into [`Row`]s. See the [module docs](self).
+pub struct Table {
+    config: TableConfig,
+    /// The last known value of every column.
+    held: Vec<Option<Value>>,
+    /// When the next resampled row is due.
+    next: Option<Timestamp>,
+    /// When the last event was.
+    last: Option<Timestamp>,
+}
+
+impl Table {
+    /// Create a new [`Table`] from a [`TableConfig`].
+    pub fn new(config: TableConfig) -> Self {
+        Self {
+            held: vec![None; config.columns.len()],
+            config,
+            next: None,
+            last: None,
+        }
+    }
+
+    /// Get the [`TableConfig`].
+    pub fn config(&self) -> &TableConfig {
+        &self.config
+    }
+
+    /// The CSV header line (without the newline).
+    pub fn header(&self) -> String {
+        std::iter::once("timestamp".to_owned())
+            .chain(self.config.columns.iter().map(Column::to_string))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Whether `at` is within [`TableConfig::start`] and
+    /// [`TableConfig::end`].
+    fn in_range(&self, at: Timestamp) -> bool {
+        self.config.start.is_none_or(|start| at >= start)
+            && self.config.end.is_none_or(|end| at <= end)
+    }
+
+    /// Update with an [`Event`] that happened `at` some time. Events must be
+    /// in time order. Returns the [`Row`]s that are complete.
+    pub fn update(&mut self, event: &Event, at: Timestamp) -> Vec<Row> {
+        let mut rows = Vec::new();
+
+        if let Rows::Resample(period) = self.config.rows {
+            // Rows for times before this event have all the values they'll
+            // ever have.
+            let mut next = *self.next.get_or_insert_with(|| {
+                self.config.start.map_or(at, |start| start.max(at))
+            });
+            while next < at && !period.is_zero() {
+                if self.in_range(next) {
+                    rows.push(Row {
+                        timestamp: next,
+                        values: self.held.clone(),
+                    });
+                }
+                next += period;
+            }
+            self.next = Some(next);
+        }
+
+        let values: Vec<_> = self
+            .config
+            .columns
+            .iter()
+            .map(|column| column.value(event))
+            .collect();
+        if values.iter().all(Option::is_none) {
+            return rows;
+        }
+        self.last = Some(at);
+        for (held, value) in self.held.iter_mut().zip(&values) {
+            if value.is_some() {
+                *held = *value;
+            }
+        }
+
+        if self.config.rows == Rows::Sparse && self.in_range(at) {
+            rows.push(Row {
+                timestamp: at,
+                values,
+            });
+        }
+
+        rows
+    }
+
+    /// With [`Rows::Resample`], the row due at the time of the last event, if
+    /// any. Call this after the last event.
+    pub fn finish(&mut self) -> Option<Row> {
+        let (next, last) = (self.next?, self.last?);
+        (matches!(self.config.rows, Rows::Resample(_))
+            && next <= last
+            && self.in_range(next))
+        .then(|| {
+            self.next = None;
+            Row {
+                timestamp: next,
+                values: self.held.clone(),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{
+        doors::Doors,
+        engine::{RPMs, MPH},
+        hvac::Temperature,
+    };
+    use std::time::Duration;
+
+    fn events() -> Vec<(Timestamp, Event)> {
+        let ms = Duration::from_millis;
+        vec![
+            (ms(0), Event::Engine(Engine::RPMs(RPMs(800)))),
+            (ms(100), Event::Doors(Doors::DRIVER)),
+            (ms(250), Event::Engine(Engine::MPH(MPH::from_raw(1000)))),
+            (ms(300), Event::Lights(Lights::HazardsOnOff)),
+            (
+                ms(420),
+                Event::HVAC(HVAC::Cabin(Temperature::from_raw(6500))),
+            ),
+        ]
+    }
+
+    fn run(config: TableConfig) -> Vec<String> {
+        let mut table = Table::new(config);
+        let mut lines = vec![table.header()];
+        for (at, event) in events() {
+            lines.extend(table.update(&event, at).iter().map(Row::to_string));
+        }
+        lines.extend(table.finish().as_ref().map(Row::to_string));
+        lines
+    }
+
+    #[test]
+    fn test_sparse() {
+        let lines = run(TableConfig {
+            columns: vec![Column::Rpm, Column::Mph, Column::Doors],
+            ..Default::default()
+        });
+        assert_eq!(
+            lines,
+            [
+                "timestamp,rpm,mph,doors",
+                "0.000000,800,,",
+                "0.100000,,,1",
+                "0.250000,,5,",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resample() {
+        let lines = run(TableConfig {
+            columns: vec![Column::Rpm, Column::Mph, Column::CabinC],
+            rows: Rows::Resample(Duration::from_millis(100)),
+            start: Some(Duration::from_millis(100)),
+            end: Some(Duration::from_millis(350)),
+        });
+        assert_eq!(
+            lines,
+            [
+                "timestamp,rpm,mph,cabin_c",
+                "0.100000,800,,",
+                "0.200000,800,,",
+                "0.300000,800,5,",
+            ]
+        );
+
+        // Without a range, the last row is at the last event.
+        let lines = run(TableConfig {
+            columns: vec![Column::CabinC],
+            rows: Rows::Resample(Duration::from_millis(210)),
+            ..Default::default()
+        });
+        assert_eq!(
+            lines,
+            ["timestamp,cabin_c", "0.000000,", "0.210000,", "0.420000,25"]
+        );
+    }
+
+    #[test]
+    fn test_column_names() {
+        for column in Column::ALL {
+            assert_eq!(column.to_string().parse::<Column>().unwrap(), column);
+        }
+        assert_eq!("AUX_VOLTS".parse::<Column>().unwrap(), Column::AuxVolts);
+        assert!("windows".parse::<Column>().is_err());
+    }
+}
@@ -0,0 +1,308 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! A [`TripComputer`] that segments driving into [`Trip`]s using [`Ignition`]
+//! transitions.
+//!
+//! A trip starts when the ignition leaves [`Ignition::Off`] (or
+//! [`Ignition::Kill`]) and ends when it returns. During a trip, time between
+//! events is counted as *moving* when the speed is non-zero and *idle* when
+//! the engine is on but the speed is zero (so a 4xE driving on electric power
+//! with the engine off still counts as moving).
+//!
+//! Time is supplied by the caller, so trips can be computed live or from a
+//! replayed log (see [`crate::candump`]).
+
+use std::{ops::RangeBounds, time::Duration};
+
+use crate::{
+    events::{
+        datetime::DateTime,
+        engine::{Engine, RPMs},
+        ignition::Ignition,
+        odometer::Odometer,
+    },
+    Event, Timestamp,
+};
+
+/// A single trip. Distance and speed are in legacy units to match the rest
+/// of the crate.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trip {
+    /// When the trip started.
+    pub started: Timestamp,
+    /// When the trip ended, or `None` if it's still in progress.
+    pub ended: Option<Timestamp>,
+    /// The Jeep's reported [`DateTime`] at the start, if known.
+    pub start_datetime: Option<DateTime>,
+    /// The Jeep's reported [`DateTime`] at the end (or latest), if known.
+    pub end_datetime: Option<DateTime>,
+    /// [`Odometer`] at the start, if known.
+    pub start_odometer: Option<Odometer>,
+    /// [`Odometer`] at the end (or latest), if known.
+    pub end_odometer: Option<Odometer>,
+    /// Time spent with a non-zero speed.
+    pub moving: Duration,
+    /// Time spent with the engine on but a zero speed.
+    pub idle: Duration,
+    /// Maximum speed.
+    pub max_mph: f32,
+    /// Integral of speed over moving time, in miles. Used for the average.
+    mph_seconds: f64,
+}
+
+impl Trip {
+    fn new(started: Timestamp) -> Self {
+        Self {
+            started,
+            ended: None,
+            start_datetime: None,
+            end_datetime: None,
+            start_odometer: None,
+            end_odometer: None,
+            moving: Duration::ZERO,
+            idle: Duration::ZERO,
+            max_mph: 0.0,
+            mph_seconds: 0.0,
+        }
+    }
+
+    /// Returns true if the trip has ended.
+    pub const fn is_finished(&self) -> bool {
+        self.ended.is_some()
+    }
+
+    /// Duration of the trip, as of `now` if it's still in progress.
+    pub fn duration(&self, now: Timestamp) -> Duration {
+        self.ended.unwrap_or(now).saturating_sub(self.started)
+    }
+
+    /// Distance from [`Odometer`] deltas in kilometers, if the odometer was
+    /// seen.
+    pub fn distance_km(&self) -> Option<f64> {
+        match (self.start_odometer, self.end_odometer) {
+            (Some(start), Some(end)) => Some(
+                Odometer::from_raw(end.raw().saturating_sub(start.raw()))
+                    .kilometers(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Distance from [`Odometer`] deltas in miles, if the odometer was seen.
+    pub fn distance_miles(&self) -> Option<f64> {
+        match (self.start_odometer, self.end_odometer) {
+            (Some(start), Some(end)) => Some(
+                Odometer::from_raw(end.raw().saturating_sub(start.raw()))
+                    .miles(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Average speed while moving, or `None` if the trip never moved.
+    pub fn average_mph(&self) -> Option<f32> {
+        if self.moving.is_zero() {
+            None
+        } else {
+            Some((self.mph_seconds / self.moving.as_secs_f64()) as f32)
+        }
+    }
+}
+
+/// Segments [`Event`]s into [`Trip`]s.
+#[derive(Debug, Clone, Default)]
+pub struct TripComputer {
+    trips: Vec<Trip>,
+    current: Option<Trip>,
+    last: Option<Timestamp>,
+    mph: f32,
+    engine_on: bool,
+    odometer: Option<Odometer>,
+    datetime: Option<DateTime>,
+}
+
+impl TripComputer {
+    /// Create a new [`TripComputer`] with no trips.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Update with an `event` received `at` some time. If the event ended a
+    /// trip, the finished [`Trip`] is returned.
+    pub fn update(&mut self, event: &Event, at: Timestamp) -> Option<&Trip> {
+        // Account for the time since the last event using the state before
+        // this one.
+        if let (Some(trip), Some(last)) = (&mut self.current, self.last) {
+            let elapsed = at.saturating_sub(last);
+            if self.mph > 0.0 {
+                trip.moving += elapsed;
+                trip.mph_seconds += f64::from(self.mph) * elapsed.as_secs_f64();
+            } else if self.engine_on {
+                trip.idle += elapsed;
+            }
+        }
+        self.last = Some(at);
+
+        match event {
+            Event::Ignition(Ignition::Off | Ignition::Kill) => {
+                if let Some(mut trip) = self.current.take() {
+                    trip.ended = Some(at);
+                    self.trips.push(trip);
+                    return self.trips.last();
+                }
+            }
+            Event::Ignition(_) if self.current.is_none() => {
+                let mut trip = Trip::new(at);
+                trip.start_odometer = self.odometer;
+                trip.end_odometer = self.odometer;
+                trip.start_datetime = self.datetime;
+                trip.end_datetime = self.datetime;
+                self.current = Some(trip);
+            }
+            Event::Engine(Engine::RPMs(rpms)) => {
+                self.engine_on = RPMs::engine_is_on(*rpms);
+            }
+            Event::Engine(Engine::MPH(mph) | Engine::ApproxMPH(mph)) => {
+                self.mph = f32::from(*mph);
+                if let Some(trip) = &mut self.current {
+                    trip.max_mph = trip.max_mph.max(self.mph);
+                }
+            }
+            Event::Odometer(odometer) => {
+                self.odometer = Some(*odometer);
+                if let Some(trip) = &mut self.current {
+                    trip.start_odometer.get_or_insert(*odometer);
+                    trip.end_odometer = Some(*odometer);
+                }
+            }
+            Event::DateTime(datetime) => {
+                self.datetime = Some(*datetime);
+                if let Some(trip) = &mut self.current {
+                    trip.start_datetime.get_or_insert(*datetime);
+                    trip.end_datetime = Some(*datetime);
+                }
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    /// The [`Trip`] in progress, if any.
+    pub fn current(&self) -> Option<&Trip> {
+        self.current.as_ref()
+    }
+
+    /// All finished [`Trip`]s, oldest first.
+    pub fn trips(&self) -> &[Trip] {
+        &self.trips
+    }
+
+    /// Finished [`Trip`]s that started within `range`.
+    pub fn trips_in<R>(&self, range: R) -> impl Iterator<Item = &Trip>
+    where
+        R: RangeBounds<Timestamp>,
+    {
+        self.trips
+            .iter()
+            .filter(move |trip| range.contains(&trip.started))
+    }
+
+    /// Take the finished [`Trip`]s, eg. to store them elsewhere.
+    pub fn take_trips(&mut self) -> Vec<Trip> {
+        std::mem::take(&mut self.trips)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{candump, events::engine::MPH};
+
+    fn replay(computer: &mut TripComputer, log: &str) {
+        for (at, event) in
+            candump::events(log.as_bytes()).filter_map(Result::ok)
+        {
+            computer.update(&event, at);
+        }
+    }
+
+    #[test]
+    fn test_trip() {
+        let mut computer = TripComputer::new();
+        let t = Duration::from_secs;
+
+        // Odometer before the trip starts is used as the start.
+        computer.update(&Event::Odometer(Odometer(100_000)), t(0));
+        computer.update(&Event::Ignition(Ignition::Acc), t(10));
+        assert!(computer.current().is_some());
+        computer.update(&Event::Engine(Engine::RPMs(RPMs(800))), t(10));
+        // Idle for 20 seconds
+        computer.update(&Event::Engine(Engine::MPH(MPH(30 * 200))), t(30));
+        computer.update(&Event::Engine(Engine::MPH(MPH(60 * 200))), t(40));
+        // Moving (30 for 10 seconds, then 60 for 10 seconds)
+        computer.update(&Event::Engine(Engine::MPH(MPH(0))), t(50));
+        computer.update(&Event::Odometer(Odometer(100_050)), t(55));
+        // Idle for 5 + 5 seconds
+        let trip = computer
+            .update(&Event::Ignition(Ignition::Off), t(60))
+            .unwrap()
+            .clone();
+
+        assert!(computer.current().is_none());
+        assert_eq!(trip.started, t(10));
+        assert_eq!(trip.duration(t(1000)), t(50));
+        assert_eq!(trip.moving, t(20));
+        assert_eq!(trip.idle, t(30));
+        assert_eq!(trip.max_mph, 60.0);
+        assert_eq!(trip.average_mph(), Some(45.0));
+        assert_eq!(trip.distance_km(), Some(0.5));
+        assert_eq!(computer.trips(), &[trip]);
+        assert_eq!(computer.trips_in(t(0)..t(10)).count(), 0);
+        assert_eq!(computer.trips_in(t(10)..).count(), 1);
+    }
+
+    #[test]
+    fn test_replay() {
+        let mut computer = TripComputer::new();
+        // Two trips, with the ignition off in between.
+        replay(
+            &mut computer,
+            "\
+            (1.000000) can0 122#05020000\n\
+            (2.000000) can0 3D2#000186A0\n\
+            (9.000000) can0 3D2#000186A5\n\
+            (10.000000) can0 122#00000000\n\
+            (11.000000) can0 122#00000000\n\
+            (20.000000) can0 122#05020000\n\
+            (30.000000) can0 122#03010000\n",
+        );
+
+        let trips = computer.take_trips();
+        assert_eq!(trips.len(), 2);
+        assert_eq!(trips[0].distance_km(), Some(0.05));
+        assert_eq!(trips[1].duration(Duration::ZERO), Duration::from_secs(10));
+        assert!(computer.trips().is_empty());
+    }
+}
@@ -0,0 +1,458 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Exact units for the values Jeep sends on the bus.
+//!
+//! Values are kept as the raw fixed-point integers from the frame (eg. 1/200
+//! mph for [`MPH`](crate::events::engine::MPH)) and converted with [`Ratio`]s,
+//! so converting never accumulates float error. Floats are only produced at
+//! the edges ([`Ratio::to_f64`]).
+//!
+//! Every [`Quantity`] can be displayed in either [`UnitSystem`], with the
+//! usual precision syntax (2 decimals by default):
+//!
+//! ```
+//! use jeep::{
+//!     events::engine::MPH,
+//!     units::{Quantity, Ratio, UnitSystem},
+//! };
+//!
+//! let speed = MPH::from(30);
+//! assert_eq!(speed.mph(), Ratio::from_integer(30));
+//! assert_eq!(speed.kph(), Ratio::new(150_876, 3125)); // 48.28032 exactly
+//! assert_eq!(speed.to_string(), "30.00 mph");
+//! assert_eq!(format!("{:.1}", speed.display(UnitSystem::Metric)), "48.3 km/h");
+//! ```
+//!
+//! With the `serde` feature, quantities serialize as both the raw value and
+//! the value in each [`UnitSystem`] (eg. `{"raw":6000,"kph":48.28032,
+//! "mph":30.0}`). Only `raw` is read back, so either form (or a bare raw
+//! value) deserializes.
+
+use std::{cmp::Ordering, fmt, ops};
+
+/// Kilometers in a mile (1.609344, exactly).
+pub const KM_PER_MILE: Ratio = Ratio::new(25_146, 15_625);
+
+/// An exact rational number, always in lowest terms with a positive
+/// denominator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Ratio {
+    numer: i64,
+    denom: i64,
+}
+
+impl Ratio {
+    /// Zero.
+    pub const ZERO: Ratio = Ratio::from_integer(0);
+
+    /// Create a new [`Ratio`], reduced to lowest terms.
+    ///
+    /// # Panics
+    /// * If `denom` is zero.
+    pub const fn new(numer: i64, denom: i64) -> Self {
+        assert!(denom != 0, "Ratio with a zero denominator");
+        let sign = if denom < 0 { -1 } else { 1 };
+        let gcd = gcd(numer.unsigned_abs(), denom.unsigned_abs()) as i64;
+        Self {
+            numer: sign * numer / gcd,
+            denom: sign * denom / gcd,
+        }
+    }
+
+    /// Create a new [`Ratio`] from an integer.
+    pub const fn from_integer(value: i64) -> Self {
+        Self {
+            numer: value,
+            denom: 1,
+        }
+    }
+
+    /// The numerator.
+    pub const fn numer(self) -> i64 {
+        self.numer
+    }
+
+    /// The (always positive) denominator.
+    pub const fn denom(self) -> i64 {
+        self.denom
+    }
+
+    /// Returns true if the value is a whole number.
+    pub const fn is_integer(self) -> bool {
+        self.denom == 1
+    }
+
+    /// The nearest [`f64`].
+    pub fn to_f64(self) -> f64 {
+        self.numer as f64 / self.denom as f64
+    }
+
+    /// The nearest [`f32`].
+    pub fn to_f32(self) -> f32 {
+        self.to_f64() as f32
+    }
+
+    /// The value times `10^decimals`, rounded half away from zero.
+    pub fn round_to(self, decimals: u32) -> i128 {
+        let scaled = i128::from(self.numer) * 10_i128.pow(decimals);
+        let denom = i128::from(self.denom);
+        let half = denom / 2;
+        if scaled < 0 {
+            (scaled - half) / denom
+        } else {
+            (scaled + half) / denom
+        }
+    }
+
+    /// Reduce an `i128` fraction back into a [`Ratio`].
+    ///
+    /// # Panics
+    /// * If the result doesn't fit in an `i64` fraction.
+    fn from_i128(numer: i128, denom: i128) -> Self {
+        let gcd = gcd_u128(numer.unsigned_abs(), denom.unsigned_abs()) as i128;
+        let sign = denom.signum();
+        Self::new(
+            i64::try_from(sign * numer / gcd).expect("Ratio overflow"),
+            i64::try_from(sign * denom / gcd).expect("Ratio overflow"),
+        )
+    }
+}
+
+const fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    if a == 0 {
+        1
+    } else {
+        a
+    }
+}
+
+fn gcd_u128(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a.max(1)
+}
+
+impl From<i64> for Ratio {
+    fn from(value: i64) -> Self {
+        Self::from_integer(value)
+    }
+}
+
+impl ops::Add for Ratio {
+    type Output = Ratio;
+
+    fn add(self, rhs: Ratio) -> Ratio {
+        let (a, b) = (i128::from(self.denom), i128::from(rhs.denom));
+        Ratio::from_i128(
+            i128::from(self.numer) * b + i128::from(rhs.numer) * a,
+            a * b,
+        )
+    }
+}
+
+impl ops::Sub for Ratio {
+    type Output = Ratio;
+
+    fn sub(self, rhs: Ratio) -> Ratio {
+        self + -rhs
+    }
+}
+
+impl ops::Neg for Ratio {
+    type Output = Ratio;
+
+    fn neg(self) -> Ratio {
+        Ratio {
+            numer: -self.numer,
+            denom: self.denom,
+        }
+    }
+}
+
+impl ops::Mul for Ratio {
+    type Output = Ratio;
+
+    fn mul(self, rhs: Ratio) -> Ratio {
+        Ratio::from_i128(
+            i128::from(self.numer) * i128::from(rhs.numer),
+            i128::from(self.denom) * i128::from(rhs.denom),
+        )
+    }
+}
+
+impl ops::Div for Ratio {
+    type Output = Ratio;
+
+    /// # Panics
+    /// * If `rhs` is zero.
+    fn div(self, rhs: Ratio) -> Ratio {
+        Ratio::from_i128(
+            i128::from(self.numer) * i128::from(rhs.denom),
+            i128::from(self.denom) * i128::from(rhs.numer),
+        )
+    }
+}
+
+impl PartialOrd for Ratio {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ratio {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (i128::from(self.numer) * i128::from(other.denom))
+            .cmp(&(i128::from(other.numer) * i128::from(self.denom)))
+    }
+}
+
+impl fmt::Display for Ratio {
+    /// Format as a decimal, rounded to the precision (default 2).
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let decimals = f.precision().unwrap_or(2);
+        let scaled = self.round_to(decimals as u32);
+        let unit = 10_u128.pow(decimals as u32);
+        let digits = match (scaled.unsigned_abs(), decimals) {
+            (abs, 0) => abs.to_string(),
+            (abs, _) => format!(
+                "{}.{:0decimals$}",
+                abs / unit,
+                abs % unit,
+                decimals = decimals
+            ),
+        };
+        f.pad_integral(scaled >= 0, "", &digits)
+    }
+}
+
+/// A system of units to display a [`Quantity`] in.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, derive_more::Display,
+)]
+pub enum UnitSystem {
+    /// km/h, °C, km, V.
+    #[default]
+    Metric,
+    /// mph, °F, mi, V.
+    Imperial,
+}
+
+/// A physical quantity decoded from the bus.
+pub trait Quantity: Copy {
+    /// The [`UnitSystem`] the quantity is sent in, which is also the one
+    /// used by its [`Display`](fmt::Display).
+    const NATIVE: UnitSystem;
+
+    /// The exact value in the units of a [`UnitSystem`].
+    fn value(self, system: UnitSystem) -> Ratio;
+
+    /// The symbol for the units of a [`UnitSystem`] (eg. `"km/h"`).
+    fn symbol(system: UnitSystem) -> &'static str;
+
+    /// Display the quantity in the units of a [`UnitSystem`].
+    fn display(self, system: UnitSystem) -> Displayed<Self> {
+        Displayed {
+            quantity: self,
+            system,
+        }
+    }
+}
+
+/// A [`Quantity`] displayed in a [`UnitSystem`]. See [`Quantity::display`].
+#[derive(Debug, Clone, Copy)]
+pub struct Displayed<Q> {
+    quantity: Q,
+    system: UnitSystem,
+}
+
+impl<Q: Quantity> fmt::Display for Displayed<Q> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let decimals = f.precision().unwrap_or(2);
+        write!(
+            f,
+            "{:.decimals$} {}",
+            self.quantity.value(self.system),
+            Q::symbol(self.system),
+            decimals = decimals
+        )
+    }
+}
+
+/// Implement `serde` for a [`Quantity`] with a `raw()` getter and a
+/// `from_raw` constructor, as `{"raw": .., metric: .., imperial: ..}`. The
+/// scaled values are ignored when deserializing and a bare raw value is also
+/// accepted.
+#[cfg(feature = "serde")]
+macro_rules! impl_quantity_serde {
+    ($ty:ty, $raw:ty, $metric:literal) => {
+        $crate::units::impl_quantity_serde!(@impl $ty, $raw, $metric);
+    };
+    ($ty:ty, $raw:ty, $metric:literal, $imperial:literal) => {
+        $crate::units::impl_quantity_serde!(@impl $ty, $raw, $metric, $imperial);
+    };
+    (@impl $ty:ty, $raw:ty, $($field:literal),+) => {
+        impl serde::Serialize for $ty {
+            fn serialize<S: serde::Serializer>(
+                &self,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error> {
+                use serde::ser::SerializeStruct;
+                use $crate::units::{Quantity, UnitSystem};
+
+                const SYSTEMS: [UnitSystem; 2] =
+                    [UnitSystem::Metric, UnitSystem::Imperial];
+                const FIELDS: &[&str] = &[$($field),+];
+
+                let mut state = serializer
+                    .serialize_struct(stringify!($ty), 1 + FIELDS.len())?;
+                state.serialize_field("raw", &self.raw())?;
+                for (field, system) in FIELDS.iter().zip(SYSTEMS) {
+                    state
+                        .serialize_field(field, &self.value(system).to_f64())?;
+                }
+                state.end()
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $ty {
+            fn deserialize<D: serde::Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Self, D::Error> {
+                #[derive(serde::Deserialize)]
+                #[serde(untagged)]
+                enum Repr {
+                    Raw($raw),
+                    Scaled { raw: $raw },
+                }
+
+                match <Repr as serde::Deserialize>::deserialize(deserializer)? {
+                    Repr::Raw(raw) | Repr::Scaled { raw } => {
+                        Ok(Self::from_raw(raw))
+                    }
+                }
+            }
+        }
+    };
+}
+#[cfg(feature = "serde")]
+pub(crate) use impl_quantity_serde;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{
+        battery::Aux, engine::KPH, engine::MPH, hvac::Temperature,
+        odometer::Odometer,
+    };
+
+    #[test]
+    fn test_ratio() {
+        assert_eq!(Ratio::new(6, -4), Ratio::new(-3, 2));
+        assert_eq!(Ratio::new(0, 5), Ratio::ZERO);
+        assert_eq!(Ratio::new(1, 3) + Ratio::new(1, 6), Ratio::new(1, 2));
+        assert_eq!(Ratio::new(1, 3) - Ratio::new(1, 2), Ratio::new(-1, 6));
+        assert_eq!(Ratio::new(2, 3) * Ratio::new(3, 4), Ratio::new(1, 2));
+        assert_eq!(Ratio::new(2, 3) / Ratio::new(4, 3), Ratio::new(1, 2));
+        assert!(Ratio::new(1, 3) < Ratio::new(1, 2));
+        assert!(KM_PER_MILE / KM_PER_MILE == Ratio::from_integer(1));
+        assert_eq!(KM_PER_MILE.to_f64(), 1.609344);
+    }
+
+    #[test]
+    fn test_ratio_display() {
+        assert_eq!(Ratio::new(1, 3).to_string(), "0.33");
+        assert_eq!(Ratio::new(2, 3).to_string(), "0.67");
+        assert_eq!(format!("{:.0}", Ratio::new(5, 2)), "3");
+        assert_eq!(format!("{:.1}", Ratio::new(-5, 4)), "-1.3");
+        assert_eq!(format!("{:.3}", Ratio::new(-1, 200)), "-0.005");
+        assert_eq!(format!("{:>6.1}", Ratio::from_integer(12)), "  12.0");
+        assert_eq!(format!("{:.4}", KM_PER_MILE), "1.6093");
+    }
+
+    #[test]
+    fn test_quantities() {
+        let speed = MPH::from_raw(6100); // 30.5 mph
+        assert_eq!(speed.mph(), Ratio::new(61, 2));
+        assert_eq!(KPH::from(speed).kph(), speed.mph() * KM_PER_MILE);
+        assert_eq!(MPH::from(KPH::from(speed)), speed);
+        assert_eq!(f32::from(speed), 30.5);
+        assert_eq!(speed.to_string(), "30.50 mph");
+        assert_eq!(KPH::from(speed).to_string(), "49.08 km/h");
+
+        let temperature = Temperature::from_raw(6500);
+        assert_eq!(temperature.celsius(), Ratio::from_integer(25));
+        assert_eq!(temperature.fahrenheit(), Ratio::from_integer(77));
+        assert_eq!(temperature.to_string(), "25.00 °C");
+        assert_eq!(
+            format!("{:.1}", temperature.display(UnitSystem::Imperial)),
+            "77.0 °F"
+        );
+        let cold = Temperature::from_raw(0);
+        assert_eq!(cold.celsius(), cold.fahrenheit());
+
+        // 625 miles, exactly.
+        let odometer = Odometer::from_raw(100_584);
+        assert_eq!(odometer.km(), Ratio::new(100_584, 100));
+        assert_eq!(odometer.mi(), Ratio::from_integer(625));
+        assert_eq!(odometer.to_string(), "1005.84 km");
+
+        let aux = Aux::from_raw([0, 0, 126, 0]);
+        assert_eq!(aux.voltage(), Ratio::new(63, 5));
+        assert_eq!(aux.to_string(), "12.60 V");
+        assert_eq!(aux.display(UnitSystem::Imperial).to_string(), "12.60 V");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let speed = MPH::from_raw(6000);
+        let json = serde_json::to_string(&speed).unwrap();
+        assert_eq!(json, r#"{"raw":6000,"kph":48.28032,"mph":30.0}"#);
+        assert_eq!(serde_json::from_str::<MPH>(&json).unwrap(), speed);
+        // A bare raw value is accepted too.
+        assert_eq!(serde_json::from_str::<MPH>("6000").unwrap(), speed);
+
+        let temperature = Temperature::from_raw(6500);
+        let json = serde_json::to_string(&temperature).unwrap();
+        assert_eq!(json, r#"{"raw":6500,"celsius":25.0,"fahrenheit":77.0}"#);
+        assert_eq!(
+            serde_json::from_str::<Temperature>(&json).unwrap(),
+            temperature
+        );
+
+        let aux = Aux::from_raw([1, 2, 126, 4]);
+        let json = serde_json::to_string(&aux).unwrap();
+        assert_eq!(json, r#"{"raw":[1,2,126,4],"volts":12.6}"#);
+        assert_eq!(serde_json::from_str::<Aux>(&json).unwrap(), aux);
+
+        let odometer = Odometer::from_raw(100);
+        let json = serde_json::to_string(&odometer).unwrap();
+        assert!(json.starts_with(r#"{"raw":100,"km":1.0,"miles":0.62137"#));
+        assert_eq!(serde_json::from_str::<Odometer>(&json).unwrap(), odometer);
+    }
+}
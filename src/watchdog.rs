@@ -0,0 +1,347 @@
+// MIT License
+
+// Copyright (c) 2023 Michael de Gans
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! A [`Watchdog`] that notices when periodic ids stop arriving (a silent
+//! node) and when the whole bus goes to sleep or wakes up.
+//!
+//! * An id is [`Alert::Silent`] when it misses [`WatchdogConfig::missed`]
+//!   periods while other frames keep arriving. If everything goes quiet at
+//!   once, that's the bus going to sleep, not a silent node.
+//! * The bus is [`BusState::Asleep`] after [`WatchdogConfig::sleep_after`]
+//!   without any frame, and [`BusState::Awake`] again on the next frame. If
+//!   that frame (or a later one) is a [`Bus::Wake`], its cause is kept.
+//!
+//! Silence can only be noticed by calling [`Watchdog::tick`] (or
+//! [`Watchdog::update`]) now and then, eg. whenever a read times out:
+//!
+//! ```
+//! use std::time::Duration;
+//! use jeep::{watchdog::{Alert, Watchdog}, Frame};
+//!
+//! let mut watchdog = Watchdog::default();
+//! let t = Duration::from_secs;
+//! let odometer = Frame::from_id_slice(0x3d2, &[0, 1, 0x86, 0xa0]).unwrap();
+//!
+//! watchdog.update(&odometer, t(0));
+//! assert!(watchdog.tick(t(4)).is_empty());
+//! // Time to shut down and save the battery.
+//! assert_eq!(watchdog.tick(t(5)), [Alert::Asleep { since: t(0) }]);
+//! ```
+
+use std::{collections::BTreeMap, fmt, time::Duration};
+
+use crate::{
+    events::{
+        bus::{Bus, Wake},
+        registry,
+    },
+    Frame, Timestamp,
+};
+
+/// Configuration for a [`Watchdog`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchdogConfig {
+    /// Number of missed periods after which an id is [`Alert::Silent`].
+    pub missed: u32,
+    /// How long without any frame before the bus is [`BusState::Asleep`].
+    pub sleep_after: Duration,
+    /// Expected period of each watched id. Defaults to every id in the
+    /// [`registry`] with a period.
+    pub periods: BTreeMap<u32, Duration>,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            missed: 3,
+            sleep_after: Duration::from_secs(5),
+            periods: registry::MESSAGES
+                .iter()
+                .filter_map(|info| Some((info.id, info.period?)))
+                .collect(),
+        }
+    }
+}
+
+/// Whether the bus is awake, as far as a [`Watchdog`] can tell.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, derive_more::Display)]
+pub enum BusState {
+    /// Nothing has been seen yet.
+    #[default]
+    Unknown,
+    /// Frames are arriving.
+    Awake,
+    /// No frames for [`WatchdogConfig::sleep_after`].
+    Asleep,
+}
+
+/// Something a [`Watchdog`] noticed.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Alert {
+    /// A periodic id stopped arriving while the rest of the bus didn't.
+    Silent {
+        /// The frame id.
+        id: u32,
+        /// When it was last seen.
+        last_seen: Timestamp,
+    },
+    /// A [`Alert::Silent`] id is arriving again.
+    Resumed {
+        /// The frame id.
+        id: u32,
+        /// How long it was missing for.
+        silent_for: Duration,
+    },
+    /// The bus went to sleep.
+    Asleep {
+        /// When the last frame was seen.
+        since: Timestamp,
+    },
+    /// The bus woke up.
+    Awake {
+        /// Why, if the waking frame was a [`Bus::Wake`].
+        cause: Option<Wake>,
+    },
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Alert::Silent { id, last_seen } => write!(
+                f,
+                "{id:#05X} silent since {:.3}",
+                last_seen.as_secs_f64()
+            ),
+            Alert::Resumed { id, silent_for } => write!(
+                f,
+                "{id:#05X} resumed after {:.3}s",
+                silent_for.as_secs_f64()
+            ),
+            Alert::Asleep { since } => {
+                write!(f, "bus asleep since {:.3}", since.as_secs_f64())
+            }
+            Alert::Awake { cause: Some(cause) } => {
+                write!(f, "bus awake ({cause})")
+            }
+            Alert::Awake { cause: None } => write!(f, "bus awake"),
+        }
+    }
+}
+
+/// What a [`Watchdog`] knows about a watched id.
+#[derive(Debug, Clone)]
+struct Node {
+    last_seen: Timestamp,
+    silent: bool,
+}
+
+/// Watches for silent nodes and bus sleep. See the [module docs](self).
+#[derive(Debug, Clone, Default)]
+pub struct Watchdog {
+    config: WatchdogConfig,
+    state: BusState,
+    cause: Option<Wake>,
+    /// When the watchdog was first updated or ticked.
+    started: Option<Timestamp>,
+    last_frame: Option<Timestamp>,
+    /// Watched ids seen since the bus woke up.
+    nodes: BTreeMap<u32, Node>,
+}
+
+impl Watchdog {
+    /// Create a new [`Watchdog`] with a [`WatchdogConfig`].
+    pub fn new(config: WatchdogConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// The current [`BusState`].
+    pub const fn state(&self) -> BusState {
+        self.state
+    }
+
+    /// The cause of the latest [`Bus::Wake`] since the bus woke up, if any.
+    pub const fn wake_cause(&self) -> Option<&Wake> {
+        self.cause.as_ref()
+    }
+
+    /// Ids that are currently [`Alert::Silent`].
+    pub fn silent(&self) -> impl Iterator<Item = u32> + '_ {
+        self.nodes
+            .iter()
+            .filter(|(_, node)| node.silent)
+            .map(|(id, _)| *id)
+    }
+
+    /// Update with a `frame` received `at` some time. Returns anything
+    /// noticed, oldest first.
+    pub fn update(&mut self, frame: &Frame, at: Timestamp) -> Vec<Alert> {
+        let mut alerts = self.tick(at);
+        self.last_frame = Some(at);
+
+        let cause = match Bus::try_from(frame.clone()) {
+            Ok(Bus::Wake(cause)) => Some(cause),
+            Err(_) => None,
+        };
+        if self.state != BusState::Awake {
+            self.state = BusState::Awake;
+            self.cause = cause.clone();
+            alerts.push(Alert::Awake { cause });
+        } else if cause.is_some() {
+            self.cause = cause;
+        }
+
+        if self.config.periods.contains_key(&frame.id()) {
+            let node = self.nodes.entry(frame.id()).or_insert(Node {
+                last_seen: at,
+                silent: false,
+            });
+            if node.silent {
+                node.silent = false;
+                alerts.push(Alert::Resumed {
+                    id: frame.id(),
+                    silent_for: at.saturating_sub(node.last_seen),
+                });
+            }
+            node.last_seen = at;
+        }
+
+        // Another frame arrived, so the bus is awake and anything overdue
+        // really is silent.
+        for (id, node) in self.nodes.iter_mut().filter(|(_, n)| !n.silent) {
+            let period = self.config.periods[id];
+            if at.saturating_sub(node.last_seen) > period * self.config.missed {
+                node.silent = true;
+                alerts.push(Alert::Silent {
+                    id: *id,
+                    last_seen: node.last_seen,
+                });
+            }
+        }
+
+        alerts
+    }
+
+    /// Check whether the bus went to sleep as of `now`. Returns
+    /// [`Alert::Asleep`] if it just did.
+    pub fn tick(&mut self, now: Timestamp) -> Vec<Alert> {
+        let started = *self.started.get_or_insert(now);
+        let since = self.last_frame.unwrap_or(started);
+        if self.state != BusState::Asleep
+            && now.saturating_sub(since) >= self.config.sleep_after
+        {
+            self.state = BusState::Asleep;
+            self.cause = None;
+            // Everything stopped, so nothing is silent on its own.
+            self.nodes.clear();
+            return vec![Alert::Asleep { since }];
+        }
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(id: u32, data: &[u8]) -> Frame {
+        Frame::from_id_slice(id, data).unwrap()
+    }
+
+    #[test]
+    fn test_silent_node() {
+        let mut watchdog = Watchdog::default();
+        let t = Duration::from_millis;
+        let odometer = frame(0x3d2, &[0, 1, 0x86, 0xa0]);
+        let filler = frame(0x7ff, &[0]);
+
+        let mut alerts = Vec::new();
+        for ms in (0..10_000).step_by(100) {
+            alerts.extend(watchdog.update(&filler, t(ms)));
+            // The odometer stops for 5 seconds.
+            if ms % 1000 == 0 && !(2000..7000).contains(&ms) {
+                alerts.extend(watchdog.update(&odometer, t(ms)));
+            }
+        }
+
+        assert_eq!(
+            alerts,
+            [
+                Alert::Awake { cause: None },
+                // 3 periods after 1s.
+                Alert::Silent {
+                    id: 0x3d2,
+                    last_seen: t(1000)
+                },
+                Alert::Resumed {
+                    id: 0x3d2,
+                    silent_for: t(6000)
+                },
+            ]
+        );
+        assert_eq!(watchdog.state(), BusState::Awake);
+        assert_eq!(watchdog.silent().count(), 0);
+    }
+
+    #[test]
+    fn test_sleep_wake() {
+        let mut watchdog = Watchdog::default();
+        let t = Duration::from_millis;
+        let odometer = frame(0x3d2, &[0, 1, 0x86, 0xa0]);
+        let plug = frame(0x401, &[0, 0, 0, 0, 0x01, 0x03, 0, 0]);
+
+        assert!(watchdog.tick(t(0)).is_empty());
+        assert_eq!(watchdog.state(), BusState::Unknown);
+        // Started while the bus was already asleep.
+        assert_eq!(watchdog.tick(t(5000)), [Alert::Asleep { since: t(0) }]);
+        assert!(watchdog.tick(t(6000)).is_empty());
+
+        assert_eq!(
+            watchdog.update(&plug, t(7000)),
+            [Alert::Awake {
+                cause: Some(Wake::Plug)
+            }]
+        );
+        assert_eq!(watchdog.wake_cause(), Some(&Wake::Plug));
+        assert!(watchdog.update(&odometer, t(7500)).is_empty());
+
+        // Everything stops: sleep, not a silent odometer.
+        assert!(watchdog.tick(t(12_000)).is_empty());
+        assert_eq!(
+            watchdog.tick(t(12_500)),
+            [Alert::Asleep { since: t(7500) }]
+        );
+        assert_eq!(watchdog.state(), BusState::Asleep);
+        assert_eq!(watchdog.wake_cause(), None);
+
+        // Waking up again doesn't report the odometer resuming.
+        assert_eq!(
+            watchdog.update(&odometer, t(20_000)),
+            [Alert::Awake { cause: None }]
+        );
+    }
+}